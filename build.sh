@@ -20,6 +20,11 @@ pushd linuxcnc-hal-sys
 cargo package
 popd
 
+# Check that derive macros package builds correctly
+pushd linuxcnc-hal-derive
+cargo package
+popd
+
 # Check that higher level package builds correctly
 pushd linuxcnc-hal
 cargo package
@@ -0,0 +1,701 @@
+//! Derive macros for the [`linuxcnc-hal`](https://crates.io/crates/linuxcnc-hal) crate.
+//!
+//! This crate should not be used directly. The macros are re-exported from `linuxcnc-hal`, so use
+//! `linuxcnc_hal::Resources` to derive the `Resources` trait.
+
+#![deny(missing_docs)]
+
+extern crate proc_macro;
+
+use heck::KebabCase;
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, spanned::Spanned, Data, DeriveInput, Error, Field, Fields, GenericArgument,
+    Lit, Meta, NestedMeta, PathArguments, Type,
+};
+
+/// Derive the `Resources` trait for a struct of pins and parameters
+///
+/// Each field must be an `InputPin`, `OutputPin`, `BidirectionalPin`, `InputPort`, `OutputPort`
+/// or `Parameter`, or a fixed size array of them. Fields are registered with the field name
+/// converted to `kebab-case`, so a field called `input_1` becomes a pin called `input-1`. Arrays
+/// are registered with `-#` appended to the name, so a field `inputs: [InputPin<f64>; 4]` becomes
+/// the pins `inputs-0` to `inputs-3`.
+///
+/// # Attributes
+///
+/// * `#[hal(name = "...")]` - Use the given name instead of the field name. For arrays, the name
+///   must contain a `#` placeholder for the index.
+/// * `#[hal(readonly)]` - Register a `Parameter` as read only
+///
+/// A single field of type `LoopStats` may be added to register the `loop-time`, `loop-tmax` and
+/// `overruns` parameters used by `HalComponent::run_at`. Its name can't be changed.
+///
+/// The `RegisterError` is `PinRegisterError` if the struct only contains pins,
+/// `ParameterRegisterError` if it only contains parameters and `ResourcesError` otherwise.
+#[proc_macro_derive(Resources, attributes(hal))]
+pub fn derive_resources(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand_resources(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Derive the `ProcessImage` trait for a struct of pins and parameters
+///
+/// Two structs of plain values are generated next to the derived struct, with the same visibility.
+/// For a struct called `Pins`:
+///
+/// * `PinsInputs` has a field for each `InputPin`, `BidirectionalPin` and read/write `Parameter`
+/// * `PinsOutputs` has a field for each `OutputPin`, `BidirectionalPin` and read only `Parameter`
+///
+/// Each field has the same name as the pin or parameter and holds its storage type, so an
+/// `InputPin<f64>` becomes an `f64` and `[OutputPin<bool>; 4]` becomes `[bool; 4]`. Ports are
+/// skipped. Both structs derive `Debug`, `Clone`, `Copy` and `PartialEq`.
+///
+/// This is usually derived alongside `Resources`, and accepts the same `#[hal(...)]` attributes.
+#[proc_macro_derive(ProcessImage, attributes(hal))]
+pub fn derive_process_image(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand_process_image(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// The kind of HAL resource a field holds
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum ResourceKind {
+    Pin,
+    Parameter,
+    ReadonlyParameter,
+    LoopStats,
+}
+
+/// A field to register with the component
+struct Resource<'a> {
+    field: &'a Field,
+    kind: ResourceKind,
+    name: String,
+    array: bool,
+
+    /// The pin or parameter type, e.g. `InputPin<f64>` for a field `[InputPin<f64>; 4]`
+    ty: &'a Type,
+
+    /// The name of the pin or parameter type, e.g. `InputPin`
+    type_name: String,
+}
+
+impl<'a> Resource<'a> {
+    fn from_field(field: &'a Field) -> Result<Self, Error> {
+        let ident = field
+            .ident
+            .as_ref()
+            .ok_or_else(|| Error::new(field.span(), "fields must be named"))?;
+
+        // Arrays of pins or parameters are registered with the array methods, which replace the `#`
+        // in the name with each index
+        let (ty, array) = match &field.ty {
+            Type::Array(array) => (&*array.elem, true),
+            ty => (ty, false),
+        };
+
+        let mut name = ident.to_string().to_kebab_case();
+        let mut readonly = false;
+        let mut renamed = false;
+
+        if array {
+            name.push_str("-#");
+        }
+
+        for attr in field.attrs.iter().filter(|attr| attr.path.is_ident("hal")) {
+            let list = match attr.parse_meta()? {
+                Meta::List(list) => list,
+                meta => return Err(Error::new(meta.span(), "expected #[hal(...)]")),
+            };
+
+            for item in list.nested.iter() {
+                match item {
+                    NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("name") => {
+                        match &nv.lit {
+                            Lit::Str(lit) => {
+                                name = lit.value();
+                                renamed = true;
+                            }
+                            lit => return Err(Error::new(lit.span(), "name must be a string")),
+                        }
+                    }
+                    NestedMeta::Meta(Meta::Path(path)) if path.is_ident("readonly") => {
+                        readonly = true
+                    }
+                    item => {
+                        return Err(Error::new(
+                            item.span(),
+                            "unknown attribute, expected `name = \"...\"` or `readonly`",
+                        ))
+                    }
+                }
+            }
+        }
+
+        if name.is_empty() {
+            return Err(Error::new(field.span(), "name must not be empty"));
+        }
+
+        let type_name = type_name(ty).unwrap_or_default();
+
+        let kind = match (type_name.as_str(), readonly) {
+            ("InputPin", false)
+            | ("OutputPin", false)
+            | ("BidirectionalPin", false)
+            | ("InputPort", false)
+            | ("OutputPort", false) => ResourceKind::Pin,
+            ("Parameter", false) => ResourceKind::Parameter,
+            ("Parameter", true) => ResourceKind::ReadonlyParameter,
+            ("LoopStats", false) if !array && !renamed => ResourceKind::LoopStats,
+            ("LoopStats", _) => {
+                return Err(Error::new(
+                    field.span(),
+                    "LoopStats can't be an array, renamed or marked #[hal(readonly)]",
+                ))
+            }
+            ("InputPin", true)
+            | ("OutputPin", true)
+            | ("BidirectionalPin", true)
+            | ("InputPort", true)
+            | ("OutputPort", true) => {
+                return Err(Error::new(
+                    field.span(),
+                    "#[hal(readonly)] can only be used on parameters",
+                ))
+            }
+            _ => {
+                return Err(Error::new(
+                    field.ty.span(),
+                    concat!(
+                        "field must be an InputPin, OutputPin, BidirectionalPin, InputPort, ",
+                        "OutputPort or Parameter, or an array of them"
+                    ),
+                ))
+            }
+        };
+
+        Ok(Self {
+            field,
+            kind,
+            name,
+            array,
+            ty,
+            type_name,
+        })
+    }
+
+    /// Whether this resource is read into the process image's inputs
+    fn is_input(&self) -> bool {
+        match self.kind {
+            ResourceKind::Pin => {
+                self.type_name == "InputPin" || self.type_name == "BidirectionalPin"
+            }
+            ResourceKind::Parameter => true,
+            ResourceKind::ReadonlyParameter | ResourceKind::LoopStats => false,
+        }
+    }
+
+    /// Whether this resource is written from the process image's outputs
+    fn is_output(&self) -> bool {
+        match self.kind {
+            ResourceKind::Pin => {
+                self.type_name == "OutputPin" || self.type_name == "BidirectionalPin"
+            }
+            ResourceKind::Parameter | ResourceKind::LoopStats => false,
+            ResourceKind::ReadonlyParameter => true,
+        }
+    }
+
+    /// Type of this resource's field in the process image, e.g. `[f64; 4]`
+    fn value_type(&self) -> Result<TokenStream2, Error> {
+        let storage = storage_type(self.ty).ok_or_else(|| {
+            Error::new(
+                self.ty.span(),
+                "expected a pin or parameter with a storage type, e.g. `InputPin<f64>`",
+            )
+        })?;
+
+        Ok(match &self.field.ty {
+            Type::Array(array) => {
+                let len = &array.len;
+
+                quote!([#storage; #len])
+            }
+            _ => quote!(#storage),
+        })
+    }
+
+    /// Expression that reads this resource's value from `self`
+    fn read(&self) -> TokenStream2 {
+        let ident = &self.field.ident;
+
+        if self.array {
+            quote!(#ident: ::core::array::from_fn(|i| self.#ident[i].get()))
+        } else {
+            quote!(#ident: self.#ident.get())
+        }
+    }
+
+    /// Statement that writes this resource's value from `outputs`
+    fn write(&self) -> TokenStream2 {
+        let ident = &self.field.ident;
+
+        if self.array {
+            quote! {
+                for (resource, value) in self.#ident.iter().zip(outputs.#ident.iter()) {
+                    resource.set(*value);
+                }
+            }
+        } else {
+            quote!(self.#ident.set(outputs.#ident);)
+        }
+    }
+
+    /// Expression that registers this resource, returning early on error
+    fn register(&self) -> TokenStream2 {
+        let ident = &self.field.ident;
+        let ty = &self.field.ty;
+        let name = &self.name;
+
+        match (self.kind, self.array) {
+            (ResourceKind::Pin, false) => quote!(#ident: comp.register_pin::<#ty>(#name)?),
+            (ResourceKind::Parameter, false) => {
+                quote!(#ident: comp.register_parameter::<#ty>(#name)?)
+            }
+            (ResourceKind::ReadonlyParameter, false) => {
+                quote!(#ident: comp.register_readonly_parameter::<#ty>(#name)?)
+            }
+            // The array length is inferred from the field type
+            (ResourceKind::Pin, true) => quote!(#ident: comp.register_pin_array(#name)?),
+            (ResourceKind::Parameter, true) => {
+                quote!(#ident: comp.register_parameter_array(#name)?)
+            }
+            (ResourceKind::ReadonlyParameter, true) => {
+                quote!(#ident: comp.register_readonly_parameter_array(#name)?)
+            }
+            (ResourceKind::LoopStats, _) => quote!(#ident: comp.register_loop_stats()?),
+        }
+    }
+}
+
+/// Get the name of the last segment of a type path, e.g. `InputPin` for `hal_pin::InputPin<f64>`
+fn type_name(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string()),
+        _ => None,
+    }
+}
+
+/// Get the first type argument of a type path, e.g. `f64` for `InputPin<f64>`
+fn storage_type(ty: &Type) -> Option<&Type> {
+    let segment = match ty {
+        Type::Path(path) if path.qself.is_none() => path.path.segments.last()?,
+        _ => return None,
+    };
+
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => args.args.iter().find_map(|arg| match arg {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        }),
+        _ => None,
+    }
+}
+
+/// Pick the narrowest error type that covers every resource
+fn register_error(resources: &[Resource]) -> TokenStream2 {
+    let has_pins = resources.iter().any(|r| r.kind == ResourceKind::Pin);
+    let has_parameters = resources.iter().any(|r| r.kind != ResourceKind::Pin);
+
+    match (has_pins, has_parameters) {
+        (true, false) => quote!(::linuxcnc_hal::error::PinRegisterError),
+        (false, true) => quote!(::linuxcnc_hal::error::ParameterRegisterError),
+        _ => quote!(::linuxcnc_hal::error::ResourcesError),
+    }
+}
+
+/// Parse every field of a struct as a resource
+fn resources<'a>(input: &'a DeriveInput, derive: &str) -> Result<Vec<Resource<'a>>, Error> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            Fields::Unit => {
+                return Err(Error::new(
+                    Span::call_site(),
+                    format!("{} cannot be derived for unit structs", derive),
+                ))
+            }
+            Fields::Unnamed(fields) => {
+                return Err(Error::new(
+                    fields.span(),
+                    format!(
+                        "{} can only be derived for structs with named fields",
+                        derive
+                    ),
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new(
+                Span::call_site(),
+                format!("{} can only be derived for structs", derive),
+            ))
+        }
+    };
+
+    fields.iter().map(Resource::from_field).collect()
+}
+
+fn expand_resources(input: DeriveInput) -> Result<TokenStream2, Error> {
+    let resources = resources(&input, "Resources")?;
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let register_error = register_error(&resources);
+    let registrations = resources.iter().map(Resource::register);
+
+    let mut loop_stats = resources
+        .iter()
+        .filter(|r| r.kind == ResourceKind::LoopStats);
+
+    let loop_stats = match (loop_stats.next(), loop_stats.next()) {
+        (_, Some(duplicate)) => {
+            return Err(Error::new(
+                duplicate.field.span(),
+                "only one LoopStats field is allowed",
+            ))
+        }
+        (Some(stats), None) => {
+            let ident = &stats.field.ident;
+
+            Some(quote! {
+                fn loop_stats(
+                    &self,
+                ) -> ::core::option::Option<&::linuxcnc_hal::run_loop::LoopStats> {
+                    ::core::option::Option::Some(&self.#ident)
+                }
+            })
+        }
+        (None, None) => None,
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::linuxcnc_hal::Resources for #ident #ty_generics #where_clause {
+            type RegisterError = #register_error;
+
+            fn register_resources(
+                comp: &::linuxcnc_hal::RegisterResources,
+            ) -> ::core::result::Result<Self, Self::RegisterError> {
+                ::core::result::Result::Ok(Self {
+                    #(#registrations,)*
+                })
+            }
+
+            #loop_stats
+        }
+    })
+}
+
+fn expand_process_image(input: DeriveInput) -> Result<TokenStream2, Error> {
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            input.generics.span(),
+            "ProcessImage cannot be derived for generic structs",
+        ));
+    }
+
+    let resources = resources(&input, "ProcessImage")?;
+
+    let ident = &input.ident;
+    let vis = &input.vis;
+    let inputs_ident = format_ident!("{}Inputs", ident);
+    let outputs_ident = format_ident!("{}Outputs", ident);
+
+    let inputs = resources
+        .iter()
+        .filter(|r| r.is_input())
+        .collect::<Vec<_>>();
+    let outputs = resources
+        .iter()
+        .filter(|r| r.is_output())
+        .collect::<Vec<_>>();
+
+    let input_fields = fields(&inputs)?;
+    let output_fields = fields(&outputs)?;
+    let reads = inputs.iter().map(|r| r.read());
+    let writes = outputs.iter().map(|r| r.write());
+
+    let inputs_doc = format!("Values of the inputs of [`{}`], read in one go", ident);
+    let outputs_doc = format!("Values of the outputs of [`{}`], written in one go", ident);
+
+    Ok(quote! {
+        #[doc = #inputs_doc]
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        #vis struct #inputs_ident {
+            #(#input_fields,)*
+        }
+
+        #[doc = #outputs_doc]
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        #vis struct #outputs_ident {
+            #(#output_fields,)*
+        }
+
+        impl ::linuxcnc_hal::ProcessImage for #ident {
+            type Inputs = #inputs_ident;
+            type Outputs = #outputs_ident;
+
+            fn snapshot_inputs(&self) -> Self::Inputs {
+                #[allow(unused_imports)]
+                use ::linuxcnc_hal::prelude::*;
+
+                #inputs_ident {
+                    #(#reads,)*
+                }
+            }
+
+            #[allow(unused_variables)]
+            fn commit_outputs(&self, outputs: Self::Outputs) {
+                #[allow(unused_imports)]
+                use ::linuxcnc_hal::prelude::*;
+
+                #(#writes)*
+            }
+        }
+    })
+}
+
+/// Fields of a process image struct, copying the docs from each resource
+fn fields(resources: &[&Resource]) -> Result<Vec<TokenStream2>, Error> {
+    resources
+        .iter()
+        .map(|r| {
+            let ident = &r.field.ident;
+            let ty = r.value_type()?;
+            let docs = r
+                .field
+                .attrs
+                .iter()
+                .filter(|attr| attr.path.is_ident("doc"));
+
+            Ok(quote! {
+                #(#docs)*
+                pub #ident: #ty
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    fn resources(input: DeriveInput) -> Result<Vec<(String, ResourceKind)>, Error> {
+        match input.data {
+            Data::Struct(data) => data
+                .fields
+                .iter()
+                .map(|field| Resource::from_field(field).map(|r| (r.name, r.kind)))
+                .collect(),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn field_names() {
+        let input: DeriveInput = parse_quote! {
+            struct Pins {
+                input_1: InputPin<f64>,
+                #[hal(name = "custom.name")]
+                output: hal_pin::OutputPin<bool>,
+                #[hal(readonly)]
+                max_speed: Parameter<f64>,
+                #[hal(name = "rw", readonly)]
+                other: Parameter<u32>,
+                inputs: [InputPin<f64>; 4],
+                rx: InputPort,
+                #[hal(name = "limit.##")]
+                limits: [Parameter<u32>; 12],
+            }
+        };
+
+        assert_eq!(
+            resources(input).unwrap(),
+            vec![
+                ("input-1".to_string(), ResourceKind::Pin),
+                ("custom.name".to_string(), ResourceKind::Pin),
+                ("max-speed".to_string(), ResourceKind::ReadonlyParameter),
+                ("rw".to_string(), ResourceKind::ReadonlyParameter),
+                ("inputs-#".to_string(), ResourceKind::Pin),
+                ("rx".to_string(), ResourceKind::Pin),
+                ("limit.##".to_string(), ResourceKind::Parameter),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_fields() {
+        let readonly_pin: DeriveInput = parse_quote! {
+            struct Pins {
+                #[hal(readonly)]
+                input: InputPin<f64>,
+            }
+        };
+
+        let unknown_type: DeriveInput = parse_quote! {
+            struct Pins {
+                count: u32,
+            }
+        };
+
+        let unknown_attribute: DeriveInput = parse_quote! {
+            struct Pins {
+                #[hal(skip)]
+                input: InputPin<f64>,
+            }
+        };
+
+        assert!(resources(readonly_pin).is_err());
+        assert!(resources(unknown_type).is_err());
+        assert!(resources(unknown_attribute).is_err());
+    }
+
+    #[test]
+    fn loop_stats() {
+        let input: DeriveInput = parse_quote! {
+            struct Pins {
+                input: InputPin<f64>,
+                stats: run_loop::LoopStats,
+            }
+        };
+
+        let renamed: DeriveInput = parse_quote! {
+            struct Pins {
+                #[hal(name = "stats")]
+                stats: LoopStats,
+            }
+        };
+
+        let array: DeriveInput = parse_quote! {
+            struct Pins {
+                stats: [LoopStats; 2],
+            }
+        };
+
+        let duplicate: DeriveInput = parse_quote! {
+            struct Pins {
+                first: LoopStats,
+                second: LoopStats,
+            }
+        };
+
+        assert_eq!(
+            resources(input).unwrap(),
+            vec![
+                ("input".to_string(), ResourceKind::Pin),
+                ("stats".to_string(), ResourceKind::LoopStats),
+            ]
+        );
+        assert!(resources(renamed).is_err());
+        assert!(resources(array).is_err());
+        assert!(expand_resources(duplicate).is_err());
+    }
+
+    #[test]
+    fn error_type() {
+        let error = |input: DeriveInput| {
+            let input = match input.data {
+                Data::Struct(data) => data.fields,
+                _ => unreachable!(),
+            };
+
+            let resources = input
+                .iter()
+                .map(|field| Resource::from_field(field).unwrap())
+                .collect::<Vec<_>>();
+
+            register_error(&resources).to_string()
+        };
+
+        assert!(error(parse_quote!(
+            struct A {
+                a: InputPin<f64>,
+            }
+        ))
+        .ends_with("PinRegisterError"));
+        assert!(error(parse_quote!(
+            struct A {
+                #[hal(readonly)]
+                a: Parameter<f64>,
+            }
+        ))
+        .ends_with("ParameterRegisterError"));
+        assert!(error(parse_quote!(
+            struct A {
+                a: InputPin<f64>,
+                b: Parameter<f64>,
+            }
+        ))
+        .ends_with("ResourcesError"));
+    }
+
+    #[test]
+    fn process_image() {
+        let input: DeriveInput = parse_quote! {
+            struct Pins {
+                input: InputPin<f64>,
+                outputs: [OutputPin<bool>; 4],
+                bidir: BidirectionalPin<u32>,
+                gain: Parameter<f64>,
+                #[hal(readonly)]
+                status: Parameter<i32>,
+                rx: InputPort,
+            }
+        };
+
+        let fields = match &input.data {
+            Data::Struct(data) => &data.fields,
+            _ => unreachable!(),
+        };
+
+        let image = fields
+            .iter()
+            .map(|field| {
+                let r = Resource::from_field(field).unwrap();
+                let ty = r.value_type().map(|ty| ty.to_string()).ok();
+
+                (r.name.clone(), r.is_input(), r.is_output(), ty)
+            })
+            .collect::<Vec<_>>();
+
+        let entry = |name: &str, input, output, ty: Option<&str>| {
+            (name.to_string(), input, output, ty.map(str::to_string))
+        };
+
+        assert_eq!(
+            image,
+            vec![
+                entry("input", true, false, Some("f64")),
+                entry("outputs-#", false, true, Some("[bool ; 4]")),
+                entry("bidir", true, true, Some("u32")),
+                entry("gain", true, false, Some("f64")),
+                entry("status", false, true, Some("i32")),
+                entry("rx", false, false, None),
+            ]
+        );
+    }
+}
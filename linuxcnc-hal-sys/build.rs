@@ -1,15 +1,27 @@
 extern crate bindgen;
 
 use std::env;
-// use std::fs;
+use std::fs;
 use std::path::PathBuf;
 
 fn main() {
     println!("cargo:rerun-if-changed=wrapper.h");
+
+    println!("cargo:rerun-if-env-changed=LINUXCNC_VERSION");
+
+    // The mock HAL is written in Rust, so there is nothing to generate bindings for. It implements
+    // the LinuxCNC 2.9 API.
+    if env::var_os("CARGO_FEATURE_MOCK").is_some() {
+        emit_version((2, 9));
+
+        return;
+    }
     // println!("cargo:rerun-if-changed=patch/config.h");
 
     let linuxcnc_root = env::var("LINUXCNC_SRC").expect("LINUXCNC_SRC env var must be set and pointing to the root of the LinuxCNC source Git repository");
 
+    emit_version(linuxcnc_version(&linuxcnc_root));
+
     // fs::copy("patch/config.h", "linuxcnc-src/src/config.h")
     //     .expect("Failed to copy config patch file");
 
@@ -55,3 +67,40 @@ fn main() {
     //     .warnings(false)
     //     .compile("linuxcnchal");
 }
+
+/// Get the `(major, minor)` version of the LinuxCNC source code
+///
+/// This is read from the `VERSION` file in the root of the LinuxCNC source tree, or can be
+/// overridden with the `LINUXCNC_VERSION` env var, e.g. `LINUXCNC_VERSION=2.9`.
+fn linuxcnc_version(linuxcnc_root: &str) -> (u32, u32) {
+    let version = env::var("LINUXCNC_VERSION").unwrap_or_else(|_| {
+        let path = PathBuf::from(linuxcnc_root).join("VERSION");
+
+        println!("cargo:rerun-if-changed={}", path.display());
+
+        fs::read_to_string(&path).unwrap_or_else(|e| {
+            panic!(
+                "Failed to read LinuxCNC version from {}: {}. Set the LINUXCNC_VERSION env var to \
+                 override.",
+                path.display(),
+                e
+            )
+        })
+    });
+
+    // Versions look like `2.8.1` or `2.9.0~pre0`
+    let mut parts = version
+        .trim()
+        .split(|c: char| !c.is_ascii_digit())
+        .map(|part| part.parse::<u32>());
+
+    match (parts.next(), parts.next()) {
+        (Some(Ok(major)), Some(Ok(minor))) => (major, minor),
+        _ => panic!("Failed to parse LinuxCNC version {:?}", version),
+    }
+}
+
+/// Pass the LinuxCNC version to dependent crates' build scripts as `DEP_LINUXCNC_HAL_VERSION`
+fn emit_version((major, minor): (u32, u32)) {
+    println!("cargo:version={}.{}", major, minor);
+}
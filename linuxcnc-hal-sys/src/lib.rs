@@ -27,6 +27,11 @@
 //! **The version of the LinuxCNC sources must match the LinuxCNC version used in the machine
 //! control.**
 //!
+//! The LinuxCNC version is read from the `VERSION` file in the root of the source code and is
+//! passed on to `linuxcnc-hal` so it can enable APIs that depend on it, like 64 bit pins in
+//! LinuxCNC 2.9. It can be overridden by setting the `LINUXCNC_VERSION` environment variable,
+//! e.g. `LINUXCNC_VERSION=2.9`.
+//!
 //! ```bash
 //! # Clone LinuxCNC source code into linuxcnc/
 //! git clone https://github.com/LinuxCNC/linuxcnc.git
@@ -108,7 +113,8 @@
 //!
 //!     println!("ID {}", id);
 //!
-//!     let signals = Signals::new(&[signal_hook::SIGTERM, signal_hook::SIGINT]).unwrap();
+//!     let mut signals =
+//!         Signals::new(&[signal_hook::consts::SIGTERM, signal_hook::consts::SIGINT]).unwrap();
 //!
 //!     let storage = hal_malloc(mem::size_of::<*mut f64>() as i64) as *mut *mut f64;
 //!
@@ -130,7 +136,9 @@
 //!     println!("Ready {}", ret);
 //!
 //!     while !signals.pending().any(|signal| match signal {
-//!         signal_hook::SIGTERM | signal_hook::SIGINT | signal_hook::SIGKILL => true,
+//!         signal_hook::consts::SIGTERM
+//!         | signal_hook::consts::SIGINT
+//!         | signal_hook::consts::SIGKILL => true,
 //!         _ => false,
 //!     }) {
 //!         println!("Input {:?}", **storage);
@@ -166,7 +174,8 @@
 //!
 //!     println!("Component registered with ID {}", component_id);
 //!
-//!     let signals = Signals::new(&[signal_hook::SIGTERM, signal_hook::SIGINT]).unwrap();
+//!     let mut signals =
+//!         Signals::new(&[signal_hook::consts::SIGTERM, signal_hook::consts::SIGINT]).unwrap();
 //!
 //!     let storage = hal_malloc(mem::size_of::<*mut f64>() as i64) as *mut *mut f64;
 //!
@@ -204,7 +213,9 @@
 //!     }
 //!
 //!     while !signals.pending().any(|signal| match signal {
-//!         signal_hook::SIGTERM | signal_hook::SIGINT | signal_hook::SIGKILL => true,
+//!         signal_hook::consts::SIGTERM
+//!         | signal_hook::consts::SIGINT
+//!         | signal_hook::consts::SIGKILL => true,
 //!         _ => false,
 //!     }) {
 //!         println!("Input {:?}", **storage);
@@ -213,6 +224,18 @@
 //!     }
 //! }
 //! ```
+//!
+//! # Testing without LinuxCNC
+//!
+//! Enabling the `mock` feature replaces the generated bindings with an in-process, pure Rust
+//! implementation of the HAL. The LinuxCNC sources are not required when this feature is enabled.
+//! See the [`mock`] module for more details.
+//!
+//! ```toml
+//! [dev-dependencies]
+//! linuxcnc-hal-sys = { version = "0.2.0", features = [ "mock" ] }
+//! ```
+//!
 //! [`linuxcnc-hal`]: https://docs.rs/linuxcnc-hal
 //! [`bindgen`]: https://docs.rs/bindgen
 //! [`signal_hook`]: https://docs.rs/signal_hook
@@ -224,4 +247,11 @@
 
 mod check_readme;
 
+#[cfg(feature = "mock")]
+pub mod mock;
+
+#[cfg(feature = "mock")]
+pub use mock::ffi::*;
+
+#[cfg(not(feature = "mock"))]
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
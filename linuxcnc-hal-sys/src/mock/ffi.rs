@@ -0,0 +1,789 @@
+//! Pure Rust replacements for the generated LinuxCNC bindings
+//!
+//! Only the subset of the HAL and RTAPI used by this workspace is implemented. Names, types and
+//! signatures match what `bindgen` generates so code written against the real bindings compiles
+//! unchanged.
+
+// These functions mirror the generated `extern "C"` bindings which don't carry any docs either.
+#![allow(clippy::missing_safety_doc)]
+
+use crate::mock::{with_hal, DataCell, Funct, Param, Pin, PortBuffer, Shared, Signal, Thread};
+use std::{
+    alloc::{alloc_zeroed, Layout},
+    ffi::CStr,
+    os::raw::{c_char, c_int, c_long, c_uint, c_ulong, c_void},
+};
+
+/// Operation not permitted
+pub const EPERM: u32 = 1;
+/// Out of memory
+pub const ENOMEM: u32 = 12;
+/// File exists
+pub const EEXIST: u32 = 17;
+/// Invalid argument
+pub const EINVAL: u32 = 22;
+
+/// Maximum length of a component, pin, parameter or function name
+pub const HAL_NAME_LEN: u32 = 47;
+
+pub type hal_bit_t = bool;
+pub type hal_float_t = f64;
+pub type hal_s32_t = i32;
+pub type hal_u32_t = u32;
+pub type hal_s64_t = i64;
+pub type hal_u64_t = u64;
+pub type hal_port_t = c_int;
+
+pub type hal_type_t = i32;
+pub const hal_type_t_HAL_TYPE_UNSPECIFIED: hal_type_t = -1;
+pub const hal_type_t_HAL_BIT: hal_type_t = 1;
+pub const hal_type_t_HAL_FLOAT: hal_type_t = 2;
+pub const hal_type_t_HAL_S32: hal_type_t = 3;
+pub const hal_type_t_HAL_U32: hal_type_t = 4;
+pub const hal_type_t_HAL_PORT: hal_type_t = 5;
+pub const hal_type_t_HAL_S64: hal_type_t = 6;
+pub const hal_type_t_HAL_U64: hal_type_t = 7;
+
+pub type hal_pin_dir_t = i32;
+pub const hal_pin_dir_t_HAL_DIR_UNSPECIFIED: hal_pin_dir_t = -1;
+pub const hal_pin_dir_t_HAL_IN: hal_pin_dir_t = 16;
+pub const hal_pin_dir_t_HAL_OUT: hal_pin_dir_t = 32;
+pub const hal_pin_dir_t_HAL_IO: hal_pin_dir_t = 48;
+
+pub type hal_param_dir_t = u32;
+pub const hal_param_dir_t_HAL_RO: hal_param_dir_t = 64;
+pub const hal_param_dir_t_HAL_RW: hal_param_dir_t = 192;
+
+pub type msg_level_t = u32;
+pub const msg_level_t_RTAPI_MSG_NONE: msg_level_t = 0;
+pub const msg_level_t_RTAPI_MSG_ERR: msg_level_t = 1;
+pub const msg_level_t_RTAPI_MSG_WARN: msg_level_t = 2;
+pub const msg_level_t_RTAPI_MSG_INFO: msg_level_t = 3;
+pub const msg_level_t_RTAPI_MSG_DBG: msg_level_t = 4;
+pub const msg_level_t_RTAPI_MSG_ALL: msg_level_t = 5;
+
+/// Convert a C string name into an owned string, checking it against [`HAL_NAME_LEN`]
+unsafe fn name_from_ptr(name: *const c_char) -> Option<String> {
+    if name.is_null() {
+        return None;
+    }
+
+    let name = CStr::from_ptr(name).to_string_lossy().into_owned();
+
+    if name.len() > HAL_NAME_LEN as usize {
+        log::error!("HAL: ERROR: name '{}' is too long", name);
+
+        None
+    } else {
+        Some(name)
+    }
+}
+
+pub unsafe extern "C" fn hal_init(name: *const c_char) -> c_int {
+    let name = match name_from_ptr(name) {
+        Some(name) => name,
+        None => return -(EINVAL as c_int),
+    };
+
+    with_hal(|hal| hal.add_component(name))
+}
+
+pub unsafe extern "C" fn hal_exit(comp_id: c_int) -> c_int {
+    with_hal(|hal| hal.remove_component(comp_id))
+}
+
+pub unsafe extern "C" fn hal_ready(comp_id: c_int) -> c_int {
+    with_hal(|hal| match hal.components.get_mut(&comp_id) {
+        Some(comp) if !comp.ready => {
+            comp.ready = true;
+
+            0
+        }
+        _ => -(EINVAL as c_int),
+    })
+}
+
+/// Allocate zeroed memory
+///
+/// Like the real HAL shared memory allocator, memory is never returned to the system.
+pub unsafe extern "C" fn hal_malloc(size: c_long) -> *mut c_void {
+    if size <= 0 {
+        return std::ptr::null_mut();
+    }
+
+    match Layout::from_size_align(size as usize, 8) {
+        Ok(layout) => alloc_zeroed(layout).cast(),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+unsafe fn pin_new(
+    name: *const c_char,
+    ty: hal_type_t,
+    dir: hal_pin_dir_t,
+    data_ptr_addr: *mut *mut c_void,
+    comp_id: c_int,
+) -> c_int {
+    let name = match name_from_ptr(name) {
+        Some(name) => name,
+        None => return -(EINVAL as c_int),
+    };
+
+    if data_ptr_addr.is_null()
+        || !matches!(
+            dir,
+            hal_pin_dir_t_HAL_IN | hal_pin_dir_t_HAL_OUT | hal_pin_dir_t_HAL_IO
+        )
+    {
+        return -(EINVAL as c_int);
+    }
+
+    with_hal(|hal| {
+        let ret = hal.can_add(comp_id, &name, &hal.pins);
+
+        if ret != 0 {
+            return ret;
+        }
+
+        let pin = Pin::new(comp_id, ty, dir, Shared(data_ptr_addr));
+
+        hal.pins.insert(name, pin);
+
+        0
+    })
+}
+
+pub unsafe extern "C" fn hal_pin_bit_new(
+    name: *const c_char,
+    dir: hal_pin_dir_t,
+    data_ptr_addr: *mut *mut hal_bit_t,
+    comp_id: c_int,
+) -> c_int {
+    pin_new(name, hal_type_t_HAL_BIT, dir, data_ptr_addr.cast(), comp_id)
+}
+
+pub unsafe extern "C" fn hal_pin_float_new(
+    name: *const c_char,
+    dir: hal_pin_dir_t,
+    data_ptr_addr: *mut *mut hal_float_t,
+    comp_id: c_int,
+) -> c_int {
+    pin_new(
+        name,
+        hal_type_t_HAL_FLOAT,
+        dir,
+        data_ptr_addr.cast(),
+        comp_id,
+    )
+}
+
+pub unsafe extern "C" fn hal_pin_u32_new(
+    name: *const c_char,
+    dir: hal_pin_dir_t,
+    data_ptr_addr: *mut *mut hal_u32_t,
+    comp_id: c_int,
+) -> c_int {
+    pin_new(name, hal_type_t_HAL_U32, dir, data_ptr_addr.cast(), comp_id)
+}
+
+pub unsafe extern "C" fn hal_pin_s32_new(
+    name: *const c_char,
+    dir: hal_pin_dir_t,
+    data_ptr_addr: *mut *mut hal_s32_t,
+    comp_id: c_int,
+) -> c_int {
+    pin_new(name, hal_type_t_HAL_S32, dir, data_ptr_addr.cast(), comp_id)
+}
+
+pub unsafe extern "C" fn hal_pin_s64_new(
+    name: *const c_char,
+    dir: hal_pin_dir_t,
+    data_ptr_addr: *mut *mut hal_s64_t,
+    comp_id: c_int,
+) -> c_int {
+    pin_new(name, hal_type_t_HAL_S64, dir, data_ptr_addr.cast(), comp_id)
+}
+
+pub unsafe extern "C" fn hal_pin_u64_new(
+    name: *const c_char,
+    dir: hal_pin_dir_t,
+    data_ptr_addr: *mut *mut hal_u64_t,
+    comp_id: c_int,
+) -> c_int {
+    pin_new(name, hal_type_t_HAL_U64, dir, data_ptr_addr.cast(), comp_id)
+}
+
+pub unsafe extern "C" fn hal_pin_port_new(
+    name: *const c_char,
+    dir: hal_pin_dir_t,
+    data_ptr_addr: *mut *mut hal_port_t,
+    comp_id: c_int,
+) -> c_int {
+    // Ports can only be read by one component and written by one other
+    if dir == hal_pin_dir_t_HAL_IO {
+        return -(EINVAL as c_int);
+    }
+
+    pin_new(
+        name,
+        hal_type_t_HAL_PORT,
+        dir,
+        data_ptr_addr.cast(),
+        comp_id,
+    )
+}
+
+/// Run a closure with the buffer of the given port, or return `default` if the port is not
+/// connected to a buffer
+fn with_port<T>(port: hal_port_t, default: T, f: impl FnOnce(&mut PortBuffer) -> T) -> T {
+    with_hal(|hal| match hal.ports.get_mut(&port) {
+        Some(buffer) => f(buffer),
+        None => default,
+    })
+}
+
+pub unsafe extern "C" fn hal_port_read(port: hal_port_t, dest: *mut c_char, count: c_uint) -> bool {
+    with_port(port, false, |buffer| {
+        let dest = std::slice::from_raw_parts_mut(dest.cast::<u8>(), count as usize);
+
+        buffer.read(dest, true)
+    })
+}
+
+pub unsafe extern "C" fn hal_port_peek(port: hal_port_t, dest: *mut c_char, count: c_uint) -> bool {
+    with_port(port, false, |buffer| {
+        let dest = std::slice::from_raw_parts_mut(dest.cast::<u8>(), count as usize);
+
+        buffer.read(dest, false)
+    })
+}
+
+pub unsafe extern "C" fn hal_port_peek_commit(port: hal_port_t, count: c_uint) -> bool {
+    with_port(port, false, |buffer| buffer.consume(count as usize))
+}
+
+pub unsafe extern "C" fn hal_port_write(
+    port: hal_port_t,
+    src: *const c_char,
+    count: c_uint,
+) -> bool {
+    with_port(port, false, |buffer| {
+        let src = std::slice::from_raw_parts(src.cast::<u8>(), count as usize);
+
+        buffer.write(src)
+    })
+}
+
+pub unsafe extern "C" fn hal_port_readable(port: hal_port_t) -> c_uint {
+    with_port(port, 0, |buffer| buffer.readable() as c_uint)
+}
+
+pub unsafe extern "C" fn hal_port_writable(port: hal_port_t) -> c_uint {
+    with_port(port, 0, |buffer| buffer.writable() as c_uint)
+}
+
+pub unsafe extern "C" fn hal_port_buffer_size(port: hal_port_t) -> c_uint {
+    with_port(port, 0, |buffer| buffer.size as c_uint)
+}
+
+pub unsafe extern "C" fn hal_port_clear(port: hal_port_t) {
+    with_port(port, (), |buffer| buffer.data.clear())
+}
+
+unsafe fn param_new(
+    name: *const c_char,
+    ty: hal_type_t,
+    dir: hal_param_dir_t,
+    data_addr: *mut c_void,
+    comp_id: c_int,
+) -> c_int {
+    let name = match name_from_ptr(name) {
+        Some(name) => name,
+        None => return -(EINVAL as c_int),
+    };
+
+    if data_addr.is_null() || !matches!(dir, hal_param_dir_t_HAL_RO | hal_param_dir_t_HAL_RW) {
+        return -(EINVAL as c_int);
+    }
+
+    with_hal(|hal| {
+        let ret = hal.can_add(comp_id, &name, &hal.params);
+
+        if ret != 0 {
+            return ret;
+        }
+
+        hal.params.insert(
+            name,
+            Param {
+                owner: comp_id,
+                ty,
+                dir,
+                data: Shared(data_addr),
+            },
+        );
+
+        0
+    })
+}
+
+pub unsafe extern "C" fn hal_param_bit_new(
+    name: *const c_char,
+    dir: hal_param_dir_t,
+    data_addr: *mut hal_bit_t,
+    comp_id: c_int,
+) -> c_int {
+    param_new(name, hal_type_t_HAL_BIT, dir, data_addr.cast(), comp_id)
+}
+
+pub unsafe extern "C" fn hal_param_float_new(
+    name: *const c_char,
+    dir: hal_param_dir_t,
+    data_addr: *mut hal_float_t,
+    comp_id: c_int,
+) -> c_int {
+    param_new(name, hal_type_t_HAL_FLOAT, dir, data_addr.cast(), comp_id)
+}
+
+pub unsafe extern "C" fn hal_param_u32_new(
+    name: *const c_char,
+    dir: hal_param_dir_t,
+    data_addr: *mut hal_u32_t,
+    comp_id: c_int,
+) -> c_int {
+    param_new(name, hal_type_t_HAL_U32, dir, data_addr.cast(), comp_id)
+}
+
+pub unsafe extern "C" fn hal_param_s32_new(
+    name: *const c_char,
+    dir: hal_param_dir_t,
+    data_addr: *mut hal_s32_t,
+    comp_id: c_int,
+) -> c_int {
+    param_new(name, hal_type_t_HAL_S32, dir, data_addr.cast(), comp_id)
+}
+
+pub unsafe extern "C" fn hal_param_s64_new(
+    name: *const c_char,
+    dir: hal_param_dir_t,
+    data_addr: *mut hal_s64_t,
+    comp_id: c_int,
+) -> c_int {
+    param_new(name, hal_type_t_HAL_S64, dir, data_addr.cast(), comp_id)
+}
+
+pub unsafe extern "C" fn hal_param_u64_new(
+    name: *const c_char,
+    dir: hal_param_dir_t,
+    data_addr: *mut hal_u64_t,
+    comp_id: c_int,
+) -> c_int {
+    param_new(name, hal_type_t_HAL_U64, dir, data_addr.cast(), comp_id)
+}
+
+pub unsafe extern "C" fn hal_export_funct(
+    name: *const c_char,
+    funct: Option<unsafe extern "C" fn(arg1: *mut c_void, arg2: c_long)>,
+    arg: *mut c_void,
+    uses_fp: c_int,
+    reentrant: c_int,
+    comp_id: c_int,
+) -> c_int {
+    let name = match name_from_ptr(name) {
+        Some(name) => name,
+        None => return -(EINVAL as c_int),
+    };
+
+    let funct = match funct {
+        Some(funct) => funct,
+        None => return -(EINVAL as c_int),
+    };
+
+    with_hal(|hal| {
+        let ret = hal.can_add(comp_id, &name, &hal.functs);
+
+        if ret != 0 {
+            return ret;
+        }
+
+        hal.functs.insert(
+            name,
+            Funct {
+                owner: comp_id,
+                funct,
+                arg: Shared(arg),
+                uses_fp: uses_fp != 0,
+                reentrant: reentrant != 0,
+            },
+        );
+
+        0
+    })
+}
+
+pub unsafe extern "C" fn hal_create_thread(
+    name: *const c_char,
+    period_nsec: c_ulong,
+    uses_fp: c_int,
+) -> c_int {
+    let name = match name_from_ptr(name) {
+        Some(name) => name,
+        None => return -(EINVAL as c_int),
+    };
+
+    if period_nsec == 0 || period_nsec > c_long::MAX as c_ulong {
+        log::error!("HAL: ERROR: invalid period for thread '{}'", name);
+
+        return -(EINVAL as c_int);
+    }
+
+    with_hal(|hal| {
+        if hal.threads.contains_key(&name) {
+            log::error!("HAL: ERROR: duplicate thread name '{}'", name);
+
+            return -(EINVAL as c_int);
+        }
+
+        hal.threads.insert(
+            name,
+            Thread {
+                period: period_nsec as c_long,
+                uses_fp: uses_fp != 0,
+                functs: Vec::new(),
+            },
+        );
+
+        0
+    })
+}
+
+pub unsafe extern "C" fn hal_thread_delete(name: *const c_char) -> c_int {
+    let name = match name_from_ptr(name) {
+        Some(name) => name,
+        None => return -(EINVAL as c_int),
+    };
+
+    with_hal(|hal| match hal.threads.remove(&name) {
+        Some(_) => 0,
+        None => {
+            log::error!("HAL: ERROR: thread '{}' not found", name);
+
+            -(EINVAL as c_int)
+        }
+    })
+}
+
+pub unsafe extern "C" fn hal_add_funct_to_thread(
+    funct_name: *const c_char,
+    thread_name: *const c_char,
+    position: c_int,
+) -> c_int {
+    let (funct_name, thread_name) = match (name_from_ptr(funct_name), name_from_ptr(thread_name)) {
+        (Some(funct_name), Some(thread_name)) => (funct_name, thread_name),
+        _ => return -(EINVAL as c_int),
+    };
+
+    with_hal(|hal| {
+        let users = hal
+            .threads
+            .values()
+            .flat_map(|thread| thread.functs.iter())
+            .filter(|name| **name == funct_name)
+            .count();
+
+        let (funct, thread) = match (
+            hal.functs.get(&funct_name),
+            hal.threads.get_mut(&thread_name),
+        ) {
+            (Some(funct), Some(thread)) => (funct, thread),
+            (None, _) => {
+                log::error!("HAL: ERROR: function '{}' not found", funct_name);
+
+                return -(EINVAL as c_int);
+            }
+            (_, None) => {
+                log::error!("HAL: ERROR: thread '{}' not found", thread_name);
+
+                return -(EINVAL as c_int);
+            }
+        };
+
+        if users > 0 && !funct.reentrant {
+            log::error!(
+                "HAL: ERROR: function '{}' may only be added to one thread",
+                funct_name
+            );
+
+            return -(EINVAL as c_int);
+        }
+
+        if funct.uses_fp && !thread.uses_fp {
+            log::error!(
+                "HAL: ERROR: function '{}' needs FP, but thread '{}' doesn't support it",
+                funct_name,
+                thread_name
+            );
+
+            return -(EINVAL as c_int);
+        }
+
+        // Positive positions count from the start of the list, 1 being the first function.
+        // Negative positions count from the end, -1 being the last.
+        let len = thread.functs.len() as c_int;
+
+        let index = match position {
+            position if position > 0 && position <= len + 1 => position - 1,
+            position if position < 0 && -position <= len + 1 => len + 1 + position,
+            _ => {
+                log::error!(
+                    "HAL: ERROR: bad position {} in thread '{}'",
+                    position,
+                    thread_name
+                );
+
+                return -(EINVAL as c_int);
+            }
+        };
+
+        thread.functs.insert(index as usize, funct_name);
+
+        0
+    })
+}
+
+pub unsafe extern "C" fn hal_del_funct_from_thread(
+    funct_name: *const c_char,
+    thread_name: *const c_char,
+) -> c_int {
+    let (funct_name, thread_name) = match (name_from_ptr(funct_name), name_from_ptr(thread_name)) {
+        (Some(funct_name), Some(thread_name)) => (funct_name, thread_name),
+        _ => return -(EINVAL as c_int),
+    };
+
+    with_hal(|hal| {
+        let thread = match hal.threads.get_mut(&thread_name) {
+            Some(thread) => thread,
+            None => {
+                log::error!("HAL: ERROR: thread '{}' not found", thread_name);
+
+                return -(EINVAL as c_int);
+            }
+        };
+
+        match thread.functs.iter().position(|name| *name == funct_name) {
+            Some(index) => {
+                thread.functs.remove(index);
+
+                0
+            }
+            None => {
+                log::error!(
+                    "HAL: ERROR: thread '{}' doesn't use function '{}'",
+                    thread_name,
+                    funct_name
+                );
+
+                -(EINVAL as c_int)
+            }
+        }
+    })
+}
+
+pub unsafe extern "C" fn hal_start_threads() -> c_int {
+    with_hal(|hal| hal.threads_running = true);
+
+    0
+}
+
+pub unsafe extern "C" fn hal_stop_threads() -> c_int {
+    with_hal(|hal| hal.threads_running = false);
+
+    0
+}
+
+pub unsafe extern "C" fn hal_signal_new(name: *const c_char, type_: hal_type_t) -> c_int {
+    let name = match name_from_ptr(name) {
+        Some(name) => name,
+        None => return -(EINVAL as c_int),
+    };
+
+    if !matches!(
+        type_,
+        hal_type_t_HAL_BIT
+            | hal_type_t_HAL_FLOAT
+            | hal_type_t_HAL_S32
+            | hal_type_t_HAL_U32
+            | hal_type_t_HAL_PORT
+            | hal_type_t_HAL_S64
+            | hal_type_t_HAL_U64
+    ) {
+        return -(EINVAL as c_int);
+    }
+
+    with_hal(|hal| {
+        if hal.signals.contains_key(&name) {
+            log::error!("HAL: ERROR: duplicate signal '{}'", name);
+
+            return -(EINVAL as c_int);
+        }
+
+        hal.signals.insert(
+            name,
+            Signal {
+                ty: type_,
+                data: Box::new(DataCell::default()),
+            },
+        );
+
+        0
+    })
+}
+
+pub unsafe extern "C" fn hal_signal_delete(name: *const c_char) -> c_int {
+    let name = match name_from_ptr(name) {
+        Some(name) => name,
+        None => return -(EINVAL as c_int),
+    };
+
+    with_hal(|hal| {
+        if hal.signals.remove(&name).is_none() {
+            log::error!("HAL: ERROR: signal '{}' not found", name);
+
+            return -(EINVAL as c_int);
+        }
+
+        for pin in hal.pins.values_mut() {
+            if pin.signal.as_deref() == Some(name.as_str()) {
+                pin.unlink();
+            }
+        }
+
+        0
+    })
+}
+
+pub unsafe extern "C" fn hal_link(pin_name: *const c_char, sig_name: *const c_char) -> c_int {
+    let (pin_name, sig_name) = match (name_from_ptr(pin_name), name_from_ptr(sig_name)) {
+        (Some(pin_name), Some(sig_name)) => (pin_name, sig_name),
+        _ => return -(EINVAL as c_int),
+    };
+
+    with_hal(|hal| {
+        let count = |dir| {
+            hal.pins
+                .values()
+                .filter(|pin| pin.signal.as_ref() == Some(&sig_name) && pin.dir == dir)
+                .count()
+        };
+
+        let (writers, bidirs) = (count(hal_pin_dir_t_HAL_OUT), count(hal_pin_dir_t_HAL_IO));
+
+        let (pin, signal) = match (hal.pins.get_mut(&pin_name), hal.signals.get_mut(&sig_name)) {
+            (Some(pin), Some(signal)) => (pin, signal),
+            (None, _) => {
+                log::error!("HAL: ERROR: pin '{}' not found", pin_name);
+
+                return -(EINVAL as c_int);
+            }
+            (_, None) => {
+                log::error!("HAL: ERROR: signal '{}' not found", sig_name);
+
+                return -(EINVAL as c_int);
+            }
+        };
+
+        match &pin.signal {
+            Some(linked) if *linked == sig_name => return 0,
+            Some(linked) => {
+                log::error!(
+                    "HAL: ERROR: pin '{}' is linked to '{}', cannot link to '{}'",
+                    pin_name,
+                    linked,
+                    sig_name
+                );
+
+                return -(EINVAL as c_int);
+            }
+            None => (),
+        }
+
+        if pin.ty != signal.ty {
+            log::error!("HAL: ERROR: type mismatch '{}' <- '{}'", pin_name, sig_name);
+
+            return -(EINVAL as c_int);
+        }
+
+        let conflict = match pin.dir {
+            hal_pin_dir_t_HAL_OUT => writers > 0 || bidirs > 0,
+            hal_pin_dir_t_HAL_IO => writers > 0,
+            _ => false,
+        };
+
+        if conflict {
+            log::error!("HAL: ERROR: signal '{}' already has output pin", sig_name);
+
+            return -(EINVAL as c_int);
+        }
+
+        pin.link(&sig_name, signal);
+
+        0
+    })
+}
+
+pub unsafe extern "C" fn hal_unlink(pin_name: *const c_char) -> c_int {
+    let pin_name = match name_from_ptr(pin_name) {
+        Some(pin_name) => pin_name,
+        None => return -(EINVAL as c_int),
+    };
+
+    with_hal(|hal| match hal.pins.get_mut(&pin_name) {
+        Some(pin) => {
+            pin.unlink();
+
+            0
+        }
+        None => {
+            log::error!("HAL: ERROR: pin '{}' not found", pin_name);
+
+            -(EINVAL as c_int)
+        }
+    })
+}
+
+/// Print a message to the log
+///
+/// Unlike the real `rtapi_print_msg`, this function is not variadic. `fmt` is logged verbatim using
+/// the [`log`] crate.
+pub unsafe extern "C" fn rtapi_print_msg(level: msg_level_t, fmt: *const c_char) {
+    if fmt.is_null() || level > with_hal(|hal| hal.msg_level) as msg_level_t {
+        return;
+    }
+
+    let message = CStr::from_ptr(fmt).to_string_lossy();
+    let message = message.trim_end();
+
+    match level {
+        msg_level_t_RTAPI_MSG_NONE => (),
+        msg_level_t_RTAPI_MSG_ERR => log::error!("{}", message),
+        msg_level_t_RTAPI_MSG_WARN => log::warn!("{}", message),
+        msg_level_t_RTAPI_MSG_INFO => log::info!("{}", message),
+        msg_level_t_RTAPI_MSG_DBG => log::debug!("{}", message),
+        _ => log::trace!("{}", message),
+    }
+}
+
+pub unsafe extern "C" fn rtapi_get_msg_level() -> c_int {
+    with_hal(|hal| hal.msg_level)
+}
+
+pub unsafe extern "C" fn rtapi_set_msg_level(level: c_int) -> c_int {
+    if !(msg_level_t_RTAPI_MSG_NONE as c_int..=msg_level_t_RTAPI_MSG_ALL as c_int).contains(&level)
+    {
+        return -(EINVAL as c_int);
+    }
+
+    with_hal(|hal| hal.msg_level = level);
+
+    0
+}
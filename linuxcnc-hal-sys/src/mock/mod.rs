@@ -0,0 +1,765 @@
+//! An in-process mock of the LinuxCNC HAL
+//!
+//! Enabled with the `mock` feature. When enabled, the generated bindings are replaced by a pure
+//! Rust implementation of the HAL functions used to create components, pins, parameters and
+//! functions. No LinuxCNC installation, source code or running realtime environment is required,
+//! so components can be tested with a plain `cargo test`.
+//!
+//! The mock keeps track of every component, pin, parameter and exported function in the current
+//! process. The functions in this module can be used to inspect and modify that state from a test.
+//!
+//! Component and pin names are global to the process, just like in LinuxCNC. Tests run in parallel
+//! by default, so each test should use a unique component name.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use linuxcnc_hal_sys::*;
+//! use std::{ffi::CString, mem};
+//!
+//! unsafe {
+//!     let id = hal_init(CString::new("mock-example").unwrap().as_ptr());
+//!
+//!     let storage = hal_malloc(mem::size_of::<*mut f64>() as i64) as *mut *mut f64;
+//!
+//!     let pin_name = CString::new("mock-example.input-1").unwrap();
+//!
+//!     hal_pin_float_new(pin_name.as_ptr(), hal_pin_dir_t_HAL_IN, storage, id);
+//!
+//!     hal_ready(id);
+//!
+//!     mock::set_pin_value("mock-example.input-1", 1.5).unwrap();
+//!
+//!     assert_eq!(**storage, 1.5);
+//!
+//!     hal_exit(id);
+//! }
+//! ```
+
+pub(crate) mod ffi;
+
+use self::ffi::*;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    convert::TryFrom,
+    fmt,
+    os::raw::{c_int, c_long, c_void},
+    sync::{Mutex, MutexGuard},
+};
+
+/// The value of a pin or parameter
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Value {
+    /// `HAL_BIT`
+    Bit(bool),
+
+    /// `HAL_FLOAT`
+    Float(f64),
+
+    /// `HAL_S32`
+    S32(i32),
+
+    /// `HAL_U32`
+    U32(u32),
+
+    /// `HAL_S64`
+    S64(i64),
+
+    /// `HAL_U64`
+    U64(u64),
+}
+
+impl Value {
+    fn hal_type(&self) -> hal_type_t {
+        match self {
+            Value::Bit(_) => hal_type_t_HAL_BIT,
+            Value::Float(_) => hal_type_t_HAL_FLOAT,
+            Value::S32(_) => hal_type_t_HAL_S32,
+            Value::U32(_) => hal_type_t_HAL_U32,
+            Value::S64(_) => hal_type_t_HAL_S64,
+            Value::U64(_) => hal_type_t_HAL_U64,
+        }
+    }
+
+    /// Read a value of the given type from HAL memory
+    unsafe fn read(ty: hal_type_t, ptr: *const c_void) -> Self {
+        match ty {
+            hal_type_t_HAL_BIT => Value::Bit(ptr.cast::<bool>().read_volatile()),
+            hal_type_t_HAL_FLOAT => Value::Float(ptr.cast::<f64>().read_volatile()),
+            hal_type_t_HAL_S32 => Value::S32(ptr.cast::<i32>().read_volatile()),
+            hal_type_t_HAL_U32 => Value::U32(ptr.cast::<u32>().read_volatile()),
+            hal_type_t_HAL_S64 => Value::S64(ptr.cast::<i64>().read_volatile()),
+            hal_type_t_HAL_U64 => Value::U64(ptr.cast::<u64>().read_volatile()),
+            ty => unreachable!("Mock HAL created an object with unknown type {}", ty),
+        }
+    }
+
+    /// Write this value into HAL memory
+    unsafe fn write(self, ptr: *mut c_void) {
+        match self {
+            Value::Bit(value) => ptr.cast::<bool>().write_volatile(value),
+            Value::Float(value) => ptr.cast::<f64>().write_volatile(value),
+            Value::S32(value) => ptr.cast::<i32>().write_volatile(value),
+            Value::U32(value) => ptr.cast::<u32>().write_volatile(value),
+            Value::S64(value) => ptr.cast::<i64>().write_volatile(value),
+            Value::U64(value) => ptr.cast::<u64>().write_volatile(value),
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bit(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Float(value)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(value: i32) -> Self {
+        Value::S32(value)
+    }
+}
+
+impl From<u32> for Value {
+    fn from(value: u32) -> Self {
+        Value::U32(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::S64(value)
+    }
+}
+
+impl From<u64> for Value {
+    fn from(value: u64) -> Self {
+        Value::U64(value)
+    }
+}
+
+macro_rules! impl_try_from_value {
+    ($storage:ty, $variant:ident) => {
+        impl TryFrom<Value> for $storage {
+            type Error = Value;
+
+            fn try_from(value: Value) -> Result<Self, Self::Error> {
+                match value {
+                    Value::$variant(value) => Ok(value),
+                    other => Err(other),
+                }
+            }
+        }
+    };
+}
+
+impl_try_from_value!(bool, Bit);
+impl_try_from_value!(f64, Float);
+impl_try_from_value!(i32, S32);
+impl_try_from_value!(u32, U32);
+impl_try_from_value!(i64, S64);
+impl_try_from_value!(u64, U64);
+
+/// An error returned when inspecting or modifying the mock HAL
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockError {
+    /// No item with the given name exists
+    NotFound(String),
+
+    /// The given value does not match the type of the item
+    TypeMismatch(String),
+
+    /// The parameter is read only
+    ReadOnly(String),
+
+    /// The port is not attached to a buffer
+    PortNotAttached(String),
+
+    /// There is not enough space in the port's buffer to write the data
+    PortFull(String),
+}
+
+impl fmt::Display for MockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MockError::NotFound(name) => write!(f, "no HAL item named {}", name),
+            MockError::TypeMismatch(name) => write!(f, "value has wrong type for {}", name),
+            MockError::ReadOnly(name) => write!(f, "parameter {} is read only", name),
+            MockError::PortNotAttached(name) => write!(f, "port {} is not attached", name),
+            MockError::PortFull(name) => write!(f, "not enough space in port {}", name),
+        }
+    }
+}
+
+impl std::error::Error for MockError {}
+
+/// A raw pointer into memory owned by the HAL or by a component
+///
+/// All access to these pointers goes through the global HAL lock.
+#[derive(Debug)]
+pub(crate) struct Shared<T>(pub(crate) *mut T);
+
+impl<T> Clone for Shared<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Shared<T> {}
+
+unsafe impl<T> Send for Shared<T> {}
+
+/// Backing storage for a signal, or for a pin that is not connected to a signal
+#[repr(C, align(8))]
+#[derive(Debug, Copy, Clone, Default)]
+pub(crate) struct DataCell([u8; 8]);
+
+#[derive(Debug)]
+pub(crate) struct Component {
+    pub(crate) name: String,
+    pub(crate) ready: bool,
+}
+
+#[derive(Debug)]
+pub(crate) struct Pin {
+    pub(crate) owner: c_int,
+    pub(crate) ty: hal_type_t,
+    pub(crate) dir: hal_pin_dir_t,
+
+    /// The name of the signal the pin is linked to, if any
+    pub(crate) signal: Option<String>,
+
+    /// The address the component reads the pin's data pointer from
+    pub(crate) data_ptr_addr: Shared<*mut c_void>,
+
+    /// Storage used while the pin is not linked to a signal
+    pub(crate) dummy: Box<DataCell>,
+}
+
+impl Pin {
+    /// Create a new pin and point the component's data pointer at its dummy storage
+    pub(crate) unsafe fn new(
+        owner: c_int,
+        ty: hal_type_t,
+        dir: hal_pin_dir_t,
+        data_ptr_addr: Shared<*mut c_void>,
+    ) -> Self {
+        let mut pin = Self {
+            owner,
+            ty,
+            dir,
+            signal: None,
+            data_ptr_addr,
+            dummy: Box::new(DataCell::default()),
+        };
+
+        *data_ptr_addr.0 = pin.dummy_ptr();
+
+        pin
+    }
+
+    fn dummy_ptr(&mut self) -> *mut c_void {
+        (&mut *self.dummy as *mut DataCell).cast()
+    }
+
+    fn data(&self) -> *mut c_void {
+        unsafe { *self.data_ptr_addr.0 }
+    }
+
+    /// Point the component's data pointer at the signal's storage
+    ///
+    /// Like the real HAL, the value of an output or bidirectional pin is copied to the signal.
+    /// Port handles are never copied so a port signal keeps its buffer.
+    pub(crate) unsafe fn link(&mut self, signal_name: &str, signal: &mut Signal) {
+        if self.dir != hal_pin_dir_t_HAL_IN && self.ty != hal_type_t_HAL_PORT {
+            *signal.data = *self.dummy;
+        }
+
+        *self.data_ptr_addr.0 = (&mut *signal.data as *mut DataCell).cast();
+
+        self.signal = Some(signal_name.to_string());
+    }
+
+    /// Point the component's data pointer back at the dummy storage, keeping the current value
+    pub(crate) unsafe fn unlink(&mut self) {
+        if self.signal.take().is_some() {
+            *self.dummy = self.data().cast::<DataCell>().read();
+
+            *self.data_ptr_addr.0 = self.dummy_ptr();
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Signal {
+    pub(crate) ty: hal_type_t,
+    pub(crate) data: Box<DataCell>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Param {
+    pub(crate) owner: c_int,
+    pub(crate) ty: hal_type_t,
+    pub(crate) dir: hal_param_dir_t,
+    pub(crate) data: Shared<c_void>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Funct {
+    pub(crate) owner: c_int,
+    pub(crate) funct: unsafe extern "C" fn(*mut c_void, c_long),
+    pub(crate) arg: Shared<c_void>,
+    pub(crate) uses_fp: bool,
+    pub(crate) reentrant: bool,
+}
+
+#[derive(Debug)]
+pub(crate) struct Thread {
+    pub(crate) period: c_long,
+    pub(crate) uses_fp: bool,
+
+    /// Names of the functions run by the thread, in order
+    pub(crate) functs: Vec<String>,
+}
+
+/// The byte buffer shared by the two ends of a port
+#[derive(Debug)]
+pub(crate) struct PortBuffer {
+    pub(crate) data: VecDeque<u8>,
+    pub(crate) size: usize,
+}
+
+impl PortBuffer {
+    pub(crate) fn readable(&self) -> usize {
+        self.data.len()
+    }
+
+    pub(crate) fn writable(&self) -> usize {
+        self.size - self.data.len()
+    }
+
+    /// Copy exactly `dest.len()` bytes out of the buffer, removing them if `consume` is set
+    pub(crate) fn read(&mut self, dest: &mut [u8], consume: bool) -> bool {
+        if dest.len() > self.readable() {
+            return false;
+        }
+
+        for (dest, src) in dest.iter_mut().zip(self.data.iter()) {
+            *dest = *src;
+        }
+
+        if consume {
+            self.data.drain(..dest.len());
+        }
+
+        true
+    }
+
+    /// Remove exactly `count` bytes from the buffer
+    pub(crate) fn consume(&mut self, count: usize) -> bool {
+        if count > self.readable() {
+            return false;
+        }
+
+        self.data.drain(..count);
+
+        true
+    }
+
+    /// Write all of `src` into the buffer, or nothing if there isn't enough space
+    pub(crate) fn write(&mut self, src: &[u8]) -> bool {
+        if src.len() > self.writable() {
+            return false;
+        }
+
+        self.data.extend(src);
+
+        true
+    }
+}
+
+/// The global mock HAL state
+#[derive(Debug)]
+pub(crate) struct Hal {
+    next_id: c_int,
+    pub(crate) msg_level: c_int,
+    pub(crate) components: BTreeMap<c_int, Component>,
+    pub(crate) pins: BTreeMap<String, Pin>,
+    pub(crate) params: BTreeMap<String, Param>,
+    pub(crate) functs: BTreeMap<String, Funct>,
+    pub(crate) signals: BTreeMap<String, Signal>,
+    pub(crate) threads: BTreeMap<String, Thread>,
+    pub(crate) threads_running: bool,
+
+    /// Port buffers, keyed by the `hal_port_t` handle stored in port pins
+    pub(crate) ports: BTreeMap<hal_port_t, PortBuffer>,
+    next_port: hal_port_t,
+}
+
+static HAL: Mutex<Hal> = Mutex::new(Hal {
+    next_id: 1,
+    msg_level: msg_level_t_RTAPI_MSG_ERR as c_int,
+    components: BTreeMap::new(),
+    pins: BTreeMap::new(),
+    params: BTreeMap::new(),
+    functs: BTreeMap::new(),
+    signals: BTreeMap::new(),
+    threads: BTreeMap::new(),
+    threads_running: false,
+    ports: BTreeMap::new(),
+    next_port: 1,
+});
+
+fn lock() -> MutexGuard<'static, Hal> {
+    // A test panicking while holding the lock shouldn't fail every other test
+    HAL.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Run a closure with exclusive access to the global mock HAL state
+pub(crate) fn with_hal<T>(f: impl FnOnce(&mut Hal) -> T) -> T {
+    f(&mut lock())
+}
+
+impl Hal {
+    pub(crate) fn add_component(&mut self, name: String) -> c_int {
+        if self.components.values().any(|comp| comp.name == name) {
+            log::error!("HAL: ERROR: duplicate component name '{}'", name);
+
+            return -(EINVAL as c_int);
+        }
+
+        let id = self.next_id;
+
+        self.next_id += 1;
+
+        self.components.insert(id, Component { name, ready: false });
+
+        id
+    }
+
+    pub(crate) fn remove_component(&mut self, id: c_int) -> c_int {
+        if self.components.remove(&id).is_none() {
+            return -(EINVAL as c_int);
+        }
+
+        self.pins.retain(|_, pin| pin.owner != id);
+        self.params.retain(|_, param| param.owner != id);
+        self.functs.retain(|_, funct| funct.owner != id);
+
+        let functs = &self.functs;
+
+        for thread in self.threads.values_mut() {
+            thread.functs.retain(|name| functs.contains_key(name));
+        }
+
+        0
+    }
+
+    /// Get the buffer handle of the port pin with the given name
+    fn port_handle(&self, name: &str) -> Result<hal_port_t, MockError> {
+        let pin = self
+            .pins
+            .get(name)
+            .ok_or_else(|| MockError::NotFound(name.to_string()))?;
+
+        if pin.ty != hal_type_t_HAL_PORT {
+            return Err(MockError::TypeMismatch(name.to_string()));
+        }
+
+        Ok(unsafe { pin.data().cast::<hal_port_t>().read_volatile() })
+    }
+
+    /// Check that the component exists and isn't ready yet, and that `name` isn't in use already,
+    /// returning 0 or a negative errno code
+    ///
+    /// Pins, parameters and functions each have their own namespace, so `existing` should be the
+    /// collection the new item will be added to.
+    pub(crate) fn can_add<T>(
+        &self,
+        comp_id: c_int,
+        name: &str,
+        existing: &BTreeMap<String, T>,
+    ) -> c_int {
+        match self.components.get(&comp_id) {
+            Some(comp) if !comp.ready => (),
+            Some(comp) => {
+                log::error!(
+                    "HAL: ERROR: cannot add '{}' to component '{}' after hal_ready",
+                    name,
+                    comp.name
+                );
+
+                return -(EINVAL as c_int);
+            }
+            None => return -(EINVAL as c_int),
+        }
+
+        if existing.contains_key(name) {
+            log::error!("HAL: ERROR: duplicate name '{}'", name);
+
+            return -(EINVAL as c_int);
+        }
+
+        0
+    }
+}
+
+/// Get the ID of the component with the given name
+pub fn component_id(name: &str) -> Option<i32> {
+    lock()
+        .components
+        .iter()
+        .find(|(_, comp)| comp.name == name)
+        .map(|(id, _)| *id)
+}
+
+/// Check whether the component with the given name has called `hal_ready`
+pub fn component_is_ready(name: &str) -> Option<bool> {
+    lock()
+        .components
+        .values()
+        .find(|comp| comp.name == name)
+        .map(|comp| comp.ready)
+}
+
+/// Get the full names of all registered pins
+pub fn pin_names() -> Vec<String> {
+    lock().pins.keys().cloned().collect()
+}
+
+/// Get the full names of all registered parameters
+pub fn param_names() -> Vec<String> {
+    lock().params.keys().cloned().collect()
+}
+
+/// Get the full names of all exported functions
+pub fn funct_names() -> Vec<String> {
+    lock().functs.keys().cloned().collect()
+}
+
+/// Get the names of all signals
+pub fn signal_names() -> Vec<String> {
+    lock().signals.keys().cloned().collect()
+}
+
+/// Get the name of the signal a pin is linked to
+pub fn pin_signal(name: &str) -> Option<String> {
+    lock().pins.get(name)?.signal.clone()
+}
+
+/// Get the current value of a signal
+///
+/// Like [`pin_value`], `None` is returned for port signals.
+pub fn signal_value(name: &str) -> Option<Value> {
+    let hal = lock();
+
+    let signal = hal
+        .signals
+        .get(name)
+        .filter(|signal| signal.ty != hal_type_t_HAL_PORT)?;
+
+    Some(unsafe { Value::read(signal.ty, (&*signal.data as *const DataCell).cast()) })
+}
+
+/// Get the current value of a pin
+///
+/// Ports don't have a value, so `None` is returned for port pins. Use [`port_read`] instead.
+pub fn pin_value(name: &str) -> Option<Value> {
+    let hal = lock();
+
+    let pin = hal
+        .pins
+        .get(name)
+        .filter(|pin| pin.ty != hal_type_t_HAL_PORT)?;
+
+    Some(unsafe { Value::read(pin.ty, pin.data()) })
+}
+
+/// Set the value of a pin
+///
+/// Unlike `halcmd setp`, this may be used on pins of any direction so a test can simulate values
+/// written by other components.
+pub fn set_pin_value(name: &str, value: impl Into<Value>) -> Result<(), MockError> {
+    let value = value.into();
+    let hal = lock();
+
+    let pin = hal
+        .pins
+        .get(name)
+        .ok_or_else(|| MockError::NotFound(name.to_string()))?;
+
+    if pin.ty != value.hal_type() {
+        return Err(MockError::TypeMismatch(name.to_string()));
+    }
+
+    unsafe { value.write(pin.data()) };
+
+    Ok(())
+}
+
+/// Get the current value of a parameter
+pub fn param_value(name: &str) -> Option<Value> {
+    let hal = lock();
+
+    let param = hal.params.get(name)?;
+
+    Some(unsafe { Value::read(param.ty, param.data.0) })
+}
+
+/// Set the value of a parameter
+///
+/// Like `halcmd setp`, read only parameters cannot be changed.
+pub fn set_param_value(name: &str, value: impl Into<Value>) -> Result<(), MockError> {
+    let value = value.into();
+    let hal = lock();
+
+    let param = hal
+        .params
+        .get(name)
+        .ok_or_else(|| MockError::NotFound(name.to_string()))?;
+
+    if param.dir != hal_param_dir_t_HAL_RW {
+        return Err(MockError::ReadOnly(name.to_string()));
+    }
+
+    if param.ty != value.hal_type() {
+        return Err(MockError::TypeMismatch(name.to_string()));
+    }
+
+    unsafe { value.write(param.data.0) };
+
+    Ok(())
+}
+
+/// Call an exported function once, as if it were run by a HAL thread with the given period in
+/// nanoseconds
+pub fn call_funct(name: &str, period: i64) -> Result<(), MockError> {
+    // The lock must be released before calling the function in case it calls back into the HAL
+    let (funct, arg) = {
+        let hal = lock();
+
+        let funct = hal
+            .functs
+            .get(name)
+            .ok_or_else(|| MockError::NotFound(name.to_string()))?;
+
+        (funct.funct, funct.arg)
+    };
+
+    unsafe { funct(arg.0, period as c_long) };
+
+    Ok(())
+}
+
+/// Get the names of all threads
+pub fn thread_names() -> Vec<String> {
+    lock().threads.keys().cloned().collect()
+}
+
+/// Get the names of the functions added to a thread, in the order they are run
+pub fn thread_functs(name: &str) -> Option<Vec<String>> {
+    Some(lock().threads.get(name)?.functs.clone())
+}
+
+/// Check whether `hal_start_threads` has been called more recently than `hal_stop_threads`
+pub fn threads_running() -> bool {
+    lock().threads_running
+}
+
+/// Run one period of a thread, calling each of its functions in order
+///
+/// Threads are never run automatically by the mock HAL, so this can be used whether or not the
+/// threads are started.
+pub fn run_thread(name: &str) -> Result<(), MockError> {
+    // The lock must be released before calling the functions in case they call back into the HAL
+    let (period, functs) = {
+        let hal = lock();
+
+        let thread = hal
+            .threads
+            .get(name)
+            .ok_or_else(|| MockError::NotFound(name.to_string()))?;
+
+        let functs = thread
+            .functs
+            .iter()
+            .map(|name| (hal.functs[name].funct, hal.functs[name].arg))
+            .collect::<Vec<_>>();
+
+        (thread.period, functs)
+    };
+
+    for (funct, arg) in functs {
+        unsafe { funct(arg.0, period) };
+    }
+
+    Ok(())
+}
+
+/// Attach a port pin to a new, empty buffer of `size` bytes
+///
+/// This simulates linking the pin to a port signal with a buffer size set by `sets`. Until a port
+/// is attached, it can't be read from or written to. If the pin is already linked to a port signal,
+/// the buffer is attached to the signal and shared by every pin linked to it.
+pub fn attach_port(name: &str, size: usize) -> Result<(), MockError> {
+    let mut hal = lock();
+
+    hal.port_handle(name)?;
+
+    let handle = hal.next_port;
+
+    hal.next_port += 1;
+
+    hal.ports.insert(
+        handle,
+        PortBuffer {
+            data: VecDeque::with_capacity(size),
+            size,
+        },
+    );
+
+    let pin = &hal.pins[name];
+
+    unsafe { pin.data().cast::<hal_port_t>().write_volatile(handle) };
+
+    Ok(())
+}
+
+/// Write bytes into a port, as if they were written by the component at the other end
+///
+/// Like `hal_port_write`, either all or none of the bytes are written.
+pub fn port_write(name: &str, data: &[u8]) -> Result<(), MockError> {
+    let mut hal = lock();
+
+    let handle = hal.port_handle(name)?;
+
+    let buffer = hal
+        .ports
+        .get_mut(&handle)
+        .ok_or_else(|| MockError::PortNotAttached(name.to_string()))?;
+
+    if buffer.write(data) {
+        Ok(())
+    } else {
+        Err(MockError::PortFull(name.to_string()))
+    }
+}
+
+/// Read all bytes currently in a port, as if they were read by the component at the other end
+pub fn port_read(name: &str) -> Result<Vec<u8>, MockError> {
+    let mut hal = lock();
+
+    let handle = hal.port_handle(name)?;
+
+    let buffer = hal
+        .ports
+        .get_mut(&handle)
+        .ok_or_else(|| MockError::PortNotAttached(name.to_string()))?;
+
+    Ok(buffer.data.drain(..).collect())
+}
@@ -0,0 +1,51 @@
+//! Compare the fallible `value`/`set_value` pin accessors with the infallible `get`/`set`.
+//!
+//! Run with `cargo bench --features mock`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use linuxcnc_hal::{
+    hal_pin::{InputPin, OutputPin},
+    prelude::*,
+    HalComponent, Resources,
+};
+
+/// Number of pins copied per iteration, roughly what a servo-rate function might touch
+const PINS: usize = 32;
+
+#[derive(Resources)]
+struct Pins {
+    inputs: [InputPin<f64>; PINS],
+    outputs: [OutputPin<f64>; PINS],
+}
+
+fn pin_access(c: &mut Criterion) {
+    let comp = HalComponent::<Pins>::new("bench-pin-access").expect("Failed to create component");
+    let pins = comp.resources();
+
+    let mut group = c.benchmark_group("copy 32 pins");
+
+    // The old accessors are deprecated, but are kept here to show what `get`/`set` save
+    #[allow(deprecated)]
+    group.bench_function("value/set_value", |b| {
+        b.iter(|| {
+            for (input, output) in pins.inputs.iter().zip(pins.outputs.iter()) {
+                let value = input.value().unwrap();
+
+                output.set_value(black_box(value) * 2.0).unwrap();
+            }
+        })
+    });
+
+    group.bench_function("get/set", |b| {
+        b.iter(|| {
+            for (input, output) in pins.inputs.iter().zip(pins.outputs.iter()) {
+                output.set(black_box(input.get()) * 2.0);
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, pin_access);
+criterion_main!(benches);
@@ -0,0 +1,23 @@
+use std::env;
+
+fn main() {
+    println!("cargo:rustc-check-cfg=cfg(linuxcnc_hal_64bit)");
+    println!("cargo:rustc-check-cfg=cfg(linuxcnc_hal_port)");
+
+    // Set by the `linuxcnc-hal-sys` build script from the version of the LinuxCNC sources
+    let version = env::var("DEP_LINUXCNC_HAL_VERSION")
+        .expect("linuxcnc-hal-sys did not provide a LinuxCNC version");
+
+    let mut parts = version.split('.').map(|part| part.parse::<u32>());
+
+    let version = match (parts.next(), parts.next()) {
+        (Some(Ok(major)), Some(Ok(minor))) => (major, minor),
+        _ => panic!("Failed to parse LinuxCNC version {:?}", version),
+    };
+
+    // 64 bit pins and parameters, and port pins were added in LinuxCNC 2.9
+    if version >= (2, 9) {
+        println!("cargo:rustc-cfg=linuxcnc_hal_64bit");
+        println!("cargo:rustc-cfg=linuxcnc_hal_port");
+    }
+}
@@ -48,9 +48,9 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // Main control loop
     while !comp.should_exit() {
-        resources.ro.set_value(1.234)?;
+        resources.ro.set(1.234);
 
-        println!("RW: {:?}", resources.rw.value());
+        println!("RW: {}", resources.rw.get());
 
         thread::sleep(Duration::from_millis(1000));
     }
@@ -6,18 +6,18 @@
 //! To build this example, run `LINUXCNC_SRC=... cargo build --examples`. The resulting file is
 //! placed in `target/debug/examples/librtapi.so`. This can be loaded into LinuxCNC with
 //! `loadrt /path/to/librtapi`. Note that the `.so` is added for you.
+//!
+//! The component exports a function called `rust-comp.update` which must be added to a realtime
+//! thread, e.g. `addf rust-comp.update servo-thread`.
 
 use linuxcnc_hal::{
     error::PinRegisterError,
+    export_rt_component,
     hal_pin::{InputPin, OutputPin},
     prelude::*,
-    HalComponent, RegisterResources, Resources,
-};
-use std::{
-    error::Error,
-    thread,
-    time::{Duration, Instant},
+    HalFunction, RegisterResources, Resources,
 };
+use std::time::Duration;
 
 struct Pins {
     input_1: InputPin<f64>,
@@ -33,50 +33,28 @@ impl Resources for Pins {
             output_1: comp.register_pin::<OutputPin<f64>>("output-1")?,
         })
     }
-}
 
-/// Component entry point.
-#[no_mangle]
-pub extern "C" fn rtapi_app_main() -> i32 {
-    // Simple wrapper around inner function that turns result into integer.
-    match inner() {
-        // NOTE: The error code here should be a lot more descriptive in real code.
-        Err(e) => -1,
-        Ok(_) => 0,
+    fn functions() -> Vec<HalFunction<Self>> {
+        vec![HalFunction::new("update", update)]
     }
 }
 
-/// Inner function that can return a `Result`.
-///
-/// This is a convenience wrapper so that the `?` syntax sugar can be used.
-fn inner() -> Result<(), Box<dyn Error>> {
-    pretty_env_logger::init();
-
-    // Create a new HAL component called `rust-comp`
-    let comp: HalComponent<Pins> = HalComponent::new("rust-comp")?;
-
-    // Get a reference to the `Pins` struct
-    let pins = comp.resources();
-
-    let start = Instant::now();
-
-    // Main control loop
-    while !comp.should_exit() {
-        let time = start.elapsed().as_secs() as i32;
-
-        // Set output pin to elapsed seconds since component started
-        pins.output_1.set_value(time.into())?;
-
-        // Print the current value of the input pin
-        println!("Input: {:?}", pins.input_1.value());
-
-        // Sleep for 1000ms. This should be a lower time if the component needs to update more
-        // frequently.
-        thread::sleep(Duration::from_millis(1000));
-    }
-
-    // The custom implementation of `Drop` for `HalComponent` ensures that `hal_exit()` is called
-    // at this point. Registered signal handlers are also deregistered.
+/// Called once per period by the thread the function is added to
+fn update(pins: &Pins, _period: Duration) {
+    // Set output pin to double the value of the input pin
+    pins.output_1.set(pins.input_1.get() * 2.0);
+}
 
-    Ok(())
+// Generate the `rtapi_app_main` and `rtapi_app_exit` entry points called by LinuxCNC.
+//
+// The component is dropped when it is unloaded which ensures `hal_exit()` is called.
+export_rt_component! {
+    name: "rust-comp",
+    resources: Pins,
+    setup: |comp| {
+        // Set an initial value for the output before any thread runs
+        comp.resources().output_1.set(0.0);
+
+        Ok(())
+    },
 }
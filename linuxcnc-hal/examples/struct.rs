@@ -5,6 +5,12 @@
 //! * Output `rust-comp.output-1`
 //! * Input `rust-comp.input-1`
 //!
+//! Parameter names:
+//!
+//! * Read only `rust-comp.loop-time`
+//! * Read write `rust-comp.loop-tmax`
+//! * Read only `rust-comp.overruns`
+//!
 //! The component can be loaded and connected using something like the following `.hal` file:
 //!
 //! ```ini
@@ -14,31 +20,38 @@
 //! ```
 
 use linuxcnc_hal::{
-    error::PinRegisterError,
+    error::ResourcesError,
     hal_pin::{InputPin, OutputPin},
     prelude::*,
+    run_loop::LoopStats,
     HalComponent, RegisterResources, Resources,
 };
 use std::{
     error::Error,
-    thread,
     time::{Duration, Instant},
 };
 
 struct Pins {
     input_1: InputPin<f64>,
     output_1: OutputPin<f64>,
+    stats: LoopStats,
 }
 
 impl Resources for Pins {
-    type RegisterError = PinRegisterError;
+    type RegisterError = ResourcesError;
 
     fn register_resources(comp: &RegisterResources) -> Result<Self, Self::RegisterError> {
         Ok(Pins {
             input_1: comp.register_pin::<InputPin<f64>>("input-1")?,
             output_1: comp.register_pin::<OutputPin<f64>>("output-1")?,
+            stats: comp.register_loop_stats()?,
         })
     }
+
+    // Record the timing of `run_at` in the `loop-time`, `loop-tmax` and `overruns` parameters
+    fn loop_stats(&self) -> Option<&LoopStats> {
+        Some(&self.stats)
+    }
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -47,25 +60,21 @@ fn main() -> Result<(), Box<dyn Error>> {
     // Create a new HAL component called `rust-comp`
     let comp: HalComponent<Pins> = HalComponent::new("rust-comp")?;
 
-    // Get a reference to the `Pins` struct
-    let pins = comp.resources();
-
     let start = Instant::now();
 
-    // Main control loop
-    while !comp.should_exit() {
+    // Main control loop, run every 1000ms until the component is signalled to exit. This should be
+    // a lower period if the component needs to update more frequently.
+    comp.run_at(Duration::from_millis(1000), |pins, _ctx| {
         let time = start.elapsed().as_secs() as i32;
 
         // Set output pin to elapsed seconds since component started
-        pins.output_1.set_value(time.into())?;
+        pins.output_1.set(time.into());
 
         // Print the current value of the input pin
-        println!("Input: {:?}", pins.input_1.value());
+        println!("Input: {:?}", pins.input_1.get());
 
-        // Sleep for 1000ms. This should be a lower time if the component needs to update more
-        // frequently.
-        thread::sleep(Duration::from_millis(1000));
-    }
+        Ok::<_, Box<dyn Error>>(())
+    })?;
 
     // The custom implementation of `Drop` for `HalComponent` ensures that `hal_exit()` is called
     // at this point. Registered signal handlers are also deregistered.
@@ -0,0 +1,371 @@
+//! Async support for components written with tokio
+//!
+//! Requires the `tokio` feature. Everything in this module must be used from within a tokio
+//! runtime with the `time` driver enabled, e.g. one created by `#[tokio::main]`.
+//!
+//! * [`HalComponent::shutdown`] completes when the component is signalled to exit
+//! * [`HalComponent::event_stream`] is a stream of the [`ComponentEvent`]s the component
+//!   receives, like [`HalComponent::events`] does for blocking code
+//! * [`HalComponent::updates`] is a stream which yields the component's resources every period,
+//!   like [`HalComponent::run_at`] does for blocking code
+//! * [`InputPin::watch`] is a stream of an input pin's values, yielding each new value when it
+//!   changes
+//!
+//! The HAL doesn't notify components when a pin changes, so pins are polled on an interval.
+//!
+//! # Examples
+//!
+//! Forward the commanded speed to a device over the network whenever it changes, and report the
+//! device's status every 100ms, until the component receives `SIGINT` or `SIGTERM`.
+//!
+//! ```rust,no_run
+//! use linuxcnc_hal::{
+//!     hal_pin::{InputPin, OutputPin},
+//!     prelude::*,
+//!     HalComponent, Resources,
+//! };
+//! use std::{error::Error, time::Duration};
+//! use tokio_stream::StreamExt;
+//!
+//! #[derive(Resources)]
+//! struct Pins {
+//!     speed_command: InputPin<f64>,
+//!     at_speed: OutputPin<bool>,
+//! }
+//!
+//! #[tokio::main(flavor = "current_thread")]
+//! async fn main() -> Result<(), Box<dyn Error>> {
+//!     let comp: HalComponent<Pins> = HalComponent::new("net-vfd")?;
+//!     let pins = comp.resources();
+//!
+//!     let mut speed = pins.speed_command.watch(Duration::from_millis(10));
+//!     let mut updates = comp.updates(Duration::from_millis(100));
+//!     let shutdown = comp.shutdown();
+//!
+//!     tokio::pin!(shutdown);
+//!
+//!     loop {
+//!         tokio::select! {
+//!             Some(speed) = speed.next() => println!("Send speed {} to the VFD", speed),
+//!             Some(pins) = updates.next() => pins.at_speed.set(true),
+//!             result = &mut shutdown => break result?,
+//!         }
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::{
+    event::{ComponentEvent, SignalEvents},
+    hal_pin::{HalPin, InputPin, PinRead},
+    HalComponent, Resources, SharedValue,
+};
+use futures_core::Stream;
+use std::{
+    future, io,
+    os::raw::c_int,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+use tokio::{
+    signal::unix::{signal, Signal, SignalKind},
+    time::{self, Interval, MissedTickBehavior},
+};
+
+impl<R, S> HalComponent<R, S>
+where
+    R: Resources,
+{
+    /// Wait until the component is signalled to shut down
+    ///
+    /// This is the async equivalent of polling [`HalComponent::should_exit`]. It completes when
+    /// the process receives `SIGINT` or `SIGTERM`, or immediately if one was received since
+    /// `should_exit` was last called. If other signals were chosen with
+    /// [`HalComponentBuilder::signals`](crate::HalComponentBuilder::signals), it waits for those
+    /// instead, and never completes if signal handling was turned off.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the signal listeners can't be registered with tokio.
+    pub async fn shutdown(&self) -> io::Result<()> {
+        let mut listeners = listen(self.signal_events().signals_for(ComponentEvent::Exit))?;
+
+        // Check after registering the listeners so a signal can't be missed in between
+        if self.should_exit() {
+            return Ok(());
+        }
+
+        future::poll_fn(|cx| {
+            if listeners
+                .iter_mut()
+                .any(|listener| listener.poll_recv(cx).is_ready())
+            {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await;
+
+        Ok(())
+    }
+
+    /// Get a stream of the events the component receives
+    ///
+    /// This is the async equivalent of [`HalComponent::events`], and ends after yielding
+    /// [`ComponentEvent::Exit`]. Events that were received before the stream was created are
+    /// yielded first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the signal listeners can't be registered with tokio.
+    pub fn event_stream(&self) -> io::Result<EventStream<'_>> {
+        let listeners = listen(self.signal_events().signals())?;
+
+        Ok(EventStream {
+            signals: self.signal_events(),
+            listeners,
+            done: false,
+        })
+    }
+
+    /// Get a stream that yields the component's resources every `period`
+    ///
+    /// The first item is yielded immediately. Like [`HalComponent::run_at`], ticks that are missed
+    /// because the consumer was busy are skipped rather than yielded back to back. The stream ends
+    /// once the component is signalled to exit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `period` is zero.
+    pub fn updates(&self, period: Duration) -> Updates<'_, R, S> {
+        Updates {
+            comp: self,
+            interval: interval(period),
+            done: false,
+        }
+    }
+}
+
+/// Stream of a component's events, created by [`HalComponent::event_stream`]
+#[derive(Debug)]
+pub struct EventStream<'a> {
+    signals: &'a SignalEvents,
+    listeners: Vec<Signal>,
+    done: bool,
+}
+
+impl Stream for EventStream<'_> {
+    type Item = ComponentEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+
+        loop {
+            // The listeners are woken after the signals are recorded, so a wakeup always has an
+            // event to go with it unless another reader took it first
+            if let Some(event) = self.signals.try_next() {
+                self.done = event == ComponentEvent::Exit;
+
+                return Poll::Ready(Some(event));
+            }
+
+            let mut woken = false;
+
+            for listener in self.listeners.iter_mut() {
+                woken |= listener.poll_recv(cx).is_ready();
+            }
+
+            if !woken {
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+/// Stream of a component's resources, created by [`HalComponent::updates`]
+#[derive(Debug)]
+pub struct Updates<'a, R, S = ()> {
+    comp: &'a HalComponent<R, S>,
+    interval: Interval,
+    done: bool,
+}
+
+impl<'a, R, S> Stream for Updates<'a, R, S>
+where
+    R: Resources,
+{
+    type Item = &'a R;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+
+        ready!(self.interval.poll_tick(cx));
+
+        if self.comp.should_exit() {
+            self.done = true;
+
+            Poll::Ready(None)
+        } else {
+            Poll::Ready(Some(self.comp.resources()))
+        }
+    }
+}
+
+impl<S> InputPin<S>
+where
+    Self: PinRead + HalPin<Storage = S>,
+    S: SharedValue + PartialEq,
+{
+    /// Get a stream of this pin's values that yields a new item whenever the value changes
+    ///
+    /// Like `tokio::sync::watch`, the pin's current value is yielded first. The pin is then read
+    /// every `poll_period`, and values that are the same as the last one yielded are skipped.
+    /// Changes that are undone within one period are never seen.
+    ///
+    /// The stream is independent of [`InputPin::changed_since_last`] and the other change
+    /// detection methods, and any number of streams can watch the same pin.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `poll_period` is zero.
+    pub fn watch(&self, poll_period: Duration) -> Watch<'_, Self> {
+        Watch {
+            pin: self,
+            interval: interval(poll_period),
+            last: None,
+        }
+    }
+}
+
+/// Stream of changes to a pin's value, created by [`InputPin::watch`]
+#[derive(Debug)]
+pub struct Watch<'a, P>
+where
+    P: HalPin,
+{
+    pin: &'a P,
+    interval: Interval,
+    last: Option<P::Storage>,
+}
+
+// Nothing in a `Watch` is structurally pinned
+impl<'a, P> Unpin for Watch<'a, P> where P: HalPin {}
+
+impl<'a, P> Stream for Watch<'a, P>
+where
+    P: PinRead,
+    P::Storage: PartialEq,
+{
+    type Item = P::Storage;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            ready!(self.interval.poll_tick(cx));
+
+            let value = self.pin.get();
+
+            if self.last != Some(value) {
+                self.last = Some(value);
+
+                return Poll::Ready(Some(value));
+            }
+        }
+    }
+}
+
+/// Register tokio listeners for each of `signals`
+fn listen(signals: impl Iterator<Item = c_int>) -> io::Result<Vec<Signal>> {
+    signals
+        .map(|signal_number| signal(SignalKind::from_raw(signal_number)))
+        .collect()
+}
+
+/// Create an interval that skips missed ticks
+fn interval(period: Duration) -> Interval {
+    let mut interval = time::interval(period);
+
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    interval
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::{hal_pin::BidirectionalPin, mock, prelude::*};
+    use tokio_stream::StreamExt;
+
+    #[derive(crate::Resources)]
+    struct Pins {
+        speed: InputPin<f64>,
+        count: BidirectionalPin<u32>,
+    }
+
+    #[tokio::test]
+    async fn watch() -> Result<(), Box<dyn std::error::Error>> {
+        let comp = HalComponent::<Pins>::new("mock-async-watch")?;
+        let mut speed = comp.resources().speed.watch(Duration::from_millis(1));
+
+        // The current value is yielded first
+        assert_eq!(speed.next().await, Some(0.0));
+
+        mock::set_pin_value("mock-async-watch.speed", 1.5)?;
+        assert_eq!(speed.next().await, Some(1.5));
+
+        // Unchanged values are skipped
+        time::sleep(Duration::from_millis(5)).await;
+        mock::set_pin_value("mock-async-watch.speed", 2.0)?;
+        assert_eq!(speed.next().await, Some(2.0));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn updates() -> Result<(), Box<dyn std::error::Error>> {
+        let comp = HalComponent::<Pins>::new("mock-async-updates")?;
+        let mut updates = comp.updates(Duration::from_millis(1)).take(3);
+
+        while let Some(pins) = updates.next().await {
+            pins.count.set(pins.count.get() + 1);
+        }
+
+        assert_eq!(
+            mock::pin_value("mock-async-updates.count"),
+            Some(mock::Value::U32(3))
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn event_stream() -> Result<(), Box<dyn std::error::Error>> {
+        let comp = HalComponent::<Pins>::builder("mock-async-events")
+            .signals(&[libc::SIGPROF])
+            .on_signal(libc::SIGTTIN, ComponentEvent::Reload)
+            .build()?
+            .ready()?;
+
+        let mut events = comp.event_stream()?;
+
+        tokio::spawn(async {
+            time::sleep(Duration::from_millis(5)).await;
+            unsafe { libc::raise(libc::SIGTTIN) };
+
+            time::sleep(Duration::from_millis(5)).await;
+            unsafe { libc::raise(libc::SIGPROF) };
+        });
+
+        assert_eq!(events.next().await, Some(ComponentEvent::Reload));
+        assert_eq!(events.next().await, Some(ComponentEvent::Exit));
+        assert_eq!(events.next().await, None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,628 @@
+//! Step by step component creation
+
+use crate::{
+    error::ComponentInitError, event::ComponentEvent, HalComponent, HalFunction, Instance,
+    InstanceRef, RegisterResources, Resources,
+};
+use std::{error::Error, fmt, mem, os::raw::c_int};
+
+/// A function called just before a component is marked as ready
+type PreReadyHook<R, S> =
+    Box<dyn FnOnce(&UnreadyHalComponent<R, S>) -> Result<(), Box<dyn Error + Send + Sync>>>;
+
+/// Configure and create a [`HalComponent`]
+///
+/// [`HalComponent::new`] creates a component and marks it as ready in one step, which is what most
+/// components need. The builder allows more control:
+///
+/// * [`prefix`](HalComponentBuilder::prefix) registers resources under a different name than the
+///   component, like `halcompile`'s `prefix` option
+/// * [`count`](HalComponentBuilder::count) and [`names`](HalComponentBuilder::names) register
+///   several instances of the component's resources, like `loadrt` with `count=` or `names=`
+/// * [`signals`](HalComponentBuilder::signals) chooses which Unix signals
+///   [`HalComponent::should_exit`] responds to, or turns signal handling off when the component is
+///   part of a larger application which handles signals itself
+/// * [`on_signal`](HalComponentBuilder::on_signal) reports other signals as
+///   [`ComponentEvent`]s, for example to reload configuration on `SIGHUP`
+/// * [`function`](HalComponentBuilder::function) exports functions that use the component's state,
+///   created with [`HalFunction::with_state`]
+/// * [`pre_ready`](HalComponentBuilder::pre_ready) adds hooks which are run just before the
+///   component is marked as ready, for example to check that hardware is connected
+///
+/// [`build`](HalComponentBuilder::build) registers the component and its resources, returning an
+/// [`UnreadyHalComponent`]. LinuxCNC waits for a component loaded with `loadusr -W` until it's
+/// ready, so slow setup can be done and extra resources registered before calling
+/// [`UnreadyHalComponent::ready`].
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use linuxcnc_hal::{
+///     hal_pin::{InputPin, OutputPin},
+///     prelude::*,
+///     HalComponent, Resources,
+/// };
+/// use std::{error::Error, thread, time::Duration};
+///
+/// #[derive(Resources)]
+/// struct Pins {
+///     enable: InputPin<bool>,
+/// }
+///
+/// fn main() -> Result<(), Box<dyn Error>> {
+///     let debug = std::env::args().any(|arg| arg == "--debug");
+///
+///     let comp = HalComponent::<Pins>::builder("vfd")
+///         // Registers `spindle.0.enable` instead of `vfd.enable`
+///         .prefix("spindle.0")
+///         // Only exit on `SIGTERM`
+///         .signals(&[libc::SIGTERM])
+///         .pre_ready(|comp| {
+///             println!("Connecting to VFD for {}", comp.name());
+///
+///             Ok(())
+///         })
+///         .build()?;
+///
+///     // Register a pin that's only needed in some configurations. It keeps the component
+///     // registered while it exists.
+///     let debug_pin = if debug {
+///         Some(comp.registrar().register_pin::<OutputPin<f64>>("debug")?)
+///     } else {
+///         None
+///     };
+///
+///     let comp = comp.ready()?;
+///
+///     while !comp.should_exit() {
+///         if let Some(pin) = &debug_pin {
+///             pin.set(1.0);
+///         }
+///
+///         thread::sleep(Duration::from_millis(10));
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub struct HalComponentBuilder<R, S = ()> {
+    name: String,
+    prefix: Option<String>,
+    instances: InstanceNames,
+    events: Vec<(c_int, ComponentEvent)>,
+    state: S,
+
+    /// Copies the state for each instance, set when there may be more than one
+    clone_state: Option<fn(&S) -> S>,
+
+    functions: Vec<HalFunction<R, S>>,
+    hooks: Vec<PreReadyHook<R, S>>,
+}
+
+/// The instances of a component's resources to register
+#[derive(Debug)]
+enum InstanceNames {
+    /// One instance, prefixed with the component's prefix
+    Single,
+
+    /// Instances prefixed with the component's prefix and their index
+    Count(usize),
+
+    /// Instances prefixed with each name
+    Names(Vec<String>),
+}
+
+impl<R, S> HalComponentBuilder<R, S>
+where
+    R: Resources,
+    S: Default,
+{
+    /// Create a builder for a component with the given name
+    ///
+    /// By default, resource names are prefixed with the component name and the component exits on
+    /// `SIGINT` and `SIGTERM`. The component's state starts as `S::default()`.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self::with_state(name, S::default())
+    }
+}
+
+impl<R, S> HalComponentBuilder<R, S>
+where
+    R: Resources,
+{
+    /// Create a builder for a component with the given name which owns `state`
+    ///
+    /// See [`HalComponent::with_state`].
+    pub fn with_state(name: impl Into<String>, state: S) -> Self {
+        Self {
+            name: name.into(),
+            prefix: None,
+            instances: InstanceNames::Single,
+            events: vec![
+                (signal_hook::SIGTERM, ComponentEvent::Exit),
+                (signal_hook::SIGINT, ComponentEvent::Exit),
+            ],
+            state,
+            clone_state: None,
+            functions: Vec::new(),
+            hooks: Vec::new(),
+        }
+    }
+
+    /// Get the name of the component being built
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Set the prefix added to the name of every pin, parameter and function
+    ///
+    /// For example, a component named `vfd` with the prefix `spindle.0` and a pin named `speed`
+    /// registers the pin as `spindle.0.speed`.
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = Some(prefix.into());
+
+        self
+    }
+
+    /// Set the Unix signals which cause [`HalComponent::should_exit`] to return true
+    ///
+    /// The given signals replace the default of `SIGINT` and `SIGTERM`, and are reported as
+    /// [`ComponentEvent::Exit`]. Signals added with [`on_signal`](HalComponentBuilder::on_signal)
+    /// are kept. Pass an empty list to leave exit signals to the rest of the application, in which
+    /// case `should_exit` always returns false.
+    ///
+    /// Handlers for signals that can't be caught, like `SIGKILL`, fail to register when the
+    /// component is built.
+    pub fn signals(mut self, signals: &[c_int]) -> Self {
+        self.events
+            .retain(|(signal, event)| *event != ComponentEvent::Exit && !signals.contains(signal));
+
+        self.events
+            .extend(signals.iter().map(|signal| (*signal, ComponentEvent::Exit)));
+
+        self
+    }
+
+    /// Report a Unix signal as the given event
+    ///
+    /// Events are read with [`HalComponent::pending_events`] or [`HalComponent::events`]. This
+    /// replaces any earlier event for the same signal, so e.g. `SIGINT` can be turned into
+    /// something other than [`ComponentEvent::Exit`]. Mapping a signal to `ComponentEvent::Exit`
+    /// also makes [`HalComponent::should_exit`] return true once it's received.
+    ///
+    /// Handlers for signals that can't be caught, like `SIGKILL`, fail to register when the
+    /// component is built.
+    pub fn on_signal(mut self, signal: c_int, event: ComponentEvent) -> Self {
+        self.events.retain(|(s, _event)| *s != signal);
+
+        self.events.push((signal, event));
+
+        self
+    }
+
+    /// Export a function to the HAL along with the functions from [`Resources::functions`]
+    ///
+    /// Functions that use the component's state are created with [`HalFunction::with_state`] and
+    /// exported this way, as `Resources::functions` doesn't know the type of the state.
+    pub fn function(mut self, function: HalFunction<R, S>) -> Self
+    where
+        R: Send + Sync,
+        S: Send,
+    {
+        self.functions.push(function);
+
+        self
+    }
+
+    /// Add a function to call just before the component is marked as ready
+    ///
+    /// Hooks are called in the order they were added by [`UnreadyHalComponent::ready`]. If a hook
+    /// returns an error, the remaining hooks aren't called, the component is removed and
+    /// [`ComponentInitError::PreReady`] is returned.
+    pub fn pre_ready<F>(mut self, hook: F) -> Self
+    where
+        F: FnOnce(&UnreadyHalComponent<R, S>) -> Result<(), Box<dyn Error + Send + Sync>> + 'static,
+    {
+        self.hooks.push(Box::new(hook));
+
+        self
+    }
+
+    /// Create the component, register its resources and signal handlers and export its functions
+    ///
+    /// The component isn't ready until [`UnreadyHalComponent::ready`] is called.
+    ///
+    /// # Errors
+    ///
+    /// As well as the errors from registering the component and its resources, this returns
+    /// [`ComponentInitError::Instances`] if the component was given a count of zero or no names.
+    pub fn build(self) -> Result<UnreadyHalComponent<R, S>, ComponentInitError> {
+        let Self {
+            name,
+            prefix,
+            instances,
+            events,
+            state,
+            clone_state,
+            functions,
+            hooks,
+        } = self;
+
+        let prefix = prefix.unwrap_or_else(|| name.clone());
+
+        let prefixes = match instances {
+            InstanceNames::Single => vec![prefix.clone()],
+            InstanceNames::Count(count) => (0..count)
+                .map(|index| format!("{}.{}", prefix, index))
+                .collect(),
+            InstanceNames::Names(names) => names,
+        };
+
+        if prefixes.is_empty() {
+            return Err(ComponentInitError::Instances {
+                name,
+                reason: "a component must have at least one instance".to_string(),
+            });
+        }
+
+        let mut states = match clone_state {
+            Some(clone_state) => (1..prefixes.len()).map(|_| clone_state(&state)).collect(),
+            None => Vec::new(),
+        };
+
+        states.insert(0, state);
+
+        let instances = prefixes.into_iter().zip(states).collect();
+
+        let (comp, register) = HalComponent::init(name, prefix, instances, events, functions)?;
+
+        Ok(UnreadyHalComponent {
+            register,
+            comp,
+            hooks,
+        })
+    }
+}
+
+impl<R, S> HalComponentBuilder<R, S>
+where
+    R: Resources,
+    S: Clone,
+{
+    /// Register `count` instances of the component's resources, like `loadrt` with `count=`
+    ///
+    /// Each instance is prefixed with the component's prefix and its index, so a component named
+    /// `ddt` with a pin named `in` registers `ddt.0.in`, `ddt.1.in` and so on. Every instance has
+    /// its own copy of the state and its own copy of each exported function, e.g. `ddt.0.update`.
+    /// See [`HalComponent::instances`].
+    pub fn count(mut self, count: usize) -> Self {
+        self.instances = InstanceNames::Count(count);
+        self.clone_state = Some(S::clone);
+
+        self
+    }
+
+    /// Register an instance of the component's resources for each name, like `loadrt` with
+    /// `names=`
+    ///
+    /// The names are used as prefixes instead of the component's prefix, so a component with a pin
+    /// named `in` and the names `x` and `y` registers `x.in` and `y.in`. Otherwise this works like
+    /// [`count`](HalComponentBuilder::count).
+    pub fn names<I>(mut self, names: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.instances = InstanceNames::Names(names.into_iter().map(Into::into).collect());
+        self.clone_state = Some(S::clone);
+
+        self
+    }
+}
+
+impl<R, S> fmt::Debug for HalComponentBuilder<R, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HalComponentBuilder")
+            .field("name", &self.name)
+            .field("prefix", &self.prefix)
+            .field("instances", &self.instances)
+            .field("events", &self.events)
+            .field("functions", &self.functions)
+            .field("hooks", &self.hooks.len())
+            .finish()
+    }
+}
+
+/// A component that has been created but isn't ready yet
+///
+/// Created by [`HalComponentBuilder::build`]. Dropping this removes the component from the HAL.
+pub struct UnreadyHalComponent<R, S = ()> {
+    /// Used to register extra resources
+    ///
+    /// This holds a handle to the component, so it must be declared before `comp` to be dropped
+    /// first.
+    register: RegisterResources,
+
+    comp: HalComponent<R, S>,
+
+    hooks: Vec<PreReadyHook<R, S>>,
+}
+
+impl<R, S> UnreadyHalComponent<R, S>
+where
+    R: Resources,
+{
+    /// Get the HAL-assigned ID for this component
+    pub fn id(&self) -> i32 {
+        self.comp.id()
+    }
+
+    /// Get the component name
+    pub fn name(&self) -> &str {
+        self.comp.name()
+    }
+
+    /// Get a reference to the component's resources
+    pub fn resources(&self) -> &R {
+        self.comp.resources()
+    }
+
+    /// Get a reference to the component's state
+    ///
+    /// Functions that use the state are skipped until the returned guard is dropped.
+    pub fn state(&self) -> InstanceRef<'_, S> {
+        self.comp.state()
+    }
+
+    /// Get an iterator over the component's instances
+    pub fn instances(&self) -> impl ExactSizeIterator<Item = &Instance<R, S>> {
+        self.comp.instances()
+    }
+
+    /// Register extra pins and parameters with the component
+    ///
+    /// The HAL only allows resources to be registered until the component is ready. Resources
+    /// registered here aren't part of the component's [`Resources`], so they must be kept
+    /// somewhere else. Each one keeps the component registered with the HAL until it's dropped.
+    pub fn registrar(&self) -> &RegisterResources {
+        &self.register
+    }
+
+    /// Run the pre-ready hooks, then mark the component as ready
+    ///
+    /// If a hook fails or the component can't be marked ready, the component is removed from the
+    /// HAL.
+    pub fn ready(mut self) -> Result<HalComponent<R, S>, ComponentInitError> {
+        for hook in mem::take(&mut self.hooks) {
+            hook(&self).map_err(|source| ComponentInitError::PreReady {
+                name: self.name().to_string(),
+                source,
+            })?;
+        }
+
+        let Self { register, comp, .. } = self;
+
+        drop(register);
+
+        comp.ready()?;
+
+        Ok(comp)
+    }
+}
+
+impl<R, S> fmt::Debug for UnreadyHalComponent<R, S>
+where
+    R: fmt::Debug,
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UnreadyHalComponent")
+            .field("comp", &self.comp)
+            .field("hooks", &self.hooks.len())
+            .finish()
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::{hal_pin::InputPin, mock, prelude::*};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug, crate::Resources)]
+    struct Pins {
+        input: InputPin<f64>,
+    }
+
+    #[test]
+    fn prefix() -> Result<(), Box<dyn Error>> {
+        let comp = HalComponent::<Pins>::builder("mock-builder-name")
+            .prefix("mock-builder-prefix.0")
+            .build()?
+            .ready()?;
+
+        assert_eq!(comp.name(), "mock-builder-name");
+        assert!(mock::pin_names().contains(&"mock-builder-prefix.0.input".to_string()));
+        assert!(!mock::pin_names().contains(&"mock-builder-name.input".to_string()));
+
+        mock::set_pin_value("mock-builder-prefix.0.input", 2.5)?;
+        assert_eq!(comp.resources().input.get(), 2.5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn deferred_ready() -> Result<(), Box<dyn Error>> {
+        let comp = HalComponent::<Pins>::builder("mock-builder-deferred").build()?;
+
+        assert_eq!(
+            mock::component_is_ready("mock-builder-deferred"),
+            Some(false)
+        );
+
+        let extra = comp.registrar().register_pin::<InputPin<bool>>("extra")?;
+
+        let comp = comp.ready()?;
+
+        assert_eq!(
+            mock::component_is_ready("mock-builder-deferred"),
+            Some(true)
+        );
+
+        mock::set_pin_value("mock-builder-deferred.extra", true)?;
+        assert!(extra.get());
+
+        drop(comp);
+        drop(extra);
+
+        assert_eq!(mock::component_id("mock-builder-deferred"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn pre_ready_hooks() -> Result<(), ComponentInitError> {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+
+        let comp = HalComponent::<Pins>::builder("mock-builder-hooks")
+            .pre_ready({
+                let calls = Arc::clone(&calls);
+
+                move |comp| {
+                    // The component isn't ready while hooks run
+                    assert_eq!(mock::component_is_ready(comp.name()), Some(false));
+
+                    calls.lock().unwrap().push("first");
+
+                    Ok(())
+                }
+            })
+            .pre_ready({
+                let calls = Arc::clone(&calls);
+
+                move |_comp| {
+                    calls.lock().unwrap().push("second");
+
+                    Ok(())
+                }
+            })
+            .build()?;
+
+        assert!(calls.lock().unwrap().is_empty());
+
+        comp.ready()?;
+
+        assert_eq!(*calls.lock().unwrap(), vec!["first", "second"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn failed_hook() -> Result<(), ComponentInitError> {
+        let calls = Arc::new(Mutex::new(0));
+
+        let result = HalComponent::<Pins>::builder("mock-builder-failed-hook")
+            .pre_ready(|_comp| Err("hardware not found".into()))
+            .pre_ready({
+                let calls = Arc::clone(&calls);
+
+                move |_comp| {
+                    *calls.lock().unwrap() += 1;
+
+                    Ok(())
+                }
+            })
+            .build()?
+            .ready();
+
+        assert!(matches!(
+            result,
+            Err(ComponentInitError::PreReady { name, source })
+                if name == "mock-builder-failed-hook" && source.to_string() == "hardware not found"
+        ));
+        assert_eq!(*calls.lock().unwrap(), 0);
+        assert_eq!(mock::component_id("mock-builder-failed-hook"), None);
+
+        Ok(())
+    }
+
+    fn count_calls(_pins: &Pins, calls: &mut u32, _period: std::time::Duration) {
+        *calls += 1;
+    }
+
+    #[test]
+    fn count() -> Result<(), Box<dyn Error>> {
+        let mut comp = HalComponentBuilder::<Pins, u32>::with_state("mock-builder-count", 10)
+            .count(2)
+            .function(HalFunction::with_state("count", count_calls))
+            .build()?
+            .ready()?;
+
+        assert_eq!(
+            comp.instances().map(Instance::name).collect::<Vec<_>>(),
+            vec!["mock-builder-count.0", "mock-builder-count.1"]
+        );
+
+        mock::set_pin_value("mock-builder-count.1.input", 1.5)?;
+        assert_eq!(
+            comp.instances().nth(1).unwrap().resources().input.get(),
+            1.5
+        );
+        assert_eq!(comp.resources().input.get(), 0.0);
+
+        // Each instance has its own copy of the state and functions
+        mock::call_funct("mock-builder-count.1.count", 1_000_000)?;
+        assert_eq!(
+            comp.instances().map(|i| *i.state()).collect::<Vec<_>>(),
+            vec![10, 11]
+        );
+
+        for instance in comp.instances_mut() {
+            *instance.state_mut() = 0;
+        }
+
+        assert_eq!(*comp.state(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn names() -> Result<(), Box<dyn Error>> {
+        let comp = HalComponent::<Pins>::builder("mock-builder-names")
+            .names(vec!["mock-builder-x", "mock-builder-y"])
+            .build()?
+            .ready()?;
+
+        assert_eq!(comp.instances().len(), 2);
+        assert!(mock::pin_names().contains(&"mock-builder-x.input".to_string()));
+        assert!(mock::pin_names().contains(&"mock-builder-y.input".to_string()));
+        assert!(!mock::pin_names().contains(&"mock-builder-names.input".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn no_instances() {
+        let result = HalComponent::<Pins>::builder("mock-builder-no-instances")
+            .count(0)
+            .build();
+
+        assert!(matches!(
+            result,
+            Err(ComponentInitError::Instances { name, .. }) if name == "mock-builder-no-instances"
+        ));
+        assert_eq!(mock::component_id("mock-builder-no-instances"), None);
+    }
+
+    #[test]
+    fn no_signals() -> Result<(), ComponentInitError> {
+        let comp = HalComponent::<Pins>::builder("mock-builder-no-signals")
+            .signals(&[])
+            .build()?
+            .ready()?;
+
+        assert!(!comp.should_exit());
+
+        Ok(())
+    }
+}
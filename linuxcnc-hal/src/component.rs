@@ -1,7 +1,293 @@
-use crate::{error::ComponentInitError, RegisterResources, Resources};
+use crate::{
+    builder::HalComponentBuilder,
+    error::{ComponentInitError, ResourcesError},
+    event::{ComponentEvent, Events, PendingEvents, SignalEvents},
+    hal_function::ExportedFunction,
+    run_loop::{self, LoopContext},
+    HalFunction, ProcessImage, RegisterResources, Resources,
+};
 use linuxcnc_hal_sys::{hal_exit, hal_init, hal_ready, EINVAL, ENOMEM, HAL_NAME_LEN};
-use signal_hook::iterator::Signals;
-use std::ffi::CString;
+use std::{
+    cell::UnsafeCell,
+    ffi::CString,
+    fmt, mem,
+    ops::{Deref, DerefMut},
+    os::raw::c_int,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+/// A component's registration with the HAL
+///
+/// This is shared between a [`HalComponent`] and every pin and parameter registered with it.
+/// [`hal_exit`] is called when the last of them is dropped, so a resource can never refer to a
+/// component that has been removed from the HAL, even if it's moved out of the component's
+/// resources during registration.
+///
+/// Resources aren't tied to the component with a lifetime or a brand type. Either would make
+/// `HalComponent` borrow from itself, since it owns its resources, and would add a parameter to
+/// every pin and parameter type for the uncommon case of a resource escaping registration.
+#[derive(Debug)]
+pub(crate) struct ComponentHandle {
+    /// Component name
+    name: String,
+
+    /// Component ID
+    id: i32,
+}
+
+impl ComponentHandle {
+    /// Get the HAL-assigned ID for this component
+    pub(crate) fn id(&self) -> i32 {
+        self.id
+    }
+
+    /// Get the component name
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Drop for ComponentHandle {
+    fn drop(&mut self) {
+        debug!("Closing component ID {}, name {}", self.id, self.name);
+
+        unsafe {
+            hal_exit(self.id);
+        }
+    }
+}
+
+/// One instance of a component's resources and user state
+///
+/// Most components have a single instance, which is used by [`HalComponent::resources`],
+/// [`HalComponent::state`] and the other methods on the component. Components built with
+/// [`HalComponentBuilder::count`] or [`HalComponentBuilder::names`] have several independent
+/// instances, each with its own resources, state and exported functions, which are accessed with
+/// [`HalComponent::instances`] and [`HalComponent::instances_mut`].
+#[derive(Debug)]
+pub struct Instance<R, S = ()> {
+    /// The prefix added to the names of this instance's resources and functions
+    pub(crate) name: String,
+
+    /// Pins, parameters and other resources registered with the HAL
+    pub(crate) resources: R,
+
+    /// User state
+    ///
+    /// This is only borrowed while holding `state_lock`.
+    pub(crate) state: UnsafeCell<S>,
+
+    /// Held shared by exported functions while they use the resources, and exclusively by
+    /// [`Instance::resources_mut`]
+    pub(crate) resources_lock: InstanceLock,
+
+    /// Held exclusively by functions that use the state and by [`Instance::state_mut`] and
+    /// [`Instance::split`], and shared by [`Instance::state`]
+    pub(crate) state_lock: InstanceLock,
+}
+
+// SAFETY: Exported functions may run in any HAL thread, or in the thread calling
+// `mock::call_funct` while the component is borrowed. Every access to the state goes through
+// `state_lock`, and functions hold `resources_lock` while using the resources, so a mutable
+// reference to either is never handed out while another reference to it exists.
+unsafe impl<R, S> Sync for Instance<R, S>
+where
+    R: Send + Sync,
+    S: Send + Sync,
+{
+}
+
+impl<R, S> Instance<R, S> {
+    /// Get the prefix added to the names of this instance's resources and functions, e.g.
+    /// `rust-comp.0`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get a reference to this instance's resources
+    pub fn resources(&self) -> &R {
+        &self.resources
+    }
+
+    /// Get a mutable reference to this instance's resources
+    ///
+    /// This waits for any exported functions that are running to finish. Exported functions are
+    /// skipped until the returned guard is dropped.
+    pub fn resources_mut(&mut self) -> InstanceRefMut<'_, R> {
+        self.resources_lock.lock_exclusive();
+
+        InstanceRefMut {
+            value: &mut self.resources,
+            lock: &self.resources_lock,
+        }
+    }
+
+    /// Get a reference to this instance's state
+    ///
+    /// This waits for any exported functions using the state to finish. Functions that use the
+    /// state are skipped until the returned guard is dropped.
+    pub fn state(&self) -> InstanceRef<'_, S> {
+        self.state_lock.lock_shared();
+
+        InstanceRef {
+            // SAFETY: The state is only borrowed mutably while `state_lock` is held exclusively
+            value: unsafe { &*self.state.get() },
+            lock: &self.state_lock,
+        }
+    }
+
+    /// Get a mutable reference to this instance's state
+    ///
+    /// This waits for any exported functions using the state to finish. Functions that use the
+    /// state are skipped until the returned guard is dropped.
+    pub fn state_mut(&mut self) -> InstanceRefMut<'_, S> {
+        self.state_lock.lock_exclusive();
+
+        InstanceRefMut {
+            value: self.state.get_mut(),
+            lock: &self.state_lock,
+        }
+    }
+
+    /// Borrow this instance's resources and mutably borrow its state at the same time
+    ///
+    /// The state is locked like [`Instance::state_mut`].
+    pub fn split(&mut self) -> (&R, InstanceRefMut<'_, S>) {
+        self.state_lock.lock_exclusive();
+
+        (
+            &self.resources,
+            InstanceRefMut {
+                value: self.state.get_mut(),
+                lock: &self.state_lock,
+            },
+        )
+    }
+}
+
+/// Reader/writer lock shared between an instance's exported functions and its accessors
+///
+/// Exported functions run in HAL threads and mustn't wait, so they skip their call if the lock is
+/// taken. The accessors on [`Instance`] aren't used in realtime code, so they wait for functions
+/// that are running to finish.
+#[derive(Debug, Default)]
+pub(crate) struct InstanceLock {
+    /// The number of shared holders, or `EXCLUSIVE`
+    holders: AtomicUsize,
+}
+
+impl InstanceLock {
+    const EXCLUSIVE: usize = usize::MAX;
+
+    /// Try to take the lock alongside other shared holders
+    pub(crate) fn try_lock_shared(&self) -> bool {
+        self.holders
+            .fetch_update(Ordering::Acquire, Ordering::Relaxed, |holders| {
+                if holders >= Self::EXCLUSIVE - 1 {
+                    None
+                } else {
+                    Some(holders + 1)
+                }
+            })
+            .is_ok()
+    }
+
+    /// Try to take the lock if there are no other holders
+    pub(crate) fn try_lock_exclusive(&self) -> bool {
+        self.holders
+            .compare_exchange(0, Self::EXCLUSIVE, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    fn lock_shared(&self) {
+        while !self.try_lock_shared() {
+            thread::yield_now();
+        }
+    }
+
+    fn lock_exclusive(&self) {
+        while !self.try_lock_exclusive() {
+            thread::yield_now();
+        }
+    }
+
+    pub(crate) fn unlock_shared(&self) {
+        self.holders.fetch_sub(1, Ordering::Release);
+    }
+
+    pub(crate) fn unlock_exclusive(&self) {
+        self.holders.store(0, Ordering::Release);
+    }
+}
+
+/// A shared borrow of an instance's state, returned by [`Instance::state`] and
+/// [`HalComponent::state`]
+///
+/// Exported functions that use the state are skipped until this is dropped.
+pub struct InstanceRef<'a, T> {
+    value: &'a T,
+    lock: &'a InstanceLock,
+}
+
+impl<T> Deref for InstanceRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T> Drop for InstanceRef<'_, T> {
+    fn drop(&mut self) {
+        self.lock.unlock_shared();
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for InstanceRef<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+/// A mutable borrow of an instance's resources or state, returned by
+/// [`Instance::resources_mut`], [`Instance::state_mut`] and [`Instance::split`]
+///
+/// Exported functions that use the borrowed resources or state are skipped until this is dropped.
+pub struct InstanceRefMut<'a, T> {
+    value: &'a mut T,
+    lock: &'a InstanceLock,
+}
+
+impl<T> Deref for InstanceRefMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T> DerefMut for InstanceRefMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.value
+    }
+}
+
+impl<T> Drop for InstanceRefMut<'_, T> {
+    fn drop(&mut self) {
+        self.lock.unlock_exclusive();
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for InstanceRefMut<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
 
 /// HAL component
 ///
@@ -9,68 +295,213 @@ use std::ffi::CString;
 ///
 /// During registraton, all resource names are prefixed with the component name and a `.` full stop
 /// character. For example, a component named `rust-comp` with a pin named `input-1` will show up in
-/// LinuxCNC as a pin called `rust-comp.input-1`.
+/// LinuxCNC as a pin called `rust-comp.input-1`. A different prefix can be set with
+/// [`HalComponentBuilder::prefix`].
 ///
 /// `HalComponent` has a custom `Drop` implementation which calls [`hal_exit`] (among other things)
 /// when the variable holding the component goes out of scope. Due to this, the component should be
 /// initialised in `main()` so it lives for the entire life of the program.
+///
+/// Every pin and parameter holds a handle to the component's registration, so `hal_exit` is
+/// deferred until the last of them is dropped if any were moved out of the resources while they
+/// were being registered. Exported functions are leaked in that case, as the HAL may still call
+/// them until `hal_exit`, although they no longer run once the component is dropped.
+///
+/// A component can also own user state of type `S`, for example filters, counters or previous
+/// values, which is kept alongside the resources. See [`HalComponent::with_state`].
+///
+/// Like realtime components loaded with `count=` or `names=`, a component can have several
+/// [`Instance`]s of its resources and state, each registered under its own prefix. See
+/// [`HalComponentBuilder::count`].
 #[derive(Debug)]
-pub struct HalComponent<R> {
-    /// Component name
-    name: &'static str,
+pub struct HalComponent<R, S = ()> {
+    /// Handles to Unix signals and the events they're reported as
+    signals: SignalEvents,
 
-    /// Component ID
-    id: i32,
+    /// Handles to resources (pins, signals, etc) used in the component, and the user state, for
+    /// each instance
+    ///
+    /// This is never empty until the component is dropped. The instances are cleared before the
+    /// component itself is dropped, as resources references to shared memory in LinuxCNC's HAL
+    /// must be freed before [`hal_exit`] is called.
+    ///
+    /// Each instance is boxed so exported functions can hold a pointer to it that stays valid when
+    /// the component is moved.
+    #[allow(clippy::vec_box)]
+    instances: Vec<Box<Instance<R, S>>>,
 
-    /// Handles to Unix exit signals
-    signals: Signals,
+    /// Registration with the HAL, shared with every pin and parameter
+    ///
+    /// This must be declared before `functions` so that it's dropped first. The HAL may call
+    /// exported functions until [`hal_exit`] is called.
+    component: Arc<ComponentHandle>,
 
-    /// Handle to resources (pins, signals, etc) used in the component
+    /// Functions exported to the HAL
     ///
-    /// This is an `Option` so that it can be `Drop`ped before the component itself is dropped.
-    /// Resources references to shared memory in LinuxCNC's HAL must be freed before [`hal_exit`] is
-    /// called.
-    resources: Option<R>,
+    /// These hold a pointer to an instance and are passed to the HAL as function arguments. Each
+    /// one is boxed so its address doesn't change when the vector is built.
+    #[allow(clippy::vec_box)]
+    functions: Vec<Box<ExportedFunction<R, S>>>,
 }
 
-impl<R> HalComponent<R>
+impl<R, S> HalComponent<R, S>
 where
     R: Resources,
+    S: Default,
 {
     /// Create a new HAL component
     ///
     /// `new` registers a new HAL component with LinuxCNC, registers the required UNIX signal
-    /// handlers and allocates resources (pins, signals, etc) required by the component.
-    pub fn new(name: &'static str) -> Result<Self, ComponentInitError> {
-        let id = Self::create_component(name)?;
+    /// handlers, allocates resources (pins, signals, etc) required by the component, exports any
+    /// functions returned by [`Resources::functions`] and marks the component as ready.
+    ///
+    /// The name can be computed at runtime, for example to create one component per spindle from
+    /// a command line argument.
+    ///
+    /// To change the name prefix or signal handlers, or to do more work before the component is
+    /// ready, use [`HalComponent::builder`].
+    pub fn new(name: impl Into<String>) -> Result<Self, ComponentInitError> {
+        Self::builder(name).build()?.ready()
+    }
 
-        let resources = R::register_resources(&RegisterResources { id, name })
-            .map_err(|e| ComponentInitError::ResourceRegistration(e.into()))?;
+    /// Create a builder to configure the component before it's created
+    pub fn builder(name: impl Into<String>) -> HalComponentBuilder<R, S> {
+        HalComponentBuilder::new(name)
+    }
+}
 
-        let signals = Self::register_signals()?;
+impl<R, S> HalComponent<R, S>
+where
+    R: Resources,
+{
+    /// Create a new HAL component which owns the given state
+    ///
+    /// This works like [`HalComponent::new`], but the component also holds `state`. The state can
+    /// be read with [`HalComponent::state`] and changed with [`HalComponent::state_mut`] or
+    /// [`HalComponent::split`], and is passed to functions exported with
+    /// [`HalFunction::with_state`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use linuxcnc_hal::{
+    ///     hal_pin::{InputPin, OutputPin},
+    ///     prelude::*,
+    ///     HalComponent, Resources,
+    /// };
+    /// use std::{error::Error, thread, time::Duration};
+    ///
+    /// #[derive(Resources)]
+    /// struct Pins {
+    ///     input: InputPin<f64>,
+    ///     output: OutputPin<f64>,
+    /// }
+    ///
+    /// /// A first order low pass filter
+    /// struct Filter {
+    ///     gain: f64,
+    ///     value: f64,
+    /// }
+    ///
+    /// fn main() -> Result<(), Box<dyn Error>> {
+    ///     let filter = Filter {
+    ///         gain: 0.1,
+    ///         value: 0.0,
+    ///     };
+    ///
+    ///     let mut comp = HalComponent::<Pins, _>::with_state("lowpass", filter)?;
+    ///
+    ///     while !comp.should_exit() {
+    ///         let (pins, mut filter) = comp.split();
+    ///
+    ///         filter.value += (pins.input.get() - filter.value) * filter.gain;
+    ///         pins.output.set(filter.value);
+    ///
+    ///         thread::sleep(Duration::from_millis(10));
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_state(name: impl Into<String>, state: S) -> Result<Self, ComponentInitError> {
+        HalComponentBuilder::with_state(name, state)
+            .build()?
+            .ready()
+    }
+
+    /// Create a HAL component that isn't ready yet
+    ///
+    /// An instance is registered for each prefix and state in `instances`, and each signal in
+    /// `events` is handled. The returned [`RegisterResources`] registers more resources with
+    /// `prefix` until [`HalComponent::ready`] is called.
+    pub(crate) fn init(
+        name: String,
+        prefix: String,
+        instances: Vec<(String, S)>,
+        events: Vec<(c_int, ComponentEvent)>,
+        functions: Vec<HalFunction<R, S>>,
+    ) -> Result<(Self, RegisterResources), ComponentInitError> {
+        let id = Self::create_component(&name)?;
+
+        // From here on, dropping the handle removes the component if initialisation fails
+        let component = Arc::new(ComponentHandle { name, id });
+
+        // Signal handlers are required for the component to close cleanly and to pass
+        // initialisation in LinuxCNC. If LinuxCNC hangs during starting waiting for the component
+        // to become ready, it might be due to signal handlers not being registered.
+        let signals = SignalEvents::new(events)?;
+
+        let functions = R::functions()
+            .into_iter()
+            .map(HalFunction::with_state_type)
+            .chain(functions)
+            .collect::<Vec<_>>();
 
-        let comp = Self {
-            name,
-            id,
-            resources: Some(resources),
+        // Instances and functions are added to the component as they're created, so if anything
+        // fails, dropping it detaches the functions exported so far before the instances are
+        // dropped and `hal_exit` is called
+        let mut comp = Self {
+            instances: Vec::with_capacity(instances.len()),
+            component,
+            functions: Vec::new(),
             signals,
         };
 
-        comp.ready()
-    }
+        for (prefix, state) in instances {
+            let register = RegisterResources {
+                component: comp.component.clone(),
+                prefix,
+            };
 
-    /// Register signal handlers so component closes cleanly
-    ///
-    /// These are also required for the component to pass initialisation in LinuxCNC. If LinuxCNC
-    /// hangs during starting waiting for the component to become ready, it might be due to signal
-    /// handlers not being registered.
-    fn register_signals() -> Result<Signals, ComponentInitError> {
-        let signals = Signals::new(&[signal_hook::SIGTERM, signal_hook::SIGINT])
-            .map_err(ComponentInitError::Signals)?;
+            let resources = R::register_resources(&register)
+                .map_err(|e| ComponentInitError::ResourceRegistration(e.into()))?;
 
-        debug!("Signals registered");
+            comp.instances.push(Box::new(Instance {
+                name: register.prefix,
+                resources,
+                state: UnsafeCell::new(state),
+                resources_lock: InstanceLock::default(),
+                state_lock: InstanceLock::default(),
+            }));
+        }
+
+        for instance in comp.instances.iter() {
+            for function in functions.iter().cloned() {
+                let function = ExportedFunction::export(function, &instance.name, id, &**instance)
+                    .map_err(|e| {
+                        ComponentInitError::ResourceRegistration(ResourcesError::Function(e))
+                    })?;
+
+                comp.functions.push(function);
+            }
+        }
+
+        let register = RegisterResources {
+            component: comp.component.clone(),
+            prefix,
+        };
 
-        Ok(signals)
+        Ok((comp, register))
     }
 
     /// Create a HAL component
@@ -80,89 +511,251 @@ where
     /// * [`ComponentInitError::NameLength`] - If the component name is longer than [`HAL_NAME_LEN`]
     /// * [`ComponentInitError::InvalidName`] - If the component name cannot be converted to a
     ///   [`std::ffi::CString`]
-    /// * [`ComponentInitError::Init`] - If the call to [`hal_init`] returned an [`EINVAL`] status
+    /// * [`ComponentInitError::Init`] - If the call to [`hal_init`] returned an [`EINVAL`] status,
+    ///   e.g. because a component with the same name already exists
     /// * [`ComponentInitError::Memory`] - If there is not enough memory to allocate the component
-    fn create_component(name: &'static str) -> Result<i32, ComponentInitError> {
+    /// * [`ComponentInitError::Unknown`] - If [`hal_init`] returned any other error code
+    fn create_component(name: &str) -> Result<i32, ComponentInitError> {
         if name.len() > HAL_NAME_LEN as usize {
             error!(
                 "Component name must be no longer than {} bytes",
                 HAL_NAME_LEN
             );
 
-            Err(ComponentInitError::NameLength)
+            Err(ComponentInitError::NameLength {
+                name: name.to_string(),
+            })
         } else {
-            let name_c = CString::new(name).map_err(|_| ComponentInitError::InvalidName)?;
+            let name_c = CString::new(name).map_err(|_| ComponentInitError::InvalidName {
+                name: name.to_string(),
+            })?;
 
             let id = unsafe { hal_init(name_c.as_ptr().cast()) };
 
+            let name = name.to_string();
+
             match id {
-                x if x == -(EINVAL as i32) => Err(ComponentInitError::Init),
-                x if x == -(ENOMEM as i32) => Err(ComponentInitError::Memory),
                 id if id > 0 => {
                     debug!("Init component {} with ID {}", name, id);
 
                     Ok(id)
                 }
-                code => unreachable!("Hit unreachable error code {}", code),
+                x if x == -(EINVAL as i32) => Err(ComponentInitError::Init { name }),
+                x if x == -(ENOMEM as i32) => Err(ComponentInitError::Memory { name }),
+                code => Err(ComponentInitError::Unknown { name, code }),
             }
         }
     }
 
     /// Signal to the HAL that the component is ready
-    fn ready(self) -> Result<Self, ComponentInitError> {
-        let ret = unsafe { hal_ready(self.id) };
+    pub(crate) fn ready(&self) -> Result<(), ComponentInitError> {
+        let ret = unsafe { hal_ready(self.id()) };
 
         match ret {
-            x if x == -(EINVAL as i32) => Err(ComponentInitError::Ready),
             0 => {
                 debug!("Component is ready");
 
-                Ok(self)
+                Ok(())
             }
-            ret => unreachable!("Unknown error status {} returned from hal_ready()", ret),
+            x if x == -(EINVAL as i32) => Err(ComponentInitError::Ready {
+                name: self.name().to_string(),
+            }),
+            code => Err(ComponentInitError::Unknown {
+                name: self.name().to_string(),
+                code,
+            }),
         }
     }
 
     /// Get the HAL-assigned ID for this component
     pub fn id(&self) -> i32 {
-        self.id
+        self.component.id()
     }
 
     /// Get the component name
     pub fn name(&self) -> &str {
-        self.name
+        self.component.name()
     }
 
     /// Check whether the component was signalled to shut down
+    ///
+    /// By default, this returns true once the process has received `SIGINT` or `SIGTERM`. Other
+    /// signals can be chosen with [`HalComponentBuilder::signals`]. Once this has returned true it
+    /// keeps returning true.
+    ///
+    /// Other events received while checking are kept for [`HalComponent::pending_events`] and
+    /// [`HalComponent::events`].
     pub fn should_exit(&self) -> bool {
-        self.signals.pending().any(|signal| {
-            matches!(
-                signal,
-                signal_hook::SIGTERM | signal_hook::SIGINT | signal_hook::SIGKILL
-            )
-        })
+        self.signals.should_exit()
+    }
+
+    /// Get an iterator over the events received since they were last read, without blocking
+    ///
+    /// See the [`event`](crate::event) module for the signals that are reported as events.
+    pub fn pending_events(&self) -> PendingEvents<'_> {
+        PendingEvents {
+            signals: &self.signals,
+        }
+    }
+
+    /// Get an iterator which blocks until each event is received
+    ///
+    /// The iterator ends after yielding [`ComponentEvent::Exit`]. See the [`event`](crate::event)
+    /// module for the signals that are reported as events.
+    pub fn events(&self) -> Events<'_> {
+        Events {
+            signals: &self.signals,
+            done: false,
+        }
+    }
+
+    /// Get the component's signal handlers
+    #[cfg(any(feature = "tokio", test))]
+    pub(crate) fn signal_events(&self) -> &SignalEvents {
+        &self.signals
+    }
+
+    /// Call `f` with the component's resources every `period` until the component should exit
+    ///
+    /// Iterations are scheduled against absolute deadlines so the loop doesn't drift. The loop
+    /// returns when the component receives `SIGINT` or `SIGTERM`, when `f` calls
+    /// [`LoopContext::stop`] or when `f` returns an error, which is passed on to the caller.
+    ///
+    /// The timing of each iteration is recorded in the parameters returned by
+    /// [`Resources::loop_stats`], if any. See the [`run_loop`](crate::run_loop) module for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `period` is zero.
+    pub fn run_at<F, E>(&self, period: Duration, f: F) -> Result<(), E>
+    where
+        F: FnMut(&R, &mut LoopContext) -> Result<(), E>,
+    {
+        let resources = self.resources();
+
+        run_loop::run(
+            resources,
+            resources.loop_stats(),
+            period,
+            || self.should_exit(),
+            f,
+        )
+    }
+
+    /// Read every input of the component's resources into a snapshot
+    ///
+    /// See [`ProcessImage`].
+    pub fn snapshot_inputs(&self) -> R::Inputs
+    where
+        R: ProcessImage,
+    {
+        self.resources().snapshot_inputs()
+    }
+
+    /// Write every output of the component's resources from `outputs`
+    ///
+    /// See [`ProcessImage`].
+    pub fn commit_outputs(&self, outputs: R::Outputs)
+    where
+        R: ProcessImage,
+    {
+        self.resources().commit_outputs(outputs)
     }
 
     /// Get a reference to the component's resources
+    ///
+    /// If the component has more than one instance, this returns the resources of the first.
     pub fn resources(&self) -> &R {
-        // NOTE: Unwrap is safe here due to `Some(resources)` in HalComponent::new
-        &self.resources.as_ref().unwrap()
+        self.instance().resources()
+    }
+
+    /// Get a mutable reference to the component's resources
+    ///
+    /// If the component has more than one instance, this returns the resources of the first.
+    /// Exported functions are skipped until the returned guard is dropped, see
+    /// [`Instance::resources_mut`].
+    pub fn resources_mut(&mut self) -> InstanceRefMut<'_, R> {
+        self.instance_mut().resources_mut()
+    }
+
+    /// Get a reference to the component's state
+    ///
+    /// If the component has more than one instance, this returns the state of the first.
+    /// Functions that use the state are skipped until the returned guard is dropped, see
+    /// [`Instance::state`].
+    pub fn state(&self) -> InstanceRef<'_, S> {
+        self.instance().state()
+    }
+
+    /// Get a mutable reference to the component's state
+    ///
+    /// If the component has more than one instance, this returns the state of the first.
+    /// Functions that use the state are skipped until the returned guard is dropped, see
+    /// [`Instance::state_mut`].
+    pub fn state_mut(&mut self) -> InstanceRefMut<'_, S> {
+        self.instance_mut().state_mut()
+    }
+
+    /// Borrow the component's resources and mutably borrow its state at the same time
+    ///
+    /// This allows state to be updated from pin values without copying them out first. If the
+    /// component has more than one instance, this uses the first.
+    pub fn split(&mut self) -> (&R, InstanceRefMut<'_, S>) {
+        self.instance_mut().split()
+    }
+
+    /// Get an iterator over the component's instances
+    ///
+    /// Components have a single instance unless they were built with
+    /// [`HalComponentBuilder::count`] or [`HalComponentBuilder::names`].
+    pub fn instances(&self) -> impl ExactSizeIterator<Item = &Instance<R, S>> {
+        self.instances.iter().map(|instance| &**instance)
+    }
+
+    /// Get an iterator over mutable references to the component's instances
+    pub fn instances_mut(&mut self) -> impl ExactSizeIterator<Item = &mut Instance<R, S>> {
+        self.instances.iter_mut().map(|instance| &mut **instance)
+    }
+
+    fn instance(&self) -> &Instance<R, S> {
+        // NOTE: Indexing is safe here as `HalComponentBuilder::build` checks there's at least one
+        // instance
+        &self.instances[0]
+    }
+
+    fn instance_mut(&mut self) -> &mut Instance<R, S> {
+        &mut self.instances[0]
     }
 }
 
-impl<R> Drop for HalComponent<R> {
+impl<R, S> Drop for HalComponent<R, S> {
     /// Clean up resources, signals and HAL component
     fn drop(&mut self) {
-        // Force resources to be dropped before close
-        self.resources = None;
-
-        debug!("Closing component ID {}, name {}", self.id, self.name);
+        debug!("Dropping component {}", self.component.name());
 
         self.signals.close();
 
-        unsafe {
-            hal_exit(self.id);
+        // Exported functions may be called by a HAL thread until `hal_exit`, so stop them from
+        // using the resources and state before they're dropped
+        for function in self.functions.iter() {
+            function.detach();
         }
+
+        self.instances.clear();
+
+        // If a pin or parameter outlives the component, `hal_exit` is deferred until it's dropped.
+        // The HAL holds pointers to the exported functions until then, so they must be leaked.
+        if Arc::strong_count(&self.component) > 1 {
+            warn!(
+                "Resources of component {} are still in use, deferring hal_exit",
+                self.component.name()
+            );
+
+            mem::forget(mem::take(&mut self.functions));
+        }
+
+        // `component` is dropped next, calling `hal_exit` if this was the last handle, followed by
+        // `functions`
     }
 }
 
@@ -193,9 +786,660 @@ mod tests {
         println!("{:?}", comp);
 
         match comp {
-            Err(ComponentInitError::NameLength) => Ok(()),
+            Err(ComponentInitError::NameLength { .. }) => Ok(()),
             Err(e) => Err(e),
-            Ok(_) => Err(ComponentInitError::Init),
+            Ok(comp) => Err(ComponentInitError::Init {
+                name: comp.name().to_string(),
+            }),
+        }
+    }
+
+    #[cfg(feature = "mock")]
+    mod mock {
+        use super::*;
+        use crate::{
+            error::{FunctionExportError, PinRegisterError, PortError, ResourcesError},
+            hal_pin::{BidirectionalPin, InputPin, InputPort, OutputPin, OutputPort},
+            hal_thread::HalThread,
+            mock::{self, MockError, Value},
+            prelude::*,
+            run_loop::LoopStats,
+            HalFunction, Parameter,
+        };
+        use std::{cell::RefCell, thread, time::Duration};
+
+        #[derive(Debug)]
+        struct Resources {
+            input: InputPin<f64>,
+            output: OutputPin<bool>,
+            rw: Parameter<u32>,
+            ro: Parameter<i32>,
+        }
+
+        impl crate::Resources for Resources {
+            type RegisterError = ResourcesError;
+
+            fn register_resources(comp: &RegisterResources) -> Result<Self, Self::RegisterError> {
+                Ok(Self {
+                    input: comp.register_pin("input")?,
+                    output: comp.register_pin("output")?,
+                    rw: comp.register_parameter("rw")?,
+                    ro: comp.register_readonly_parameter("ro")?,
+                })
+            }
+        }
+
+        #[test]
+        fn lifecycle() -> Result<(), ComponentInitError> {
+            let comp = HalComponent::<Resources>::new("mock-lifecycle")?;
+
+            assert_eq!(mock::component_id("mock-lifecycle"), Some(comp.id()));
+            assert_eq!(mock::component_is_ready("mock-lifecycle"), Some(true));
+            assert!(mock::pin_names().contains(&"mock-lifecycle.input".to_string()));
+            assert!(mock::param_names().contains(&"mock-lifecycle.ro".to_string()));
+
+            drop(comp);
+
+            assert_eq!(mock::component_id("mock-lifecycle"), None);
+            assert_eq!(mock::pin_value("mock-lifecycle.input"), None);
+
+            Ok(())
+        }
+
+        #[test]
+        fn runtime_name() -> Result<(), ComponentInitError> {
+            let spindle = 2;
+
+            let comp = HalComponent::<Resources>::new(format!("mock-spindle-{}", spindle))?;
+
+            assert_eq!(comp.name(), "mock-spindle-2");
+            assert!(mock::pin_names().contains(&"mock-spindle-2.input".to_string()));
+
+            Ok(())
+        }
+
+        #[test]
+        fn duplicate_name() -> Result<(), ComponentInitError> {
+            let _comp = HalComponent::<EmptyResources>::new("mock-duplicate")?;
+
+            let duplicate = HalComponent::<EmptyResources>::new("mock-duplicate");
+
+            assert!(matches!(
+                duplicate,
+                Err(ComponentInitError::Init { name }) if name == "mock-duplicate"
+            ));
+
+            Ok(())
+        }
+
+        #[derive(Debug)]
+        struct DuplicatePins {
+            _first: InputPin<f64>,
+            _second: InputPin<f64>,
+        }
+
+        impl crate::Resources for DuplicatePins {
+            type RegisterError = PinRegisterError;
+
+            fn register_resources(comp: &RegisterResources) -> Result<Self, Self::RegisterError> {
+                Ok(Self {
+                    _first: comp.register_pin("input")?,
+                    _second: comp.register_pin("input")?,
+                })
+            }
+        }
+
+        #[test]
+        fn duplicate_pin() {
+            let comp = HalComponent::<DuplicatePins>::new("mock-duplicate-pin");
+
+            match comp {
+                Err(ComponentInitError::ResourceRegistration(ResourcesError::Pin(e))) => {
+                    assert_eq!(
+                        e,
+                        PinRegisterError::Invalid {
+                            name: "mock-duplicate-pin.input".to_string()
+                        }
+                    );
+                    assert_eq!(e.errno(), -(EINVAL as i32));
+                }
+                other => panic!("Expected duplicate pin error, got {:?}", other),
+            }
+
+            // The component is removed if registering its resources fails
+            assert_eq!(mock::component_id("mock-duplicate-pin"), None);
+        }
+
+        thread_local! {
+            static ESCAPED: RefCell<Option<OutputPin<u32>>> = const { RefCell::new(None) };
+        }
+
+        /// Moves a pin out of the resources while registering them
+        struct Escape {
+            count: BidirectionalPin<u32>,
+        }
+
+        impl crate::Resources for Escape {
+            type RegisterError = PinRegisterError;
+
+            fn register_resources(comp: &RegisterResources) -> Result<Self, Self::RegisterError> {
+                let escaped = comp.register_pin("escaped")?;
+
+                ESCAPED.with(|pin| *pin.borrow_mut() = Some(escaped));
+
+                Ok(Self {
+                    count: comp.register_pin("count")?,
+                })
+            }
+
+            fn functions() -> Vec<HalFunction<Self>> {
+                vec![HalFunction::new("update", |resources: &Self, _period| {
+                    resources.count.set(resources.count.get() + 1)
+                })]
+            }
+        }
+
+        #[test]
+        fn escaped_resources() -> Result<(), Box<dyn std::error::Error>> {
+            let thread = HalThread::new("mock-escape-thread", Duration::from_millis(1), true)?;
+
+            let comp = HalComponent::<Escape>::new("mock-escape")?;
+            thread.add_function("mock-escape.update", 1)?;
+
+            mock::run_thread("mock-escape-thread")?;
+            assert_eq!(mock::pin_value("mock-escape.count"), Some(Value::U32(1)));
+
+            drop(comp);
+
+            // The escaped pin keeps the component registered, but its function no longer runs
+            assert!(mock::component_id("mock-escape").is_some());
+            mock::run_thread("mock-escape-thread")?;
+            assert_eq!(mock::pin_value("mock-escape.count"), Some(Value::U32(1)));
+
+            ESCAPED.with(|pin| pin.borrow().as_ref().unwrap().set(5));
+            assert_eq!(mock::pin_value("mock-escape.escaped"), Some(Value::U32(5)));
+
+            ESCAPED.with(|pin| pin.borrow_mut().take());
+            assert_eq!(mock::component_id("mock-escape"), None);
+
+            Ok(())
+        }
+
+        fn noop(_pins: &Resources, _period: Duration) {}
+
+        #[test]
+        fn failed_export() {
+            let comp = HalComponent::<Resources>::builder("mock-failed-export")
+                .function(HalFunction::new("update", noop))
+                .function(HalFunction::new("update", noop))
+                .build();
+
+            match comp {
+                Err(ComponentInitError::ResourceRegistration(ResourcesError::Function(e))) => {
+                    assert_eq!(
+                        e,
+                        FunctionExportError::Invalid {
+                            name: "mock-failed-export.update".to_string()
+                        }
+                    );
+                }
+                other => panic!("Expected function export error, got {:?}", other),
+            }
+
+            // The function exported before the failure is removed along with the component
+            assert_eq!(mock::component_id("mock-failed-export"), None);
+            assert!(!mock::funct_names().contains(&"mock-failed-export.update".to_string()));
+        }
+
+        #[derive(Debug, Default)]
+        struct Totals {
+            calls: u32,
+            sum: f64,
+        }
+
+        fn accumulate(pins: &Resources, totals: &mut Totals, _period: Duration) {
+            totals.calls += 1;
+            totals.sum += pins.input.get();
+        }
+
+        #[test]
+        fn state() -> Result<(), Box<dyn std::error::Error>> {
+            let mut comp = HalComponent::<Resources, Totals>::builder("mock-state")
+                .function(HalFunction::with_state("accumulate", accumulate))
+                .build()?
+                .ready()?;
+
+            mock::set_pin_value("mock-state.input", 1.5)?;
+            mock::call_funct("mock-state.accumulate", 1_000_000)?;
+            mock::call_funct("mock-state.accumulate", 1_000_000)?;
+
+            assert_eq!(comp.state().calls, 2);
+            assert_eq!(comp.state().sum, 3.0);
+
+            let (pins, mut totals) = comp.split();
+            totals.sum -= pins.input.get();
+            drop(totals);
+            assert_eq!(comp.state().sum, 1.5);
+
+            comp.state_mut().calls = 0;
+            comp.resources_mut().rw.set(3);
+            assert_eq!(comp.state().calls, 0);
+            assert_eq!(mock::param_value("mock-state.rw"), Some(Value::U32(3)));
+
+            Ok(())
+        }
+
+        #[test]
+        fn with_state() -> Result<(), ComponentInitError> {
+            let comp = HalComponent::<Resources, _>::with_state(
+                "mock-with-state",
+                Totals { calls: 5, sum: 0.0 },
+            )?;
+
+            assert_eq!(comp.state().calls, 5);
+
+            Ok(())
+        }
+
+        #[test]
+        fn state_guards() -> Result<(), Box<dyn std::error::Error>> {
+            let mut comp = HalComponent::<Resources, Totals>::builder("mock-state-guards")
+                .function(HalFunction::with_state("accumulate", accumulate))
+                .function(HalFunction::new("set", |pins: &Resources, _period| {
+                    pins.rw.set(pins.rw.get() + 1)
+                }))
+                .build()?
+                .ready()?;
+
+            // Functions that use the state are skipped while it's borrowed
+            let totals = comp.state();
+            mock::call_funct("mock-state-guards.accumulate", 1_000_000)?;
+            mock::call_funct("mock-state-guards.set", 1_000_000)?;
+            assert_eq!(totals.calls, 0);
+            drop(totals);
+
+            mock::call_funct("mock-state-guards.accumulate", 1_000_000)?;
+            assert_eq!(comp.state().calls, 1);
+
+            // Every function is skipped while the resources are borrowed mutably
+            let resources = comp.resources_mut();
+            mock::call_funct("mock-state-guards.accumulate", 1_000_000)?;
+            mock::call_funct("mock-state-guards.set", 1_000_000)?;
+            drop(resources);
+
+            assert_eq!(comp.state().calls, 1);
+            assert_eq!(
+                mock::param_value("mock-state-guards.rw"),
+                Some(Value::U32(1))
+            );
+
+            Ok(())
+        }
+
+        fn nested(_pins: &Resources, totals: &mut Totals, _period: Duration) {
+            totals.calls += 1;
+
+            // The state is already in use, so this call is skipped
+            mock::call_funct("mock-state-lock.nested", 1_000_000).unwrap();
+        }
+
+        #[test]
+        fn state_lock() -> Result<(), Box<dyn std::error::Error>> {
+            let comp = HalComponent::<Resources, Totals>::builder("mock-state-lock")
+                .function(HalFunction::with_state("nested", nested))
+                .build()?
+                .ready()?;
+
+            mock::call_funct("mock-state-lock.nested", 1_000_000)?;
+            assert_eq!(comp.state().calls, 1);
+
+            // The lock is released after each call
+            mock::call_funct("mock-state-lock.nested", 1_000_000)?;
+            assert_eq!(comp.state().calls, 2);
+
+            Ok(())
+        }
+
+        #[test]
+        fn pin_values() -> Result<(), Box<dyn std::error::Error>> {
+            let comp = HalComponent::<Resources>::new("mock-pins")?;
+            let resources = comp.resources();
+
+            mock::set_pin_value("mock-pins.input", 12.5)?;
+            assert_eq!(resources.input.get(), 12.5);
+
+            resources.output.set(true);
+            assert_eq!(mock::pin_value("mock-pins.output"), Some(Value::Bit(true)));
+
+            assert_eq!(
+                mock::set_pin_value("mock-pins.input", 1u32),
+                Err(MockError::TypeMismatch("mock-pins.input".to_string()))
+            );
+
+            Ok(())
+        }
+
+        #[test]
+        fn infallible_access() -> Result<(), Box<dyn std::error::Error>> {
+            let comp = HalComponent::<Resources>::new("mock-get-set")?;
+            let resources = comp.resources();
+
+            mock::set_pin_value("mock-get-set.input", -2.5)?;
+            assert_eq!(resources.input.get(), -2.5);
+
+            resources.output.set(true);
+            assert_eq!(
+                mock::pin_value("mock-get-set.output"),
+                Some(Value::Bit(true))
+            );
+
+            mock::set_param_value("mock-get-set.rw", 7u32)?;
+            assert_eq!(resources.rw.get(), 7);
+
+            resources.ro.set(-1);
+            assert_eq!(mock::param_value("mock-get-set.ro"), Some(Value::S32(-1)));
+
+            Ok(())
+        }
+
+        #[test]
+        fn copy_out() -> Result<(), Box<dyn std::error::Error>> {
+            let comp = HalComponent::<Resources>::new("mock-copy-out")?;
+            let resources = comp.resources();
+
+            mock::set_pin_value("mock-copy-out.input", 1.0)?;
+            let before = resources.input.get();
+
+            // Values are copied out, so later writes to shared memory don't change them
+            mock::set_pin_value("mock-copy-out.input", 2.0)?;
+            assert_eq!(before, 1.0);
+            assert_eq!(resources.input.get(), 2.0);
+
+            mock::set_param_value("mock-copy-out.rw", 3u32)?;
+            let before = resources.rw.get();
+            mock::set_param_value("mock-copy-out.rw", 4u32)?;
+            assert_eq!(before, 3);
+            assert_eq!(resources.rw.get(), 4);
+
+            Ok(())
+        }
+
+        #[test]
+        fn worker_thread() -> Result<(), Box<dyn std::error::Error>> {
+            let comp = Arc::new(HalComponent::<Resources>::new("mock-worker")?);
+
+            mock::set_pin_value("mock-worker.input", 4.0)?;
+
+            let worker = {
+                let comp = Arc::clone(&comp);
+
+                thread::spawn(move || {
+                    let resources = comp.resources();
+
+                    resources.output.set(resources.input.get() > 2.0);
+                    resources.rw.set(resources.rw.get() + 1);
+                })
+            };
+
+            worker.join().unwrap();
+
+            assert_eq!(
+                mock::pin_value("mock-worker.output"),
+                Some(Value::Bit(true))
+            );
+            assert_eq!(comp.resources().rw.get(), 1);
+
+            Ok(())
+        }
+
+        struct Edges {
+            start: InputPin<bool>,
+            speed: InputPin<f64>,
+        }
+
+        impl crate::Resources for Edges {
+            type RegisterError = PinRegisterError;
+
+            fn register_resources(comp: &RegisterResources) -> Result<Self, Self::RegisterError> {
+                Ok(Self {
+                    start: comp.register_pin("start")?,
+                    speed: comp.register_pin("speed")?,
+                })
+            }
+        }
+
+        #[test]
+        fn change_detection() -> Result<(), Box<dyn std::error::Error>> {
+            let comp = HalComponent::<Edges>::new("mock-edges")?;
+            let pins = comp.resources();
+
+            // Nothing has changed since the pins were registered
+            assert!(!pins.start.rising_edge());
+            assert!(!pins.start.changed_since_last());
+            assert!(!pins.speed.changed_since_last());
+
+            mock::set_pin_value("mock-edges.start", true)?;
+            mock::set_pin_value("mock-edges.speed", 1.5)?;
+
+            // Each method tracks its own previous value
+            assert!(!pins.start.falling_edge());
+            assert!(pins.start.rising_edge());
+            assert!(pins.start.changed_since_last());
+            assert!(pins.speed.changed_since_last());
+
+            assert!(!pins.start.rising_edge());
+            assert!(!pins.start.changed_since_last());
+            assert!(!pins.speed.changed_since_last());
+
+            mock::set_pin_value("mock-edges.start", false)?;
+
+            assert!(!pins.start.rising_edge());
+            assert!(pins.start.falling_edge());
+            assert!(!pins.start.falling_edge());
+
+            Ok(())
+        }
+
+        #[derive(crate::Resources)]
+        struct Loop {
+            count: BidirectionalPin<u32>,
+            stats: LoopStats,
+        }
+
+        #[test]
+        fn run_at() -> Result<(), Box<dyn std::error::Error>> {
+            let comp = HalComponent::<Loop>::new("mock-run-at")?;
+            let period = Duration::from_millis(20);
+
+            let mut overruns = 0;
+
+            comp.run_at(period, |pins, ctx| {
+                assert_eq!(ctx.period(), period);
+                assert_eq!(u64::from(pins.count.get()), ctx.iteration());
+
+                pins.count.set(pins.count.get() + 1);
+
+                // Overrun once by more than two periods
+                if ctx.iteration() == 1 {
+                    thread::sleep(period * 5 / 2);
+                }
+
+                if ctx.iteration() == 4 {
+                    overruns = ctx.overruns();
+                    ctx.stop();
+                }
+
+                Ok::<_, MockError>(())
+            })?;
+
+            let stats = &comp.resources().stats;
+
+            assert_eq!(comp.resources().count.get(), 5);
+            assert_eq!(overruns, 1);
+            assert_eq!(stats.overruns(), 1);
+            assert_eq!(
+                mock::param_value("mock-run-at.overruns"),
+                Some(Value::U32(1))
+            );
+            assert!(stats.tmax() >= (period * 5 / 2).as_nanos() as i32);
+            assert!(stats.time() < stats.tmax());
+
+            // `loop-tmax` can be reset from the HAL
+            mock::set_param_value("mock-run-at.loop-tmax", 0)?;
+            assert_eq!(stats.tmax(), 0);
+
+            Ok(())
+        }
+
+        #[test]
+        fn run_at_error() -> Result<(), ComponentInitError> {
+            let comp = HalComponent::<Resources>::new("mock-run-at-error")?;
+
+            let result = comp.run_at(Duration::from_millis(1), |resources, ctx| {
+                if ctx.iteration() == 2 {
+                    Err("failed")
+                } else {
+                    resources.rw.set(resources.rw.get() + 1);
+
+                    Ok(())
+                }
+            });
+
+            assert_eq!(result, Err("failed"));
+            assert_eq!(comp.resources().rw.get(), 2);
+
+            Ok(())
+        }
+
+        #[test]
+        fn parameter_values() -> Result<(), Box<dyn std::error::Error>> {
+            let comp = HalComponent::<Resources>::new("mock-params")?;
+            let resources = comp.resources();
+
+            mock::set_param_value("mock-params.rw", 100u32)?;
+            assert_eq!(resources.rw.get(), 100);
+
+            resources.ro.set(-5);
+            assert_eq!(mock::param_value("mock-params.ro"), Some(Value::S32(-5)));
+
+            assert_eq!(
+                mock::set_param_value("mock-params.ro", 1),
+                Err(MockError::ReadOnly("mock-params.ro".to_string()))
+            );
+
+            Ok(())
+        }
+
+        #[derive(Debug)]
+        struct WideResources {
+            position: InputPin<i64>,
+            counts: OutputPin<u64>,
+            offset: Parameter<i64>,
+        }
+
+        impl crate::Resources for WideResources {
+            type RegisterError = ResourcesError;
+
+            fn register_resources(comp: &RegisterResources) -> Result<Self, Self::RegisterError> {
+                Ok(Self {
+                    position: comp.register_pin("position")?,
+                    counts: comp.register_pin("counts")?,
+                    offset: comp.register_parameter("offset")?,
+                })
+            }
+        }
+
+        #[test]
+        fn wide_values() -> Result<(), Box<dyn std::error::Error>> {
+            let comp = HalComponent::<WideResources>::new("mock-wide")?;
+            let resources = comp.resources();
+
+            mock::set_pin_value("mock-wide.position", -(1i64 << 40))?;
+            assert_eq!(resources.position.get(), -(1 << 40));
+
+            resources.counts.set(u64::MAX);
+            assert_eq!(
+                mock::pin_value("mock-wide.counts"),
+                Some(Value::U64(u64::MAX))
+            );
+
+            mock::set_param_value("mock-wide.offset", i64::MIN)?;
+            assert_eq!(resources.offset.get(), i64::MIN);
+
+            Ok(())
+        }
+
+        #[derive(Debug)]
+        struct PortResources {
+            rx: InputPort,
+            tx: OutputPort,
+        }
+
+        impl crate::Resources for PortResources {
+            type RegisterError = PinRegisterError;
+
+            fn register_resources(comp: &RegisterResources) -> Result<Self, Self::RegisterError> {
+                Ok(Self {
+                    rx: comp.register_pin("rx")?,
+                    tx: comp.register_pin("tx")?,
+                })
+            }
+        }
+
+        #[test]
+        fn ports() -> Result<(), Box<dyn std::error::Error>> {
+            let mut comp = HalComponent::<PortResources>::new("mock-ports")?;
+            let mut resources = comp.resources_mut();
+            let PortResources { rx, tx } = &mut *resources;
+
+            // Ports have no buffer until they're linked
+            assert_eq!(
+                tx.write(b"a"),
+                Err(PortError::NotEnoughSpace {
+                    requested: 1,
+                    available: 0
+                })
+            );
+            assert_eq!(mock::pin_value("mock-ports.rx"), None);
+
+            mock::attach_port("mock-ports.rx", 8)?;
+            mock::attach_port("mock-ports.tx", 4)?;
+
+            mock::port_write("mock-ports.rx", b"hello")?;
+            assert_eq!(rx.readable(), Ok(5));
+            assert_eq!(rx.buffer_size(), Ok(8));
+
+            let mut buf = [0u8; 2];
+
+            rx.peek(&mut buf)?;
+            assert_eq!(&buf, b"he");
+            rx.consume(1)?;
+            rx.read(&mut buf)?;
+            assert_eq!(&buf, b"el");
+
+            let mut buf = [0u8; 3];
+
+            assert_eq!(
+                rx.read(&mut buf),
+                Err(PortError::NotEnoughData {
+                    requested: 3,
+                    available: 2
+                })
+            );
+
+            tx.write(b"abc")?;
+            assert_eq!(tx.writable(), Ok(1));
+            assert_eq!(
+                tx.write(b"de"),
+                Err(PortError::NotEnoughSpace {
+                    requested: 2,
+                    available: 1
+                })
+            );
+            assert_eq!(mock::port_read("mock-ports.tx")?, b"abc".to_vec());
+            assert_eq!(tx.writable(), Ok(4));
+
+            Ok(())
         }
     }
 }
@@ -1,6 +1,6 @@
 //! Error types
 
-use linuxcnc_hal_sys::HAL_NAME_LEN;
+use linuxcnc_hal_sys::{EINVAL, ENOMEM, EPERM, HAL_NAME_LEN};
 
 /// Errors returned by LinuxCNC bindgen functions
 
@@ -16,42 +16,150 @@ pub enum StorageError {
     Alignment,
 }
 
+/// Port read or write error
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum PortError {
+    /// The pin's storage is invalid
+    #[error("invalid port storage: {0}")]
+    Storage(StorageError),
+
+    /// The port does not hold enough bytes to fill the buffer
+    ///
+    /// Nothing is read from the port when this error is returned.
+    #[error("not enough data in port: requested {requested} bytes, {available} available")]
+    NotEnoughData {
+        /// The number of bytes requested
+        requested: usize,
+
+        /// The number of bytes available to read
+        available: usize,
+    },
+
+    /// The port does not have enough free space for the data
+    ///
+    /// Nothing is written to the port when this error is returned.
+    #[error("not enough space in port: requested {requested} bytes, {available} available")]
+    NotEnoughSpace {
+        /// The number of bytes requested
+        requested: usize,
+
+        /// The number of bytes of free space in the port
+        available: usize,
+    },
+}
+
+/// Implement conversion to and from the negative errno codes returned by HAL functions
+///
+/// The error type must have `Invalid`, `LockedHal`, `Memory` and `Unknown` variants. The HAL
+/// returns `EINVAL` for duplicate names, so they can't be told apart from other invalid calls.
+macro_rules! impl_errno {
+    ($error:ident) => {
+        impl $error {
+            /// Create an error from a negative errno code returned by a HAL function
+            pub(crate) fn from_errno(name: &str, code: i32) -> Self {
+                let name = name.to_string();
+
+                match code {
+                    x if x == -(EINVAL as i32) => Self::Invalid { name },
+                    x if x == -(EPERM as i32) => Self::LockedHal { name },
+                    x if x == -(ENOMEM as i32) => Self::Memory { name },
+                    code => Self::Unknown { name, code },
+                }
+            }
+
+            /// Get the negative errno code that best describes this error
+            pub fn errno(&self) -> i32 {
+                match self {
+                    Self::LockedHal { .. } => -(EPERM as i32),
+                    Self::Memory { .. } => -(ENOMEM as i32),
+                    Self::Unknown { code, .. } => *code,
+                    _ => -(EINVAL as i32),
+                }
+            }
+        }
+    };
+}
+
 /// Pin registration error
 #[derive(thiserror::Error, Debug, PartialEq)]
 pub enum PinRegisterError {
     /// Pin name is too long
     ///
     /// The maximum length is dictated by the [`HAL_NAME_LEN`] constant
-    #[error("pin name is too long. Must be no longer than {} bytes", HAL_NAME_LEN)]
-    NameLength,
+    #[error(
+        "pin name {name} is too long. Must be no longer than {} bytes",
+        HAL_NAME_LEN
+    )]
+    NameLength {
+        /// The full pin name
+        name: String,
+    },
+
+    /// Pin array name pattern must contain a single `#` placeholder for the index
+    #[error("pin array name {pattern} must contain a single `#` placeholder for the index")]
+    NameFormat {
+        /// The name pattern
+        pattern: String,
+    },
 
     /// Pin name could not be converted to C string
-    #[error("pin name could not be converted to a valid C string")]
-    NameConversion,
+    #[error("pin name {name} could not be converted to a valid C string")]
+    NameConversion {
+        /// The full pin name
+        name: String,
+    },
 
     /// An error occurred allocating the HAL shared memory storage backing the pin
-    #[error("failed to allocate shared memory storage for pin")]
-    Storage(StorageError),
+    #[error("failed to allocate shared memory storage for pin {name}")]
+    Storage {
+        /// The full pin name
+        name: String,
+
+        /// The storage error
+        source: StorageError,
+    },
 
     /// An error occurred in the LinuxCNC HAL functions
     ///
     /// This variant is often returned when a HAL function returns
     /// [`EINVAL`](linuxcnc_hal_sys::EINVAL). This error code is returned for various different
-    /// reasons. Check the LinuxCNC logs for error messages.
-    #[error("HAL method returned invalid (EINVAL) status code")]
-    Invalid,
+    /// reasons, including a pin with the same name already existing. Check the LinuxCNC logs for
+    /// error messages.
+    #[error("HAL method returned invalid (EINVAL) status code for pin {name}")]
+    Invalid {
+        /// The full pin name
+        name: String,
+    },
 
     /// The HAL is locked
     ///
     /// Resources cannot be registered after a component is created
-    #[error("HAL is locked")]
-    LockedHal,
+    #[error("HAL is locked, cannot register pin {name}")]
+    LockedHal {
+        /// The full pin name
+        name: String,
+    },
 
     /// There is not enough free memory available to allocate storage for this pin
-    #[error("not enough free memory to allocate storage")]
-    Memory,
+    #[error("not enough free memory to allocate storage for pin {name}")]
+    Memory {
+        /// The full pin name
+        name: String,
+    },
+
+    /// The HAL returned an error code not covered by any other variant
+    #[error("HAL method returned unknown error code {code} for pin {name}")]
+    Unknown {
+        /// The full pin name
+        name: String,
+
+        /// The negative errno code
+        code: i32,
+    },
 }
 
+impl_errno!(PinRegisterError);
+
 /// Parameter registration error
 #[derive(thiserror::Error, Debug, PartialEq)]
 pub enum ParameterRegisterError {
@@ -59,38 +167,271 @@ pub enum ParameterRegisterError {
     ///
     /// The maximum length is dictated by the [`HAL_NAME_LEN`] constant
     #[error(
-        "Parameter name is too long. Must be no longer than {} bytes",
+        "parameter name {name} is too long. Must be no longer than {} bytes",
         HAL_NAME_LEN
     )]
-    NameLength,
+    NameLength {
+        /// The full parameter name
+        name: String,
+    },
+
+    /// Parameter array name pattern must contain a single `#` placeholder for the index
+    #[error("parameter array name {pattern} must contain a single `#` placeholder for the index")]
+    NameFormat {
+        /// The name pattern
+        pattern: String,
+    },
 
     /// Parameter name could not be converted to C string
-    #[error("Parameter name could not be converted to a valid C string")]
-    NameConversion,
+    #[error("parameter name {name} could not be converted to a valid C string")]
+    NameConversion {
+        /// The full parameter name
+        name: String,
+    },
 
     /// An error occurred allocating the HAL shared memory storage backing the parameter
-    #[error("failed to allocate shared memory storage for parameter")]
-    Storage(StorageError),
+    #[error("failed to allocate shared memory storage for parameter {name}")]
+    Storage {
+        /// The full parameter name
+        name: String,
+
+        /// The storage error
+        source: StorageError,
+    },
 
     /// An error occurred in the LinuxCNC HAL functions
     ///
     /// This variant is often returned when a HAL function returns
     /// [`EINVAL`](linuxcnc_hal_sys::EINVAL). This error code is returned for various different
-    /// reasons. Check the LinuxCNC logs for error messages.
-    #[error("HAL method returned invalid (EINVAL) status code")]
-    Invalid,
+    /// reasons, including a parameter with the same name already existing. Check the LinuxCNC logs
+    /// for error messages.
+    #[error("HAL method returned invalid (EINVAL) status code for parameter {name}")]
+    Invalid {
+        /// The full parameter name
+        name: String,
+    },
 
     /// The HAL is locked
     ///
     /// Resources cannot be registered after a component is created
-    #[error("HAL is locked")]
-    LockedHal,
+    #[error("HAL is locked, cannot register parameter {name}")]
+    LockedHal {
+        /// The full parameter name
+        name: String,
+    },
 
     /// There is not enough free memory available to allocate storage for this parameter
-    #[error("not enough free memory to allocate storage")]
-    Memory,
+    #[error("not enough free memory to allocate storage for parameter {name}")]
+    Memory {
+        /// The full parameter name
+        name: String,
+    },
+
+    /// The HAL returned an error code not covered by any other variant
+    #[error("HAL method returned unknown error code {code} for parameter {name}")]
+    Unknown {
+        /// The full parameter name
+        name: String,
+
+        /// The negative errno code
+        code: i32,
+    },
+}
+
+impl_errno!(ParameterRegisterError);
+
+/// Function export error
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum FunctionExportError {
+    /// Function name is too long
+    ///
+    /// The maximum length is dictated by the [`HAL_NAME_LEN`] constant
+    #[error(
+        "function name {name} is too long. Must be no longer than {} bytes",
+        HAL_NAME_LEN
+    )]
+    NameLength {
+        /// The full function name
+        name: String,
+    },
+
+    /// Function name could not be converted to C string
+    #[error("function name {name} could not be converted to a valid C string")]
+    NameConversion {
+        /// The full function name
+        name: String,
+    },
+
+    /// An error occurred in the LinuxCNC HAL functions
+    ///
+    /// This variant is returned if the component is already ready or a function with the same name
+    /// already exists. Check the LinuxCNC logs for error messages.
+    #[error("HAL method returned invalid (EINVAL) status code for function {name}")]
+    Invalid {
+        /// The full function name
+        name: String,
+    },
+
+    /// The HAL is locked
+    ///
+    /// Functions cannot be exported after a component is created
+    #[error("HAL is locked, cannot export function {name}")]
+    LockedHal {
+        /// The full function name
+        name: String,
+    },
+
+    /// There is not enough free memory available to export this function
+    #[error("not enough free memory to export function {name}")]
+    Memory {
+        /// The full function name
+        name: String,
+    },
+
+    /// The HAL returned an error code not covered by any other variant
+    #[error("HAL method returned unknown error code {code} for function {name}")]
+    Unknown {
+        /// The full function name
+        name: String,
+
+        /// The negative errno code
+        code: i32,
+    },
+}
+
+impl_errno!(FunctionExportError);
+
+/// Signal creation or linking error
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum SignalError {
+    /// Signal or pin name is too long
+    ///
+    /// The maximum length is dictated by the [`HAL_NAME_LEN`] constant
+    #[error(
+        "signal or pin name {name} is too long. Must be no longer than {} bytes",
+        HAL_NAME_LEN
+    )]
+    NameLength {
+        /// The signal or pin name
+        name: String,
+    },
+
+    /// Signal or pin name could not be converted to C string
+    #[error("signal or pin name {name} could not be converted to a valid C string")]
+    NameConversion {
+        /// The signal or pin name
+        name: String,
+    },
+
+    /// An error occurred in the LinuxCNC HAL functions
+    ///
+    /// This variant is returned if a signal with the same name already exists, if a signal or pin
+    /// doesn't exist, if the pin and signal types differ, if the pin is already linked to another
+    /// signal or if the signal already has a writer. Check the LinuxCNC logs for error messages.
+    #[error("HAL method returned invalid (EINVAL) status code for {name}")]
+    Invalid {
+        /// The signal name, or the pin name when linking
+        name: String,
+    },
+
+    /// The HAL is locked
+    ///
+    /// Signals cannot be changed while the HAL configuration is locked
+    #[error("HAL is locked, cannot change {name}")]
+    LockedHal {
+        /// The signal name, or the pin name when linking
+        name: String,
+    },
+
+    /// There is not enough free memory available to allocate storage for this signal
+    #[error("not enough free memory to allocate storage for {name}")]
+    Memory {
+        /// The signal name, or the pin name when linking
+        name: String,
+    },
+
+    /// The HAL returned an error code not covered by any other variant
+    #[error("HAL method returned unknown error code {code} for {name}")]
+    Unknown {
+        /// The signal name, or the pin name when linking
+        name: String,
+
+        /// The negative errno code
+        code: i32,
+    },
+}
+
+impl_errno!(SignalError);
+
+/// Thread creation or scheduling error
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum ThreadError {
+    /// Thread or function name is too long
+    ///
+    /// The maximum length is dictated by the [`HAL_NAME_LEN`] constant
+    #[error(
+        "thread or function name {name} is too long. Must be no longer than {} bytes",
+        HAL_NAME_LEN
+    )]
+    NameLength {
+        /// The thread or function name
+        name: String,
+    },
+
+    /// Thread or function name could not be converted to C string
+    #[error("thread or function name {name} could not be converted to a valid C string")]
+    NameConversion {
+        /// The thread or function name
+        name: String,
+    },
+
+    /// Thread period is zero or too long
+    #[error("period of thread {name} must be at least 1ns and fit in a C long")]
+    Period {
+        /// The thread name
+        name: String,
+    },
+
+    /// An error occurred in the LinuxCNC HAL functions
+    ///
+    /// This variant is returned if a thread with the same name already exists, if a thread or
+    /// function doesn't exist, if the position is out of range, or if a function can't be added to
+    /// the thread. Check the LinuxCNC logs for error messages.
+    #[error("HAL method returned invalid (EINVAL) status code for thread {name}")]
+    Invalid {
+        /// The thread name
+        name: String,
+    },
+
+    /// The HAL is locked
+    ///
+    /// Threads cannot be changed while the HAL configuration is locked
+    #[error("HAL is locked, cannot change thread {name}")]
+    LockedHal {
+        /// The thread name
+        name: String,
+    },
+
+    /// There is not enough free memory available to create the thread
+    #[error("not enough free memory to create thread {name}")]
+    Memory {
+        /// The thread name
+        name: String,
+    },
+
+    /// The HAL returned an error code not covered by any other variant
+    #[error("HAL method returned unknown error code {code} for thread {name}")]
+    Unknown {
+        /// The thread name
+        name: String,
+
+        /// The negative errno code
+        code: i32,
+    },
 }
 
+impl_errno!(ThreadError);
+
 /// HAL component initialisation error
 #[derive(thiserror::Error, Debug)]
 pub enum ComponentInitError {
@@ -98,47 +439,136 @@ pub enum ComponentInitError {
     ///
     /// The maximum length is dictated by the [`HAL_NAME_LEN`] constant
     #[error(
-        "component name is too long. Must be no longer than {} bytes",
+        "component name {name} is too long. Must be no longer than {} bytes",
         HAL_NAME_LEN
     )]
-    NameLength,
+    NameLength {
+        /// The component name
+        name: String,
+    },
 
     /// Component name could not be converted to C type
-    #[error("component name cannot be converted to valid C string")]
-    InvalidName,
+    #[error("component name {name} cannot be converted to valid C string")]
+    InvalidName {
+        /// The component name
+        name: String,
+    },
 
-    /// There is not enough free memory available to allocate storage for this pin
-    #[error("not enough free memory to allocate storage")]
-    Memory,
+    /// There is not enough free memory available to allocate the component
+    #[error("not enough free memory to allocate component {name}")]
+    Memory {
+        /// The component name
+        name: String,
+    },
 
     /// Failed to register signal handlers
     #[error("failed to register signal handlers")]
     Signals(std::io::Error),
 
     /// Resource (pin, signal, etc) registration failed
-    #[error("failed to register resources with component")]
+    #[error("failed to register resources with component: {0}")]
     ResourceRegistration(ResourcesError),
 
     /// An error occurred when initialising the component with
     /// [`hal_init`](linuxcnc_hal_sys::hal_init)
-    #[error("failed to initialise component")]
-    Init,
+    ///
+    /// This is returned if a component with the same name already exists. Check the LinuxCNC logs
+    /// for error messages.
+    #[error("failed to initialise component {name}")]
+    Init {
+        /// The component name
+        name: String,
+    },
 
     /// An error occurred when calling [`hal_ready`](linuxcnc_hal_sys::hal_ready) on the component
-    #[error("failed to ready component")]
-    Ready,
+    #[error("failed to ready component {name}")]
+    Ready {
+        /// The component name
+        name: String,
+    },
+
+    /// A hook added with [`HalComponentBuilder::pre_ready`](crate::HalComponentBuilder::pre_ready)
+    /// returned an error
+    #[error("pre-ready hook failed for component {name}: {source}")]
+    PreReady {
+        /// The component name
+        name: String,
+
+        /// The error returned by the hook
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    /// The number or names of the component's instances are invalid
+    ///
+    /// This variant is returned if a component is built with no instances, or if a realtime
+    /// component is loaded with both `count=` and `names=`.
+    #[error("invalid instances for component {name}: {reason}")]
+    Instances {
+        /// The component name
+        name: String,
+
+        /// Why the instances are invalid
+        reason: String,
+    },
+
+    /// The HAL returned an error code not covered by any other variant
+    #[error("HAL method returned unknown error code {code} for component {name}")]
+    Unknown {
+        /// The component name
+        name: String,
+
+        /// The negative errno code
+        code: i32,
+    },
+}
+
+impl ComponentInitError {
+    /// Get the negative errno code that best describes this error
+    ///
+    /// This is the value returned from `rtapi_app_main` by
+    /// [`export_rt_component!`](crate::export_rt_component) when a realtime component fails to
+    /// load.
+    pub fn errno(&self) -> i32 {
+        match self {
+            Self::Memory { .. } => -(ENOMEM as i32),
+            Self::Signals(e) => -e.raw_os_error().unwrap_or(EINVAL as i32),
+            Self::ResourceRegistration(e) => e.errno(),
+            Self::Unknown { code, .. } => *code,
+            Self::NameLength { .. }
+            | Self::InvalidName { .. }
+            | Self::Init { .. }
+            | Self::Ready { .. }
+            | Self::PreReady { .. }
+            | Self::Instances { .. } => -(EINVAL as i32),
+        }
+    }
 }
 
 /// Resources registration error
 #[derive(thiserror::Error, Debug)]
 pub enum ResourcesError {
     /// Failed to register a pin with the HAL
-    #[error("pin registration failed")]
+    #[error("pin registration failed: {0}")]
     Pin(PinRegisterError),
 
     /// Failed to register a pin with the HAL
-    #[error("parameter registration failed")]
+    #[error("parameter registration failed: {0}")]
     Parameter(ParameterRegisterError),
+
+    /// Failed to export a function to the HAL
+    #[error("function export failed: {0}")]
+    Function(FunctionExportError),
+}
+
+impl ResourcesError {
+    /// Get the negative errno code that best describes this error
+    fn errno(&self) -> i32 {
+        match self {
+            Self::Pin(e) => e.errno(),
+            Self::Parameter(e) => e.errno(),
+            Self::Function(e) => e.errno(),
+        }
+    }
 }
 
 impl From<PinRegisterError> for ResourcesError {
@@ -152,3 +582,47 @@ impl From<ParameterRegisterError> for ResourcesError {
         Self::Parameter(e)
     }
 }
+
+impl From<FunctionExportError> for ResourcesError {
+    fn from(e: FunctionExportError) -> Self {
+        Self::Function(e)
+    }
+}
+
+impl From<StorageError> for PortError {
+    fn from(e: StorageError) -> Self {
+        Self::Storage(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errno_round_trip() {
+        for code in [EINVAL, EPERM, ENOMEM, 5].iter() {
+            let code = -(*code as i32);
+
+            assert_eq!(PinRegisterError::from_errno("comp.pin", code).errno(), code);
+            assert_eq!(SignalError::from_errno("sig", code).errno(), code);
+        }
+    }
+
+    #[test]
+    fn unknown_errno() {
+        let e = ParameterRegisterError::from_errno("comp.param", -5);
+
+        assert_eq!(
+            e,
+            ParameterRegisterError::Unknown {
+                name: "comp.param".to_string(),
+                code: -5
+            }
+        );
+        assert_eq!(
+            e.to_string(),
+            "HAL method returned unknown error code -5 for parameter comp.param"
+        );
+    }
+}
@@ -0,0 +1,322 @@
+//! Unix signals delivered to a component as events
+//!
+//! By default a component handles `SIGINT` and `SIGTERM`, which are reported as
+//! [`ComponentEvent::Exit`] and make
+//! [`HalComponent::should_exit`](crate::HalComponent::should_exit) return true. Other signals can
+//! be handled with [`HalComponentBuilder::on_signal`](crate::HalComponentBuilder::on_signal), which
+//! lets a long running component reload its configuration or report its state without being
+//! restarted.
+//!
+//! Events can be read without blocking with
+//! [`HalComponent::pending_events`](crate::HalComponent::pending_events), or by blocking on
+//! [`HalComponent::events`](crate::HalComponent::events), for example on a dedicated thread. With
+//! the `tokio` feature, `HalComponent::event_stream` provides an async stream of events.
+//!
+//! # Examples
+//!
+//! Reload on `SIGHUP`, print the value of each pin on `SIGUSR1` and exit on `SIGINT` or `SIGTERM`.
+//! Try `kill -HUP <pid>` or `kill -USR1 <pid>` while the component is running.
+//!
+//! ```rust,no_run
+//! use linuxcnc_hal::{event::ComponentEvent, hal_pin::InputPin, HalComponent, Resources};
+//! use std::{error::Error, thread, time::Duration};
+//!
+//! #[derive(Debug, Resources)]
+//! struct Pins {
+//!     speed: InputPin<f64>,
+//! }
+//!
+//! fn main() -> Result<(), Box<dyn Error>> {
+//!     let comp = HalComponent::<Pins>::builder("events")
+//!         .on_signal(libc::SIGHUP, ComponentEvent::Reload)
+//!         .on_signal(libc::SIGUSR1, ComponentEvent::DumpState)
+//!         .build()?
+//!         .ready()?;
+//!
+//!     loop {
+//!         for event in comp.pending_events() {
+//!             match event {
+//!                 ComponentEvent::Exit => return Ok(()),
+//!                 ComponentEvent::Reload => println!("Reloading configuration"),
+//!                 ComponentEvent::DumpState => println!("{:#?}", comp.resources()),
+//!                 ComponentEvent::Signal(signal) => println!("Received signal {}", signal),
+//!             }
+//!         }
+//!
+//!         thread::sleep(Duration::from_millis(100));
+//!     }
+//! }
+//! ```
+
+use crate::error::ComponentInitError;
+use signal_hook::iterator::Signals;
+use std::{
+    collections::VecDeque,
+    os::raw::c_int,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+/// An event delivered to a component by a Unix signal
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ComponentEvent {
+    /// The component should shut down
+    ///
+    /// Sent on `SIGINT` and `SIGTERM` by default.
+    Exit,
+
+    /// The component should reload its configuration
+    ///
+    /// Conventionally sent on `SIGHUP`.
+    Reload,
+
+    /// The component should report its current state, e.g. by logging the value of its pins
+    ///
+    /// Conventionally sent on `SIGUSR1`.
+    DumpState,
+
+    /// Any other signal, holding the signal number
+    Signal(c_int),
+}
+
+/// Signal handlers for a component, and the events the signals are turned into
+#[derive(Debug)]
+pub(crate) struct SignalEvents {
+    signals: Signals,
+
+    /// The event each handled signal is reported as
+    events: Vec<(c_int, ComponentEvent)>,
+
+    /// Events that have been received but not read yet
+    queue: Mutex<VecDeque<ComponentEvent>>,
+
+    /// Set once an exit event has been received, and never cleared
+    exit: AtomicBool,
+}
+
+impl SignalEvents {
+    /// Register handlers for every signal in `events`
+    pub(crate) fn new(events: Vec<(c_int, ComponentEvent)>) -> Result<Self, ComponentInitError> {
+        let signals = Signals::new(events.iter().map(|(signal, _event)| *signal))
+            .map_err(ComponentInitError::Signals)?;
+
+        debug!("Signals registered");
+
+        Ok(Self {
+            signals,
+            events,
+            queue: Mutex::new(VecDeque::new()),
+            exit: AtomicBool::new(false),
+        })
+    }
+
+    /// Get the signals that are reported as `event`
+    #[cfg(feature = "tokio")]
+    pub(crate) fn signals_for(&self, event: ComponentEvent) -> impl Iterator<Item = c_int> + '_ {
+        self.events
+            .iter()
+            .filter(move |(_signal, e)| *e == event)
+            .map(|(signal, _event)| *signal)
+    }
+
+    /// Get the signals that are handled
+    #[cfg(any(feature = "tokio", test))]
+    pub(crate) fn signals(&self) -> impl Iterator<Item = c_int> + '_ {
+        self.events.iter().map(|(signal, _event)| *signal)
+    }
+
+    /// Move signals that have been received into the event queue
+    fn queue(&self, signals: impl Iterator<Item = c_int>) {
+        let mut queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+
+        for signal in signals {
+            if let Some((_signal, event)) = self.events.iter().find(|(s, _event)| *s == signal) {
+                if *event == ComponentEvent::Exit {
+                    self.exit.store(true, Ordering::Relaxed);
+                }
+
+                queue.push_back(*event);
+            }
+        }
+    }
+
+    /// Check whether an exit event has ever been received
+    pub(crate) fn should_exit(&self) -> bool {
+        self.queue(self.signals.pending());
+
+        self.exit.load(Ordering::Relaxed)
+    }
+
+    /// Get the next event without blocking
+    pub(crate) fn try_next(&self) -> Option<ComponentEvent> {
+        self.queue(self.signals.pending());
+
+        self.queue
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .pop_front()
+    }
+
+    /// Block until the next event is received
+    ///
+    /// Returns `None` once the handlers have been closed.
+    fn next(&self) -> Option<ComponentEvent> {
+        loop {
+            if let Some(event) = self.try_next() {
+                return Some(event);
+            }
+
+            if self.signals.is_closed() {
+                return None;
+            }
+
+            self.queue(self.signals.wait());
+        }
+    }
+
+    /// Stop handling signals, waking any threads blocked waiting for an event
+    pub(crate) fn close(&self) {
+        self.signals.close();
+    }
+}
+
+/// Iterator over events that have already been received, created by
+/// [`HalComponent::pending_events`](crate::HalComponent::pending_events)
+#[derive(Debug)]
+pub struct PendingEvents<'a> {
+    pub(crate) signals: &'a SignalEvents,
+}
+
+impl Iterator for PendingEvents<'_> {
+    type Item = ComponentEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.signals.try_next()
+    }
+}
+
+/// Blocking iterator over events, created by [`HalComponent::events`](crate::HalComponent::events)
+///
+/// The iterator ends after yielding [`ComponentEvent::Exit`].
+#[derive(Debug)]
+pub struct Events<'a> {
+    pub(crate) signals: &'a SignalEvents,
+    pub(crate) done: bool,
+}
+
+impl Iterator for Events<'_> {
+    type Item = ComponentEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let event = self.signals.next();
+
+        self.done = matches!(event, Some(ComponentEvent::Exit) | None);
+
+        event
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::{hal_pin::InputPin, HalComponent};
+    use std::{error::Error, thread, time::Duration};
+
+    #[derive(Debug, crate::Resources)]
+    struct Pins {
+        #[allow(dead_code)]
+        input: InputPin<f64>,
+    }
+
+    fn raise(signal: c_int) {
+        assert_eq!(unsafe { libc::raise(signal) }, 0);
+    }
+
+    #[test]
+    fn pending_events() -> Result<(), Box<dyn Error>> {
+        let comp = HalComponent::<Pins>::builder("mock-events-pending")
+            .on_signal(libc::SIGUSR2, ComponentEvent::Reload)
+            .on_signal(libc::SIGURG, ComponentEvent::DumpState)
+            .on_signal(libc::SIGWINCH, ComponentEvent::Signal(libc::SIGWINCH))
+            .build()?
+            .ready()?;
+
+        assert_eq!(comp.pending_events().next(), None);
+
+        raise(libc::SIGUSR2);
+        raise(libc::SIGURG);
+        raise(libc::SIGWINCH);
+
+        // Events are kept while checking for exit
+        assert!(!comp.should_exit());
+
+        let mut events = comp.pending_events().collect::<Vec<_>>();
+
+        // Signals received at the same time aren't ordered
+        events.sort_by_key(|event| format!("{:?}", event));
+
+        assert_eq!(
+            events,
+            vec![
+                ComponentEvent::DumpState,
+                ComponentEvent::Reload,
+                ComponentEvent::Signal(libc::SIGWINCH),
+            ]
+        );
+        assert_eq!(comp.pending_events().next(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn exit_is_sticky() -> Result<(), Box<dyn Error>> {
+        let comp = HalComponent::<Pins>::builder("mock-events-exit")
+            .signals(&[libc::SIGVTALRM])
+            .build()?
+            .ready()?;
+
+        assert!(!comp.should_exit());
+
+        raise(libc::SIGVTALRM);
+
+        assert!(comp.should_exit());
+        assert!(comp.should_exit());
+        assert_eq!(
+            comp.pending_events().collect::<Vec<_>>(),
+            vec![ComponentEvent::Exit]
+        );
+        assert!(comp.should_exit());
+
+        Ok(())
+    }
+
+    #[test]
+    fn blocking_events() -> Result<(), Box<dyn Error>> {
+        let comp = HalComponent::<Pins>::builder("mock-events-blocking")
+            .signals(&[libc::SIGXFSZ])
+            .build()?
+            .ready()?;
+
+        let raiser = thread::spawn(|| {
+            thread::sleep(Duration::from_millis(20));
+
+            raise(libc::SIGXFSZ);
+        });
+
+        // Blocks until the exit signal is received, then ends
+        assert_eq!(
+            comp.events().collect::<Vec<_>>(),
+            vec![ComponentEvent::Exit]
+        );
+
+        raiser.join().unwrap();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,404 @@
+//! HAL functions
+
+use crate::{component::Instance, error::FunctionExportError};
+use linuxcnc_hal_sys::{hal_export_funct, HAL_NAME_LEN};
+use std::{
+    ffi::CString,
+    hint,
+    os::raw::{c_long, c_void},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+    sync::atomic::{AtomicPtr, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+/// A function exported to the HAL that can be added to a realtime thread
+///
+/// Functions are declared by implementing [`Resources::functions`](crate::Resources::functions)
+/// and are exported by [`HalComponent::new`](crate::HalComponent::new) after the component's
+/// resources are registered. Each function is exported with the component name as a prefix, so a
+/// function called `update` in a component called `rust-comp` can be added to a thread with `addf
+/// rust-comp.update servo-thread`. Components with more than one [`Instance`](crate::Instance)
+/// export each function once per instance, e.g. `rust-comp.0.update`.
+///
+/// The function is called with a reference to the component's resources and the period of the
+/// thread it runs in. Functions created with [`HalFunction::with_state`] are also given mutable
+/// access to the component's state, see
+/// [`HalComponent::with_state`](crate::HalComponent::with_state).
+///
+/// By default, functions are exported with the `uses_fp` flag set, as most components use floating
+/// point values. Functions are not reentrant.
+///
+/// HAL threads call functions while the rest of the program can still use the component, so
+/// functions can only be created for resources that are `Send` and `Sync`. Resources holding a
+/// `Cell` can't be used:
+///
+/// ```rust,compile_fail
+/// use linuxcnc_hal::HalFunction;
+/// use std::{cell::Cell, time::Duration};
+///
+/// struct Counter {
+///     count: Cell<u32>,
+/// }
+///
+/// fn update(counter: &Counter, _period: Duration) {
+///     counter.count.set(counter.count.get() + 1);
+/// }
+///
+/// let function = HalFunction::<Counter>::new("update", update);
+/// ```
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use linuxcnc_hal::{
+///     error::PinRegisterError,
+///     hal_pin::{InputPin, OutputPin},
+///     prelude::*,
+///     HalComponent, HalFunction, RegisterResources, Resources,
+/// };
+/// use std::time::Duration;
+///
+/// struct Pins {
+///     input: InputPin<f64>,
+///     output: OutputPin<f64>,
+/// }
+///
+/// impl Resources for Pins {
+///     type RegisterError = PinRegisterError;
+///
+///     fn register_resources(comp: &RegisterResources) -> Result<Self, Self::RegisterError> {
+///         Ok(Pins {
+///             input: comp.register_pin::<InputPin<f64>>("input")?,
+///             output: comp.register_pin::<OutputPin<f64>>("output")?,
+///         })
+///     }
+///
+///     fn functions() -> Vec<HalFunction<Self>> {
+///         vec![HalFunction::new("update", update)]
+///     }
+/// }
+///
+/// /// Exported as `doubler.update`
+/// fn update(pins: &Pins, _period: Duration) {
+///     pins.output.set(pins.input.get() * 2.0);
+/// }
+///
+/// let comp: HalComponent<Pins> = HalComponent::new("doubler").unwrap();
+/// ```
+pub struct HalFunction<R, S = ()> {
+    /// Function name, without the component prefix
+    pub(crate) name: String,
+
+    /// The function to call from the realtime thread
+    pub(crate) function: Callback<R, S>,
+
+    /// Whether the function uses floating point values
+    pub(crate) uses_fp: bool,
+
+    /// Whether the function may be called from more than one thread at the same time
+    pub(crate) reentrant: bool,
+}
+
+/// A function called by the HAL, with or without access to the component state
+pub(crate) enum Callback<R, S> {
+    /// Only uses the component's resources
+    Resources(fn(&R, Duration)),
+
+    /// Uses the component's resources and state
+    State(fn(&R, &mut S, Duration)),
+
+    /// Declared by [`Resources::functions`](crate::Resources::functions) with an empty state, but
+    /// exported by a component with a different state type
+    Unit(fn(&R, &mut (), Duration)),
+}
+
+// Exported functions are called from HAL threads while the component's owner can still use the
+// resources, so they can only be created for resources that can be shared between threads
+impl<R, S> HalFunction<R, S>
+where
+    R: Send + Sync,
+{
+    /// Create a new function with the given name
+    ///
+    /// The name will be prefixed with the component name when the function is exported.
+    pub fn new(name: impl Into<String>, function: fn(&R, Duration)) -> Self {
+        Self::with_callback(name.into(), Callback::Resources(function))
+    }
+}
+
+// Functions using the state are given `&mut S` in a HAL thread
+impl<R, S> HalFunction<R, S>
+where
+    R: Send + Sync,
+    S: Send,
+{
+    /// Create a new function with the given name that can modify the component's state
+    ///
+    /// Only one function at a time can use the state. If a function is added to a thread that
+    /// preempts another function using the state, the call is skipped and an error is logged, so
+    /// functions that use the state should be added to the same thread. Calls are also skipped
+    /// while the state is borrowed with [`HalComponent::state`](crate::HalComponent::state),
+    /// [`HalComponent::state_mut`](crate::HalComponent::state_mut) or
+    /// [`HalComponent::split`](crate::HalComponent::split).
+    ///
+    /// The state is moved into a HAL thread while the function runs, so it must be `Send`:
+    ///
+    /// ```rust,compile_fail
+    /// use linuxcnc_hal::HalFunction;
+    /// use std::{rc::Rc, time::Duration};
+    ///
+    /// struct Pins;
+    ///
+    /// fn update(_pins: &Pins, _state: &mut Rc<u32>, _period: Duration) {}
+    ///
+    /// let function = HalFunction::<Pins, Rc<u32>>::with_state("update", update);
+    /// ```
+    pub fn with_state(name: impl Into<String>, function: fn(&R, &mut S, Duration)) -> Self {
+        Self::with_callback(name.into(), Callback::State(function))
+    }
+}
+
+impl<R, S> HalFunction<R, S> {
+
+    fn with_callback(name: String, function: Callback<R, S>) -> Self {
+        Self {
+            name,
+            function,
+            uses_fp: true,
+            reentrant: false,
+        }
+    }
+
+    /// Set whether the function uses floating point values
+    ///
+    /// Defaults to `true`. Setting this to `false` allows LinuxCNC to skip saving and restoring
+    /// floating point registers when calling the function, but the function must not use any
+    /// floating point operations.
+    pub fn uses_fp(self, uses_fp: bool) -> Self {
+        Self { uses_fp, ..self }
+    }
+}
+
+impl<R> HalFunction<R> {
+    /// Convert a function declared without state for use in a component with state `S`
+    pub(crate) fn with_state_type<S>(self) -> HalFunction<R, S> {
+        let function = match self.function {
+            Callback::Resources(function) => Callback::Resources(function),
+            Callback::State(function) | Callback::Unit(function) => Callback::Unit(function),
+        };
+
+        HalFunction {
+            name: self.name,
+            function,
+            uses_fp: self.uses_fp,
+            reentrant: self.reentrant,
+        }
+    }
+}
+
+impl<R, S> HalFunction<R, S>
+where
+    R: Sync,
+{
+    /// Mark the function as reentrant
+    ///
+    /// Reentrant functions may be added to more than one thread and called from them at the same
+    /// time, so this is only available for resources that can be shared between threads. Calls to
+    /// a function created with [`HalFunction::with_state`] are still skipped while another call is
+    /// using the state.
+    pub fn reentrant(self) -> Self {
+        Self {
+            reentrant: true,
+            ..self
+        }
+    }
+}
+
+// Derived `Clone` would require `R: Clone` and `S: Clone`
+impl<R, S> Clone for HalFunction<R, S> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            function: self.function.clone(),
+            uses_fp: self.uses_fp,
+            reentrant: self.reentrant,
+        }
+    }
+}
+
+impl<R, S> std::fmt::Debug for HalFunction<R, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HalFunction")
+            .field("name", &self.name)
+            .field("uses_state", &self.function.uses_state())
+            .field("uses_fp", &self.uses_fp)
+            .field("reentrant", &self.reentrant)
+            .finish()
+    }
+}
+
+impl<R, S> Clone for Callback<R, S> {
+    fn clone(&self) -> Self {
+        match self {
+            Callback::Resources(function) => Callback::Resources(*function),
+            Callback::State(function) => Callback::State(*function),
+            Callback::Unit(function) => Callback::Unit(*function),
+        }
+    }
+}
+
+impl<R, S> Callback<R, S> {
+    /// Whether the function needs exclusive access to the component state
+    fn uses_state(&self) -> bool {
+        matches!(self, Callback::State(_))
+    }
+}
+
+/// A function that has been exported to the HAL
+///
+/// A pointer to this struct is passed to the HAL as the function argument, so it must not move
+/// until the component exits.
+pub(crate) struct ExportedFunction<R, S> {
+    /// Full function name including the component prefix
+    name: String,
+
+    /// The instance's resources and state, or null once the function has been detached
+    instance: AtomicPtr<Instance<R, S>>,
+
+    /// Number of calls currently running in HAL threads
+    calls: AtomicUsize,
+
+    /// The function to call
+    function: Callback<R, S>,
+}
+
+impl<R, S> ExportedFunction<R, S> {
+    /// Export a function to the HAL
+    ///
+    /// The `instance` pointer must remain valid until the function is
+    /// [detached](ExportedFunction::detach).
+    pub(crate) fn export(
+        function: HalFunction<R, S>,
+        prefix: &str,
+        component_id: i32,
+        instance: *const Instance<R, S>,
+    ) -> Result<Box<Self>, FunctionExportError> {
+        let full_name = format!("{}.{}", prefix, function.name);
+
+        if full_name.len() > HAL_NAME_LEN as usize {
+            return Err(FunctionExportError::NameLength { name: full_name });
+        }
+
+        let full_name_ffi = CString::new(full_name.as_str()).map_err(|e| {
+            error!("Failed to convert name to C string: {}", e);
+
+            FunctionExportError::NameConversion {
+                name: full_name.clone(),
+            }
+        })?;
+
+        let exported = Box::new(Self {
+            name: full_name,
+            instance: AtomicPtr::new(instance as *mut Instance<R, S>),
+            calls: AtomicUsize::new(0),
+            function: function.function,
+        });
+
+        let ret = unsafe {
+            hal_export_funct(
+                full_name_ffi.as_ptr(),
+                Some(Self::call),
+                &*exported as *const Self as *mut c_void,
+                function.uses_fp as i32,
+                function.reentrant as i32,
+                component_id,
+            )
+        };
+
+        match ret {
+            0 => {
+                debug!("Exported function {}", exported.name);
+
+                Ok(exported)
+            }
+            code => Err(FunctionExportError::from_errno(&exported.name, code)),
+        }
+    }
+
+    /// Stop the function from using the component's resources and state
+    ///
+    /// The HAL can call the function until the component is removed with
+    /// [`hal_exit`](linuxcnc_hal_sys::hal_exit), which may be after the resources are dropped. Once
+    /// this returns, any calls in progress have finished and later calls do nothing.
+    pub(crate) fn detach(&self) {
+        self.instance.store(ptr::null_mut(), Ordering::SeqCst);
+
+        while self.calls.load(Ordering::SeqCst) > 0 {
+            hint::spin_loop();
+        }
+    }
+
+    /// Trampoline called by the HAL thread
+    ///
+    /// Panics must not unwind into LinuxCNC, so they are caught and logged here.
+    unsafe extern "C" fn call(arg: *mut c_void, period: c_long) {
+        let exported = &*(arg as *const Self);
+        let period = Duration::from_nanos(period as u64);
+
+        // The count must be raised before the pointer is loaded so `detach` can't miss this call
+        exported.calls.fetch_add(1, Ordering::SeqCst);
+
+        let instance = exported.instance.load(Ordering::SeqCst);
+
+        if !instance.is_null() {
+            let instance = &*instance;
+            let uses_state = exported.function.uses_state();
+
+            if !instance.resources_lock.try_lock_shared() {
+                error!(
+                    "Function {} skipped, the component's resources are borrowed mutably",
+                    exported.name
+                );
+            } else if uses_state && !instance.state_lock.try_lock_exclusive() {
+                instance.resources_lock.unlock_shared();
+
+                error!(
+                    "Function {} skipped, the component state is in use",
+                    exported.name
+                );
+            } else {
+                let result = panic::catch_unwind(AssertUnwindSafe(|| match exported.function {
+                    Callback::Resources(function) => function(&instance.resources, period),
+                    Callback::State(function) => {
+                        function(&instance.resources, &mut *instance.state.get(), period)
+                    }
+                    Callback::Unit(function) => function(&instance.resources, &mut (), period),
+                }));
+
+                if uses_state {
+                    instance.state_lock.unlock_exclusive();
+                }
+
+                instance.resources_lock.unlock_shared();
+
+                if result.is_err() {
+                    error!("Function {} panicked", exported.name);
+                }
+            }
+        }
+
+        exported.calls.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl<R, S> std::fmt::Debug for ExportedFunction<R, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExportedFunction")
+            .field("name", &self.name)
+            .field("instance", &self.instance)
+            .field("calls", &self.calls)
+            .field("uses_state", &self.function.uses_state())
+            .finish()
+    }
+}
@@ -1,26 +1,67 @@
 macro_rules! impl_param {
     ($type:ident, $storage:ty, $hal_fn:expr) => {
+        // SAFETY: See `impl_pin!`
+        unsafe impl Send for $type<$storage> {}
+        unsafe impl Sync for $type<$storage> {}
+
         impl $type<$storage> {
             /// Get the value of the parameter
+            ///
+            /// The value is copied out of shared memory with an atomic read, as it may be changed
+            /// with `setp` at any time.
+            ///
+            /// The parameter's storage is checked when it is registered, so this never returns an
+            /// error.
+            #[deprecated(note = "use `get`, which can't fail")]
             pub fn value(
                 &self,
             ) -> Result<
-                &<Self as $crate::hal_parameter::HalParameter>::Storage,
+                <Self as $crate::hal_parameter::HalParameter>::Storage,
                 $crate::error::StorageError,
             > {
-                $crate::hal_parameter::HalParameter::storage(self)
+                if self.storage.is_null() {
+                    Err($crate::error::StorageError::Null)
+                } else {
+                    Ok(unsafe { $crate::SharedValue::load(self.storage) })
+                }
             }
 
             /// Set the value of the parameter
+            ///
+            /// The value is copied into shared memory with an atomic write.
+            ///
+            /// The parameter's storage is checked when it is registered, so this never returns an
+            /// error.
+            #[deprecated(note = "use `set`, which can't fail")]
             pub fn set_value(
                 &self,
                 value: <Self as $crate::hal_parameter::HalParameter>::Storage,
             ) -> Result<(), $crate::error::StorageError> {
-                let storage = $crate::hal_parameter::HalParameter::storage_mut(self)?;
+                if self.storage.is_null() {
+                    Err($crate::error::StorageError::Null)
+                } else {
+                    unsafe { $crate::SharedValue::store(self.storage, value) };
 
-                *storage = value;
+                    Ok(())
+                }
+            }
+
+            /// Get the value of the parameter
+            ///
+            /// The parameter's storage is checked when it is registered, so unlike
+            /// [`value`](Self::value) this can't fail.
+            #[inline]
+            pub fn get(&self) -> <Self as $crate::hal_parameter::HalParameter>::Storage {
+                unsafe { $crate::SharedValue::load(self.storage) }
+            }
 
-                Ok(())
+            /// Set the value of the parameter
+            ///
+            /// The parameter's storage is checked when it is registered, so unlike
+            /// [`set_value`](Self::set_value) this can't fail.
+            #[inline]
+            pub fn set(&self, value: <Self as $crate::hal_parameter::HalParameter>::Storage) {
+                unsafe { $crate::SharedValue::store(self.storage, value) }
             }
         }
 
@@ -31,72 +72,59 @@ macro_rules! impl_param {
                 &self.name
             }
 
-            fn storage_mut(&self) -> Result<&mut Self::Storage, $crate::error::StorageError> {
-                if self.storage.is_null() {
-                    Err($crate::error::StorageError::Null)
-                } else {
-                    Ok(unsafe { &mut *self.storage })
-                }
-            }
-
-            fn storage(&self) -> Result<&Self::Storage, $crate::error::StorageError> {
-                if self.storage.is_null() {
-                    Err($crate::error::StorageError::Null)
-                } else {
-                    Ok(unsafe { &*self.storage })
-                }
-            }
-
             fn register(
                 full_param_name: &str,
-                component_id: i32,
+                comp: &$crate::RegisterResources,
                 direction: ParameterPermissions,
             ) -> Result<Self, $crate::error::ParameterRegisterError> {
                 if full_param_name.len() > linuxcnc_hal_sys::HAL_NAME_LEN as usize {
-                    return Err($crate::error::ParameterRegisterError::NameLength);
+                    return Err($crate::error::ParameterRegisterError::NameLength {
+                        name: full_param_name.to_string(),
+                    });
                 }
 
                 let full_param_name_ffi = std::ffi::CString::new(full_param_name).map_err(|e| {
                     log::error!("Failed to convert name to C string: {}", e);
 
-                    $crate::error::ParameterRegisterError::NameConversion
+                    $crate::error::ParameterRegisterError::NameConversion {
+                        name: full_param_name.to_string(),
+                    }
                 })?;
 
                 let full_param_name_ffi = full_param_name_ffi.as_c_str();
 
                 log::debug!("Full pin name {:?}", full_param_name);
 
-                let storage = Self::allocate_storage()
-                    .map_err($crate::error::ParameterRegisterError::Storage)?;
+                let storage = Self::allocate_storage().map_err(|source| {
+                    $crate::error::ParameterRegisterError::Storage {
+                        name: full_param_name.to_string(),
+                        source,
+                    }
+                })?;
 
                 let ret = unsafe {
                     $hal_fn(
                         full_param_name_ffi.as_ptr() as *const std::os::raw::c_char,
                         direction as u32,
                         storage,
-                        component_id,
+                        comp.component().id(),
                     )
                 };
 
                 match ret {
-                    x if x == -(linuxcnc_hal_sys::EINVAL as i32) => {
-                        Err($crate::error::ParameterRegisterError::Invalid)
-                    }
-                    x if x == -(linuxcnc_hal_sys::EPERM as i32) => {
-                        Err($crate::error::ParameterRegisterError::LockedHal)
-                    }
-                    x if x == -(linuxcnc_hal_sys::ENOMEM as i32) => {
-                        Err($crate::error::ParameterRegisterError::Memory)
-                    }
                     0 => {
                         debug!("Make pin {} returned {}", full_param_name, ret);
 
                         Ok(Self {
                             name: full_param_name.to_string(),
                             storage,
+                            _component: comp.component().clone(),
                         })
                     }
-                    code => unreachable!("Hit unreachable error code {}", code),
+                    code => Err($crate::error::ParameterRegisterError::from_errno(
+                        full_param_name,
+                        code,
+                    )),
                 }
             }
         }
@@ -9,6 +9,8 @@ use linuxcnc_hal_sys::{
     hal_param_bit_new, hal_param_dir_t_HAL_RO as HAL_RO, hal_param_dir_t_HAL_RW as HAL_RW,
     hal_param_float_new, hal_param_s32_new, hal_param_u32_new,
 };
+#[cfg(linuxcnc_hal_64bit)]
+use linuxcnc_hal_sys::{hal_param_s64_new, hal_param_u64_new};
 pub use parameter_trait::HalParameter;
 
 /// Parameter write mode.
@@ -31,6 +33,8 @@ pub enum ParameterPermissions {
 /// | `Parameter<u32>`  | `u32`   | [`hal_param_u32_new`]                    |
 /// | `Parameter<i32>`  | `i32`   | [`hal_param_s32_new`]                    |
 /// | `Parameter<bool>` | `bool`  | [`hal_param_bit_new`]                    |
+/// | `Parameter<i64>`  | `i64`   | `hal_param_s64_new` (LinuxCNC 2.9+)      |
+/// | `Parameter<u64>`  | `u64`   | `hal_param_u64_new` (LinuxCNC 2.9+)      |
 ///
 /// # Examples
 ///
@@ -40,51 +44,47 @@ pub enum ParameterPermissions {
 ///
 /// ```rust,no_run
 /// use linuxcnc_hal::{
-///    error::PinRegisterError,
-///    hal_param::{Parameter},
-///    prelude::*,
-///    HalComponent, RegisterResources, Resources,
-/// };
-/// use std::{
-///    error::Error,
-///    thread,
-///    time::{Duration, Instant},
+///     error::ParameterRegisterError,
+///     prelude::*,
+///     HalComponent, Parameter, RegisterResources, Resources,
 /// };
+/// use std::{error::Error, thread, time::Duration};
 ///
 /// struct Pins {
-///    parameter: Parameter<f64>,
+///     parameter: Parameter<f64>,
 /// }
 ///
 /// impl Resources for Pins {
-///    type RegisterError = PinRegisterError;
+///     type RegisterError = ParameterRegisterError;
 ///
-///    fn register_resources(comp: &RegisterResources) -> Result<Self, Self::RegisterError> {
-///        Ok(Pins {
-///            parameter: comp.register_param::<Parameter<f64>>("named-parameter")?,
-///        })
-///    }
+///     fn register_resources(comp: &RegisterResources) -> Result<Self, Self::RegisterError> {
+///         Ok(Pins {
+///             parameter: comp.register_parameter::<Parameter<f64>>("named-parameter")?,
+///         })
+///     }
 /// }
 ///
 /// fn main() -> Result<(), Box<dyn Error>> {
-///    let comp: HalComponent<Pins> = HalComponent::new("demo-component")?;
+///     let comp: HalComponent<Pins> = HalComponent::new("demo-component")?;
 ///
-///    let Pins { parameter } = comp.resources();
+///     let Pins { parameter } = comp.resources();
 ///
-///    let start = Instant::now();
-///
-///         // Main control loop
+///     // Main control loop
 ///     while !comp.should_exit() {
-///         parameter.set_value(123.45f64);
+///         parameter.set(123.45);
 ///         thread::sleep(Duration::from_millis(1000));
 ///     }
 ///
-///    Ok(())
+///     Ok(())
 /// }
 /// ```
 #[derive(Debug)]
 pub struct Parameter<S> {
     pub(crate) name: String,
     pub(crate) storage: *mut S,
+
+    /// Keeps the component registered with the HAL while this parameter exists
+    pub(crate) _component: std::sync::Arc<crate::component::ComponentHandle>,
 }
 
 impl<S> Drop for Parameter<S> {
@@ -97,3 +97,7 @@ impl_param!(Parameter, f64, hal_param_float_new);
 impl_param!(Parameter, u32, hal_param_u32_new);
 impl_param!(Parameter, i32, hal_param_s32_new);
 impl_param!(Parameter, bool, hal_param_bit_new);
+#[cfg(linuxcnc_hal_64bit)]
+impl_param!(Parameter, i64, hal_param_s64_new);
+#[cfg(linuxcnc_hal_64bit)]
+impl_param!(Parameter, u64, hal_param_u64_new);
@@ -1,5 +1,6 @@
 use crate::error::{ParameterRegisterError, StorageError};
 use crate::hal_parameter::ParameterPermissions;
+use crate::{shared_value::SharedValue, RegisterResources};
 use linuxcnc_hal_sys::hal_malloc;
 use std::{convert::TryInto, mem};
 
@@ -16,8 +17,9 @@ fn is_aligned_to<T: ?Sized>(ptr: *const T, align: usize) -> bool {
 pub trait HalParameter: Sized + Drop {
     /// The underlying storage type for the given pin
     ///
-    /// This will usually be a scalar value such as `u32` or `bool`
-    type Storage: std::fmt::Debug;
+    /// This will usually be a scalar value such as `u32` or `bool`. Values are always copied in
+    /// and out of shared memory with atomic accesses.
+    type Storage: SharedValue;
 
     /// Allocate memory using [`hal_malloc()`] for storing pin value in
     ///
@@ -58,18 +60,13 @@ pub trait HalParameter: Sized + Drop {
     /// Get the pin's name
     fn name(&self) -> &str;
 
-    /// Get a mutable pointer to underlying shared memory storing this pin's value
-    fn storage_mut(&self) -> Result<&mut Self::Storage, StorageError>;
-
-    /// Get a reference to the underlying shared memory storing the pin's value
-    fn storage(&self) -> Result<&Self::Storage, StorageError>;
-
-    /// Register the pin with the LinuxCNC HAL
+    /// Register the parameter with the LinuxCNC HAL
     ///
-    /// Returns a raw pointer to the underling HAL shared memory for the pin
+    /// The parameter holds a handle to `comp`'s component, which stays registered with the HAL
+    /// until every parameter referring to it has been dropped.
     fn register(
         full_pin_name: &str,
-        component_id: i32,
+        comp: &RegisterResources,
         direction: ParameterPermissions,
     ) -> Result<Self, ParameterRegisterError>;
 }
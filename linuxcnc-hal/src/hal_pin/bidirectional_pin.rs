@@ -1,5 +1,7 @@
 use crate::hal_pin::{pin_direction::PinDirection, PinRead, PinWrite};
 use linuxcnc_hal_sys::{hal_pin_bit_new, hal_pin_float_new, hal_pin_s32_new, hal_pin_u32_new};
+#[cfg(linuxcnc_hal_64bit)]
+use linuxcnc_hal_sys::{hal_pin_s64_new, hal_pin_u64_new};
 
 /// A pin that can be both read from and written to
 ///
@@ -11,6 +13,8 @@ use linuxcnc_hal_sys::{hal_pin_bit_new, hal_pin_float_new, hal_pin_s32_new, hal_
 /// | `BidirectionalPin<u32>`  | `u32`   | [`hal_pin_u32_new`]                    |
 /// | `BidirectionalPin<i32>`  | `i32`   | [`hal_pin_s32_new`]                    |
 /// | `BidirectionalPin<bool>` | `bool`  | [`hal_pin_bit_new`]                    |
+/// | `BidirectionalPin<i64>`  | `i64`   | `hal_pin_s64_new` (LinuxCNC 2.9+)      |
+/// | `BidirectionalPin<u64>`  | `u64`   | `hal_pin_u64_new` (LinuxCNC 2.9+)      |
 ///
 /// # Examples
 ///
@@ -54,9 +58,9 @@ use linuxcnc_hal_sys::{hal_pin_bit_new, hal_pin_float_new, hal_pin_s32_new, hal_
 ///
 ///         // Main control loop
 ///         while !comp.should_exit() {
-///             println!("Input: {:?}", pin.value());
+///             println!("Input: {:?}", pin.get());
 ///
-///             pin.set_value(123.45f64);
+///             pin.set(123.45f64);
 ///
 ///             thread::sleep(Duration::from_millis(1000));
 ///         }
@@ -68,6 +72,9 @@ use linuxcnc_hal_sys::{hal_pin_bit_new, hal_pin_float_new, hal_pin_s32_new, hal_
 pub struct BidirectionalPin<S> {
     pub(crate) name: String,
     pub(crate) storage: *mut *mut S,
+
+    /// Keeps the component registered with the HAL while this pin exists
+    pub(crate) _component: std::sync::Arc<crate::component::ComponentHandle>,
 }
 
 impl<S> Drop for BidirectionalPin<S> {
@@ -110,3 +117,28 @@ impl PinRead for BidirectionalPin<f64> {}
 impl PinRead for BidirectionalPin<u32> {}
 impl PinRead for BidirectionalPin<i32> {}
 impl PinRead for BidirectionalPin<bool> {}
+
+#[cfg(linuxcnc_hal_64bit)]
+impl_pin!(
+    BidirectionalPin,
+    i64,
+    hal_pin_s64_new,
+    PinDirection::Bidirectional
+);
+#[cfg(linuxcnc_hal_64bit)]
+impl_pin!(
+    BidirectionalPin,
+    u64,
+    hal_pin_u64_new,
+    PinDirection::Bidirectional
+);
+
+#[cfg(linuxcnc_hal_64bit)]
+impl PinWrite for BidirectionalPin<i64> {}
+#[cfg(linuxcnc_hal_64bit)]
+impl PinWrite for BidirectionalPin<u64> {}
+
+#[cfg(linuxcnc_hal_64bit)]
+impl PinRead for BidirectionalPin<i64> {}
+#[cfg(linuxcnc_hal_64bit)]
+impl PinRead for BidirectionalPin<u64> {}
@@ -1,4 +1,9 @@
-use crate::error::{PinRegisterError, StorageError};
+use super::sealed::Sealed;
+use crate::{
+    error::{PinRegisterError, StorageError},
+    shared_value::SharedValue,
+    RegisterResources,
+};
 use linuxcnc_hal_sys::hal_malloc;
 use std::{convert::TryInto, mem};
 
@@ -12,11 +17,14 @@ fn is_aligned_to<T: ?Sized>(ptr: *const T, align: usize) -> bool {
 /// HAL pin trait
 ///
 /// Implemented for any HAL pin. Handles allocation of backing storage in LinuxCNC's memory space.
-pub trait HalPin: Sized + Drop {
+///
+/// This trait is sealed and is implemented for every pin type in this crate.
+pub trait HalPin: Sized + Drop + Sealed {
     /// The underlying storage type for the given pin
     ///
-    /// This will usually be a scalar value such as `u32` or `bool`
-    type Storage: std::fmt::Debug;
+    /// This will usually be a scalar value such as `u32` or `bool`. Values are always copied in
+    /// and out of shared memory with atomic accesses.
+    type Storage: SharedValue;
 
     /// Allocate memory using [`hal_malloc()`] for storing pin value in
     ///
@@ -57,14 +65,18 @@ pub trait HalPin: Sized + Drop {
     /// Get the pin's name
     fn name(&self) -> &str;
 
-    /// Get a mutable pointer to underlying shared memory storing this pin's value
-    fn storage_mut(&self) -> Result<&mut Self::Storage, StorageError>;
-
-    /// Get a reference to the underlying shared memory storing the pin's value
-    fn storage(&self) -> Result<&Self::Storage, StorageError>;
+    /// Get the address the HAL stores the pointer to this pin's value in
+    ///
+    /// Both this pointer and the pointer it points to are checked for null when the pin is
+    /// registered. The HAL changes the inner pointer when the pin is linked to a signal, so it must
+    /// be read again on every access. Both pointers must only be accessed atomically, as the HAL
+    /// and other components may change them at any time.
+    #[doc(hidden)]
+    fn storage_ptr(&self) -> *mut *mut Self::Storage;
 
     /// Register the pin with the LinuxCNC HAL
     ///
-    /// Returns a raw pointer to the underling HAL shared memory for the pin
-    fn register(full_pin_name: &str, component_id: i32) -> Result<Self, PinRegisterError>;
+    /// The pin holds a handle to `comp`'s component, which stays registered with the HAL until
+    /// every pin referring to it has been dropped.
+    fn register(full_pin_name: &str, comp: &RegisterResources) -> Result<Self, PinRegisterError>;
 }
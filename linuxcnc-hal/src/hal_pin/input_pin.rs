@@ -1,5 +1,10 @@
-use crate::hal_pin::{pin_direction::PinDirection, PinRead};
+use crate::{
+    hal_pin::{pin_direction::PinDirection, HalPin, PinRead},
+    shared_value::{load_ptr, AtomicCell, SharedValue},
+};
 use linuxcnc_hal_sys::{hal_pin_bit_new, hal_pin_float_new, hal_pin_s32_new, hal_pin_u32_new};
+#[cfg(linuxcnc_hal_64bit)]
+use linuxcnc_hal_sys::{hal_pin_s64_new, hal_pin_u64_new};
 
 /// An input pin readable by the component
 ///
@@ -11,6 +16,8 @@ use linuxcnc_hal_sys::{hal_pin_bit_new, hal_pin_float_new, hal_pin_s32_new, hal_
 /// | `InputPin<u32>`  | `u32`   | [`hal_pin_u32_new`]                    |
 /// | `InputPin<i32>`  | `i32`   | [`hal_pin_s32_new`]                    |
 /// | `InputPin<bool>` | `bool`  | [`hal_pin_bit_new`]                    |
+/// | `InputPin<i64>`  | `i64`   | `hal_pin_s64_new` (LinuxCNC 2.9+)      |
+/// | `InputPin<u64>`  | `u64`   | `hal_pin_u64_new` (LinuxCNC 2.9+)      |
 ///
 /// # Examples
 ///
@@ -54,7 +61,7 @@ use linuxcnc_hal_sys::{hal_pin_bit_new, hal_pin_float_new, hal_pin_s32_new, hal_
 ///
 ///         // Main control loop
 ///         while !comp.should_exit() {
-///             println!("Input: {:?}", pin.value());
+///             println!("Input: {:?}", pin.get());
 ///
 ///             thread::sleep(Duration::from_millis(1000));
 ///         }
@@ -62,24 +69,148 @@ use linuxcnc_hal_sys::{hal_pin_bit_new, hal_pin_float_new, hal_pin_s32_new, hal_
 ///    Ok(())
 /// }
 /// ```
+///
+/// ## Detect changes
+///
+/// [`InputPin::changed_since_last`] reports whether a pin's value has changed since it was last
+/// called, and `InputPin<bool>` can detect [rising](InputPin::rising_edge) and
+/// [falling](InputPin::falling_edge) edges. Each method remembers the value it saw independently
+/// of the others, starting from the pin's value when it was registered, so each can be called once
+/// per cycle.
+///
+/// ```rust,no_run
+/// use linuxcnc_hal::{hal_pin::InputPin, HalComponent, Resources};
+/// use std::{thread, time::Duration};
+///
+/// #[derive(Resources)]
+/// struct Pins {
+///     start: InputPin<bool>,
+///     speed: InputPin<f64>,
+/// }
+///
+/// let comp: HalComponent<Pins> = HalComponent::new("edges").unwrap();
+/// let pins = comp.resources();
+///
+/// while !comp.should_exit() {
+///     if pins.start.rising_edge() {
+///         println!("Started");
+///     }
+///
+///     if pins.start.falling_edge() {
+///         println!("Stopped");
+///     }
+///
+///     if pins.speed.changed_since_last() {
+///         println!("New speed");
+///     }
+///
+///     thread::sleep(Duration::from_millis(10));
+/// }
+/// ```
 #[derive(Debug)]
-pub struct InputPin<S> {
+pub struct InputPin<S: SharedValue> {
     pub(crate) name: String,
     pub(crate) storage: *mut *mut S,
+
+    /// Keeps the component registered with the HAL while this pin exists
+    pub(crate) _component: std::sync::Arc<crate::component::ComponentHandle>,
+
+    /// Value seen by the last call to [`InputPin::changed_since_last`]
+    pub(crate) previous: AtomicCell<S>,
+
+    /// Values seen by the last calls to [`InputPin::rising_edge`] and [`InputPin::falling_edge`],
+    /// indexed by `RISING` and `FALLING`
+    pub(crate) edges: S::Edges,
+}
+
+const RISING: usize = 0;
+const FALLING: usize = 1;
+
+/// Start change detection from the value of a newly registered pin
+fn previous_value<S: SharedValue>(storage: *mut *mut S) -> AtomicCell<S> {
+    // SAFETY: `register` only calls this once it has checked that the pin's storage is non-null
+    AtomicCell::new(unsafe { S::load(load_ptr(storage)) })
 }
 
-impl<S> Drop for InputPin<S> {
+/// Start edge detection from the value of a newly registered pin
+fn previous_edges(storage: *mut *mut bool) -> [AtomicCell<bool>; 2] {
+    [previous_value(storage), previous_value(storage)]
+}
+
+/// Pins other than `InputPin<bool>` don't detect edges
+fn no_edges<S>(_storage: *mut *mut S) {}
+
+impl<S: SharedValue> Drop for InputPin<S> {
     fn drop(&mut self) {
         debug!("Drop InputPin {}", self.name);
     }
 }
 
-impl_pin!(InputPin, f64, hal_pin_float_new, PinDirection::In);
-impl_pin!(InputPin, u32, hal_pin_u32_new, PinDirection::In);
-impl_pin!(InputPin, i32, hal_pin_s32_new, PinDirection::In);
-impl_pin!(InputPin, bool, hal_pin_bit_new, PinDirection::In);
+impl_pin!(InputPin, f64, hal_pin_float_new, PinDirection::In, {
+    previous: previous_value,
+    edges: no_edges,
+});
+impl_pin!(InputPin, u32, hal_pin_u32_new, PinDirection::In, {
+    previous: previous_value,
+    edges: no_edges,
+});
+impl_pin!(InputPin, i32, hal_pin_s32_new, PinDirection::In, {
+    previous: previous_value,
+    edges: no_edges,
+});
+impl_pin!(InputPin, bool, hal_pin_bit_new, PinDirection::In, {
+    previous: previous_value,
+    edges: previous_edges,
+});
 
 impl PinRead for InputPin<f64> {}
 impl PinRead for InputPin<u32> {}
 impl PinRead for InputPin<i32> {}
 impl PinRead for InputPin<bool> {}
+
+#[cfg(linuxcnc_hal_64bit)]
+impl_pin!(InputPin, i64, hal_pin_s64_new, PinDirection::In, {
+    previous: previous_value,
+    edges: no_edges,
+});
+#[cfg(linuxcnc_hal_64bit)]
+impl_pin!(InputPin, u64, hal_pin_u64_new, PinDirection::In, {
+    previous: previous_value,
+    edges: no_edges,
+});
+
+#[cfg(linuxcnc_hal_64bit)]
+impl PinRead for InputPin<i64> {}
+#[cfg(linuxcnc_hal_64bit)]
+impl PinRead for InputPin<u64> {}
+
+impl<S> InputPin<S>
+where
+    Self: PinRead + HalPin<Storage = S>,
+    S: SharedValue + PartialEq,
+{
+    /// Check whether the pin's value has changed since this method was last called
+    ///
+    /// The first call compares against the pin's value when it was registered.
+    pub fn changed_since_last(&self) -> bool {
+        let value = self.get();
+
+        self.previous.swap(value) != value
+    }
+}
+
+impl InputPin<bool> {
+    /// Check whether the pin has gone from low to high since this method was last called
+    pub fn rising_edge(&self) -> bool {
+        let value = self.get();
+
+        !self.edges[RISING].swap(value) && value
+    }
+
+    /// Check whether the pin has gone from high to low since this method was last called
+    pub fn falling_edge(&self) -> bool {
+        let value = self.get();
+
+        self.edges[FALLING].swap(value) && !value
+    }
+}
@@ -0,0 +1,134 @@
+use crate::{error::PortError, hal_pin::pin_direction::PinDirection, hal_pin::read_storage};
+use linuxcnc_hal_sys::{
+    hal_pin_port_new, hal_port_buffer_size, hal_port_peek, hal_port_peek_commit, hal_port_read,
+    hal_port_readable, hal_port_t,
+};
+use std::os::raw::{c_char, c_uint};
+
+/// A port the component can read a stream of bytes from
+///
+/// Ports are byte FIFOs shared between two components. An `InputPort` must be linked to a `port`
+/// signal which is written to by another component's [`OutputPort`](crate::hal_pin::OutputPort).
+/// The size of the buffer is set on the signal with `sets <signal> <size>`. Until the port is
+/// linked, it has a buffer size of zero and all reads fail.
+///
+/// Reads are all or nothing: if the port doesn't hold enough bytes to fill the buffer, nothing is
+/// read and [`PortError::NotEnoughData`] is returned.
+///
+/// A port has a single reader, so reading from it needs a mutable reference. Use
+/// [`HalComponent::resources_mut`](crate::HalComponent::resources_mut) to read from a port in a
+/// component's resources.
+///
+/// | Type        | Equivalent `linuxcnc_hal_sys` function |
+/// | ----------- | -------------------------------------- |
+/// | `InputPort` | `hal_pin_port_new` (LinuxCNC 2.9+)     |
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use linuxcnc_hal::{hal_pin::InputPort, HalComponent, Resources};
+/// use std::{error::Error, thread, time::Duration};
+///
+/// #[derive(Resources)]
+/// struct Pins {
+///     commands: InputPort,
+/// }
+///
+/// fn main() -> Result<(), Box<dyn Error>> {
+///     let mut comp: HalComponent<Pins> = HalComponent::new("port-reader")?;
+///
+///     let mut header = [0u8; 2];
+///
+///     while !comp.should_exit() {
+///         let mut pins = comp.resources_mut();
+///         let commands = &mut pins.commands;
+///
+///         // Look at the header without removing it to find the length of the message
+///         if commands.peek(&mut header).is_ok() {
+///             let mut message = vec![0u8; 2 + usize::from(u16::from_le_bytes(header))];
+///
+///             if commands.read(&mut message).is_ok() {
+///                 println!("Message: {:?}", &message[2..]);
+///             }
+///         }
+///
+///         thread::sleep(Duration::from_millis(10));
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct InputPort {
+    pub(crate) name: String,
+    pub(crate) storage: *mut *mut hal_port_t,
+
+    /// Keeps the component registered with the HAL while this port exists
+    pub(crate) _component: std::sync::Arc<crate::component::ComponentHandle>,
+}
+
+impl Drop for InputPort {
+    fn drop(&mut self) {
+        debug!("Drop InputPort {}", self.name);
+    }
+}
+
+impl_pin!(@impl InputPort, hal_port_t, hal_pin_port_new, PinDirection::In);
+
+impl InputPort {
+    /// Read exactly `buf.len()` bytes from the port, removing them from the port
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<(), PortError> {
+        let port = read_storage(self)?;
+
+        let ok =
+            unsafe { hal_port_read(port, buf.as_mut_ptr() as *mut c_char, buf.len() as c_uint) };
+
+        self.check_read(ok, buf.len())
+    }
+
+    /// Read exactly `buf.len()` bytes from the port without removing them
+    ///
+    /// Use [`consume`](InputPort::consume) to remove bytes once they have been peeked.
+    pub fn peek(&mut self, buf: &mut [u8]) -> Result<(), PortError> {
+        let port = read_storage(self)?;
+
+        let ok =
+            unsafe { hal_port_peek(port, buf.as_mut_ptr() as *mut c_char, buf.len() as c_uint) };
+
+        self.check_read(ok, buf.len())
+    }
+
+    /// Remove exactly `count` bytes from the port without reading them
+    pub fn consume(&mut self, count: usize) -> Result<(), PortError> {
+        let port = read_storage(self)?;
+
+        let ok = unsafe { hal_port_peek_commit(port, count as c_uint) };
+
+        self.check_read(ok, count)
+    }
+
+    /// Get the number of bytes available to read
+    pub fn readable(&self) -> Result<usize, PortError> {
+        let port = read_storage(self)?;
+
+        Ok(unsafe { hal_port_readable(port) } as usize)
+    }
+
+    /// Get the total size of the port's buffer in bytes
+    pub fn buffer_size(&self) -> Result<usize, PortError> {
+        let port = read_storage(self)?;
+
+        Ok(unsafe { hal_port_buffer_size(port) } as usize)
+    }
+
+    fn check_read(&self, ok: bool, requested: usize) -> Result<(), PortError> {
+        if ok {
+            Ok(())
+        } else {
+            Err(PortError::NotEnoughData {
+                requested,
+                available: self.readable()?,
+            })
+        }
+    }
+}
@@ -1,67 +1,86 @@
 macro_rules! impl_pin {
     ($type:ident, $storage:ty, $hal_fn:expr, $direction:expr) => {
-        impl $crate::hal_pin::HalPin for $type<$storage> {
+        impl_pin!(@impl $type<$storage>, $storage, $hal_fn, $direction, {});
+    };
+    // Pins with extra fields give functions in braces that create each field's initial value from
+    // the registered pin's storage pointer
+    ($type:ident, $storage:ty, $hal_fn:expr, $direction:expr, { $($fields:tt)* }) => {
+        impl_pin!(@impl $type<$storage>, $storage, $hal_fn, $direction, { $($fields)* });
+    };
+    // Implement `HalPin` for a pin type that isn't generic over its storage, e.g. ports
+    (@impl $pin:ty, $storage:ty, $hal_fn:expr, $direction:expr) => {
+        impl_pin!(@impl $pin, $storage, $hal_fn, $direction, {});
+    };
+    (
+        @impl $pin:ty, $storage:ty, $hal_fn:expr, $direction:expr,
+        { $($field:ident: $init:expr),* $(,)? }
+    ) => {
+        // SAFETY: The pin's storage lives in HAL shared memory, which is valid on any thread for
+        // as long as the component is registered, and the pin keeps the component registered. All
+        // accesses to the storage are atomic.
+        unsafe impl Send for $pin {}
+        unsafe impl Sync for $pin {}
+
+        impl $crate::hal_pin::sealed::Sealed for $pin {}
+
+        impl $crate::hal_pin::HalPin for $pin {
             type Storage = $storage;
 
             fn name(&self) -> &str {
                 &self.name
             }
 
-            fn storage_mut(&self) -> Result<&mut Self::Storage, $crate::error::StorageError> {
-                if self.storage.is_null() {
-                    Err($crate::error::StorageError::Null)
-                } else {
-                    Ok(unsafe { &mut **self.storage })
-                }
-            }
-
-            fn storage(&self) -> Result<&Self::Storage, $crate::error::StorageError> {
-                if self.storage.is_null() {
-                    Err($crate::error::StorageError::Null)
-                } else {
-                    Ok(unsafe { &**self.storage })
-                }
+            #[inline]
+            fn storage_ptr(&self) -> *mut *mut Self::Storage {
+                self.storage
             }
 
             fn register(
                 full_pin_name: &str,
-                component_id: i32,
+                comp: &$crate::RegisterResources,
             ) -> Result<Self, $crate::error::PinRegisterError> {
                 if full_pin_name.len() > linuxcnc_hal_sys::HAL_NAME_LEN as usize {
-                    return Err($crate::error::PinRegisterError::NameLength);
+                    return Err($crate::error::PinRegisterError::NameLength {
+                        name: full_pin_name.to_string(),
+                    });
                 }
 
                 let full_pin_name_ffi = std::ffi::CString::new(full_pin_name).map_err(|e| {
                     log::error!("Failed to convert name to C string: {}", e);
 
-                    $crate::error::PinRegisterError::NameConversion
+                    $crate::error::PinRegisterError::NameConversion {
+                        name: full_pin_name.to_string(),
+                    }
                 })?;
 
                 let full_pin_name_ffi = full_pin_name_ffi.as_c_str();
 
                 log::debug!("Full pin name {:?}", full_pin_name);
 
-                let storage =
-                    Self::allocate_storage().map_err($crate::error::PinRegisterError::Storage)?;
+                let storage = Self::allocate_storage().map_err(|source| {
+                    $crate::error::PinRegisterError::Storage {
+                        name: full_pin_name.to_string(),
+                        source,
+                    }
+                })?;
 
                 let ret = unsafe {
                     $hal_fn(
                         full_pin_name_ffi.as_ptr() as *const std::os::raw::c_char,
                         $direction as i32,
                         storage,
-                        component_id,
+                        comp.component().id(),
                     )
                 };
 
                 match ret {
-                    x if x == -(linuxcnc_hal_sys::EINVAL as i32) => {
-                        Err($crate::error::PinRegisterError::Invalid)
-                    }
-                    x if x == -(linuxcnc_hal_sys::EPERM as i32) => {
-                        Err($crate::error::PinRegisterError::LockedHal)
-                    }
-                    x if x == -(linuxcnc_hal_sys::ENOMEM as i32) => {
-                        Err($crate::error::PinRegisterError::Memory)
+                    // The HAL points new pins at their dummy storage, so this can only fail if
+                    // something has gone very wrong. Checking here means pin access never has to.
+                    0 if unsafe { $crate::shared_value::load_ptr(storage).is_null() } => {
+                        Err($crate::error::PinRegisterError::Storage {
+                            name: full_pin_name.to_string(),
+                            source: $crate::error::StorageError::Null,
+                        })
                     }
                     0 => {
                         debug!("Make pin {} returned {}", full_pin_name, ret);
@@ -69,9 +88,14 @@ macro_rules! impl_pin {
                         Ok(Self {
                             name: full_pin_name.to_string(),
                             storage,
+                            _component: comp.component().clone(),
+                            $($field: ($init)(storage),)*
                         })
                     }
-                    code => unreachable!("Hit unreachable error code {}", code),
+                    code => Err($crate::error::PinRegisterError::from_errno(
+                        full_pin_name,
+                        code,
+                    )),
                 }
             }
         }
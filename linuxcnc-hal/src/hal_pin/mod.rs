@@ -6,22 +6,86 @@ mod macros;
 mod bidirectional_pin;
 mod hal_pin;
 mod input_pin;
+#[cfg(linuxcnc_hal_port)]
+mod input_port;
 mod output_pin;
+#[cfg(linuxcnc_hal_port)]
+mod output_port;
 mod pin_direction;
 
 pub use self::{
     bidirectional_pin::BidirectionalPin, hal_pin::HalPin, input_pin::InputPin,
     output_pin::OutputPin,
 };
-use crate::error::StorageError;
+#[cfg(linuxcnc_hal_port)]
+pub use self::{input_port::InputPort, output_port::OutputPort};
+use crate::{
+    error::StorageError,
+    shared_value::{load_ptr, SharedValue},
+};
+
+pub(crate) mod sealed {
+    /// Stops pins from being implemented outside this crate
+    ///
+    /// [`PinRead::get`](super::PinRead::get) and [`PinWrite::set`](super::PinWrite::set) rely on
+    /// the pointers returned by [`HalPin::storage_ptr`](super::HalPin::storage_ptr) having been
+    /// checked when the pin was registered, which only this crate's pin types guarantee.
+    pub trait Sealed {}
+}
+
+/// Read a pin's value out of shared memory
+///
+/// The HAL may repoint the pin at a signal's storage at any time, so the inner pointer is read
+/// on every call. Both reads are atomic so the compiler can't cache or elide them.
+#[inline]
+pub(crate) fn read_storage<P: HalPin>(pin: &P) -> Result<P::Storage, StorageError> {
+    let ptr = unsafe { load_ptr(pin.storage_ptr()) };
+
+    if ptr.is_null() {
+        Err(StorageError::Null)
+    } else {
+        Ok(unsafe { P::Storage::load(ptr) })
+    }
+}
+
+/// Write a pin's value into shared memory
+///
+/// See [`read_storage`].
+#[inline]
+pub(crate) fn write_storage<P: HalPin>(pin: &P, value: P::Storage) -> Result<(), StorageError> {
+    let ptr = unsafe { load_ptr(pin.storage_ptr()) };
+
+    if ptr.is_null() {
+        Err(StorageError::Null)
+    } else {
+        unsafe { P::Storage::store(ptr, value) };
+
+        Ok(())
+    }
+}
 
 /// Readable pin trait
 ///
 /// Implemented for any pin that can only be read by a component
 pub trait PinRead: HalPin {
     /// Get the value of the pin
-    fn value(&self) -> Result<&<Self as HalPin>::Storage, StorageError> {
-        self.storage()
+    ///
+    /// The value is copied out of shared memory with an atomic read. A reference into shared
+    /// memory is never returned, as another component may write to it at any time.
+    ///
+    /// The pin's storage is checked when it is registered, so this never returns an error.
+    #[deprecated(note = "use `PinRead::get`, which can't fail")]
+    fn value(&self) -> Result<<Self as HalPin>::Storage, StorageError> {
+        read_storage(self)
+    }
+
+    /// Get the value of the pin
+    ///
+    /// The pin's storage is checked when it is registered, so unlike [`PinRead::value`] this
+    /// can't fail. Prefer this method in realtime functions.
+    #[inline]
+    fn get(&self) -> <Self as HalPin>::Storage {
+        unsafe { <Self as HalPin>::Storage::load(load_ptr(self.storage_ptr())) }
     }
 }
 
@@ -30,7 +94,21 @@ pub trait PinRead: HalPin {
 /// Implemented for any pin that can be only written to by a component
 pub trait PinWrite: HalPin {
     /// Set the value of the pin
+    ///
+    /// The value is copied into shared memory with an atomic write.
+    ///
+    /// The pin's storage is checked when it is registered, so this never returns an error.
+    #[deprecated(note = "use `PinWrite::set`, which can't fail")]
     fn set_value(&self, value: <Self as HalPin>::Storage) -> Result<(), StorageError> {
-        Ok(*self.storage_mut()? = value)
+        write_storage(self, value)
+    }
+
+    /// Set the value of the pin
+    ///
+    /// The pin's storage is checked when it is registered, so unlike [`PinWrite::set_value`] this
+    /// can't fail. Prefer this method in realtime functions.
+    #[inline]
+    fn set(&self, value: <Self as HalPin>::Storage) {
+        unsafe { <Self as HalPin>::Storage::store(load_ptr(self.storage_ptr()), value) }
     }
 }
@@ -1,5 +1,7 @@
 use crate::hal_pin::{pin_direction::PinDirection, PinWrite};
 use linuxcnc_hal_sys::{hal_pin_bit_new, hal_pin_float_new, hal_pin_s32_new, hal_pin_u32_new};
+#[cfg(linuxcnc_hal_64bit)]
+use linuxcnc_hal_sys::{hal_pin_s64_new, hal_pin_u64_new};
 
 /// A pin that can be written to by the component
 ///
@@ -11,6 +13,8 @@ use linuxcnc_hal_sys::{hal_pin_bit_new, hal_pin_float_new, hal_pin_s32_new, hal_
 /// | `OutputPin<u32>`  | `u32`   | [`hal_pin_u32_new`]                    |
 /// | `OutputPin<i32>`  | `i32`   | [`hal_pin_s32_new`]                    |
 /// | `OutputPin<bool>` | `bool`  | [`hal_pin_bit_new`]                    |
+/// | `OutputPin<i64>`  | `i64`   | `hal_pin_s64_new` (LinuxCNC 2.9+)      |
+/// | `OutputPin<u64>`  | `u64`   | `hal_pin_u64_new` (LinuxCNC 2.9+)      |
 ///
 /// # Examples
 ///
@@ -54,7 +58,7 @@ use linuxcnc_hal_sys::{hal_pin_bit_new, hal_pin_float_new, hal_pin_s32_new, hal_
 ///
 ///         // Main control loop
 ///     while !comp.should_exit() {
-///         pin.set_value(123.45f64);
+///         pin.set(123.45f64);
 ///         thread::sleep(Duration::from_millis(1000));
 ///     }
 ///
@@ -65,6 +69,9 @@ use linuxcnc_hal_sys::{hal_pin_bit_new, hal_pin_float_new, hal_pin_s32_new, hal_
 pub struct OutputPin<S> {
     pub(crate) name: String,
     pub(crate) storage: *mut *mut S,
+
+    /// Keeps the component registered with the HAL while this pin exists
+    pub(crate) _component: std::sync::Arc<crate::component::ComponentHandle>,
 }
 
 impl<S> Drop for OutputPin<S> {
@@ -82,3 +89,13 @@ impl PinWrite for OutputPin<f64> {}
 impl PinWrite for OutputPin<u32> {}
 impl PinWrite for OutputPin<i32> {}
 impl PinWrite for OutputPin<bool> {}
+
+#[cfg(linuxcnc_hal_64bit)]
+impl_pin!(OutputPin, i64, hal_pin_s64_new, PinDirection::Out);
+#[cfg(linuxcnc_hal_64bit)]
+impl_pin!(OutputPin, u64, hal_pin_u64_new, PinDirection::Out);
+
+#[cfg(linuxcnc_hal_64bit)]
+impl PinWrite for OutputPin<i64> {}
+#[cfg(linuxcnc_hal_64bit)]
+impl PinWrite for OutputPin<u64> {}
@@ -0,0 +1,98 @@
+use crate::{error::PortError, hal_pin::pin_direction::PinDirection, hal_pin::read_storage};
+use linuxcnc_hal_sys::{
+    hal_pin_port_new, hal_port_buffer_size, hal_port_t, hal_port_writable, hal_port_write,
+};
+use std::os::raw::{c_char, c_uint};
+
+/// A port the component can write a stream of bytes to
+///
+/// Ports are byte FIFOs shared between two components. An `OutputPort` must be linked to a `port`
+/// signal which is read by another component's [`InputPort`](crate::hal_pin::InputPort). The size
+/// of the buffer is set on the signal with `sets <signal> <size>`. Until the port is linked, it has
+/// a buffer size of zero and all writes fail.
+///
+/// Writes are all or nothing: if the port doesn't have enough free space for the data, nothing is
+/// written and [`PortError::NotEnoughSpace`] is returned.
+///
+/// A port has a single writer, so writing to it needs a mutable reference. Use
+/// [`HalComponent::resources_mut`](crate::HalComponent::resources_mut) to write to a port in a
+/// component's resources.
+///
+/// | Type         | Equivalent `linuxcnc_hal_sys` function |
+/// | ------------ | -------------------------------------- |
+/// | `OutputPort` | `hal_pin_port_new` (LinuxCNC 2.9+)     |
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use linuxcnc_hal::{hal_pin::OutputPort, HalComponent, Resources};
+/// use std::{error::Error, thread, time::Duration};
+///
+/// #[derive(Resources)]
+/// struct Pins {
+///     log: OutputPort,
+/// }
+///
+/// fn main() -> Result<(), Box<dyn Error>> {
+///     let mut comp: HalComponent<Pins> = HalComponent::new("port-writer")?;
+///
+///     while !comp.should_exit() {
+///         if let Err(e) = comp.resources_mut().log.write(b"tick\n") {
+///             println!("Dropped message: {}", e);
+///         }
+///
+///         thread::sleep(Duration::from_millis(1000));
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct OutputPort {
+    pub(crate) name: String,
+    pub(crate) storage: *mut *mut hal_port_t,
+
+    /// Keeps the component registered with the HAL while this port exists
+    pub(crate) _component: std::sync::Arc<crate::component::ComponentHandle>,
+}
+
+impl Drop for OutputPort {
+    fn drop(&mut self) {
+        debug!("Drop OutputPort {}", self.name);
+    }
+}
+
+impl_pin!(@impl OutputPort, hal_port_t, hal_pin_port_new, PinDirection::Out);
+
+impl OutputPort {
+    /// Write all of `buf` to the port
+    pub fn write(&mut self, buf: &[u8]) -> Result<(), PortError> {
+        let port = read_storage(self)?;
+
+        let ok =
+            unsafe { hal_port_write(port, buf.as_ptr() as *const c_char, buf.len() as c_uint) };
+
+        if ok {
+            Ok(())
+        } else {
+            Err(PortError::NotEnoughSpace {
+                requested: buf.len(),
+                available: self.writable()?,
+            })
+        }
+    }
+
+    /// Get the number of bytes of free space in the port
+    pub fn writable(&self) -> Result<usize, PortError> {
+        let port = read_storage(self)?;
+
+        Ok(unsafe { hal_port_writable(port) } as usize)
+    }
+
+    /// Get the total size of the port's buffer in bytes
+    pub fn buffer_size(&self) -> Result<usize, PortError> {
+        let port = read_storage(self)?;
+
+        Ok(unsafe { hal_port_buffer_size(port) } as usize)
+    }
+}
@@ -0,0 +1,365 @@
+//! HAL signals
+//!
+//! Signals connect pins of different components together, like `halcmd net`. A signal is created
+//! with [`Signal::new`], then pins are connected to it by their full name with [`link`].
+//!
+//! The HAL must be initialised before signals can be used, so at least one
+//! [`HalComponent`](crate::HalComponent) must exist in the process.
+//!
+//! # Examples
+//!
+//! Connect the output of one component to the input of another, equivalent to
+//! `net speed encoder.velocity pid.feedback`.
+//!
+//! ```rust,no_run
+//! use linuxcnc_hal::{
+//!     hal_signal::{link, Signal},
+//!     HalComponent, Resources,
+//! };
+//! use std::error::Error;
+//!
+//! #[derive(Resources)]
+//! struct Empty {}
+//!
+//! fn main() -> Result<(), Box<dyn Error>> {
+//!     let _comp: HalComponent<Empty> = HalComponent::new("net-setup")?;
+//!
+//!     let speed = Signal::<f64>::new("speed")?;
+//!
+//!     link("encoder.velocity", &speed)?;
+//!     link("pid.feedback", &speed)?;
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::error::SignalError;
+#[cfg(linuxcnc_hal_port)]
+use linuxcnc_hal_sys::hal_type_t_HAL_PORT;
+use linuxcnc_hal_sys::{
+    hal_link, hal_signal_delete, hal_signal_new, hal_type_t, hal_type_t_HAL_BIT,
+    hal_type_t_HAL_FLOAT, hal_type_t_HAL_S32, hal_type_t_HAL_U32, hal_unlink, HAL_NAME_LEN,
+};
+#[cfg(linuxcnc_hal_64bit)]
+use linuxcnc_hal_sys::{hal_type_t_HAL_S64, hal_type_t_HAL_U64};
+use std::{ffi::CString, marker::PhantomData};
+
+/// A type that can be carried by a [`Signal`]
+///
+/// | Type   | HAL type                   |
+/// | ------ | -------------------------- |
+/// | `f64`  | `HAL_FLOAT`                |
+/// | `u32`  | `HAL_U32`                  |
+/// | `i32`  | `HAL_S32`                  |
+/// | `bool` | `HAL_BIT`                  |
+/// | `i64`  | `HAL_S64` (LinuxCNC 2.9+)  |
+/// | `u64`  | `HAL_U64` (LinuxCNC 2.9+)  |
+/// | `Port` | `HAL_PORT` (LinuxCNC 2.9+) |
+pub trait SignalType {
+    /// The HAL type of the signal
+    const HAL_TYPE: hal_type_t;
+}
+
+impl SignalType for f64 {
+    const HAL_TYPE: hal_type_t = hal_type_t_HAL_FLOAT;
+}
+
+impl SignalType for u32 {
+    const HAL_TYPE: hal_type_t = hal_type_t_HAL_U32;
+}
+
+impl SignalType for i32 {
+    const HAL_TYPE: hal_type_t = hal_type_t_HAL_S32;
+}
+
+impl SignalType for bool {
+    const HAL_TYPE: hal_type_t = hal_type_t_HAL_BIT;
+}
+
+#[cfg(linuxcnc_hal_64bit)]
+impl SignalType for i64 {
+    const HAL_TYPE: hal_type_t = hal_type_t_HAL_S64;
+}
+
+#[cfg(linuxcnc_hal_64bit)]
+impl SignalType for u64 {
+    const HAL_TYPE: hal_type_t = hal_type_t_HAL_U64;
+}
+
+/// Marker type for signals that connect an [`OutputPort`](crate::hal_pin::OutputPort) to an
+/// [`InputPort`](crate::hal_pin::InputPort)
+///
+/// The port's buffer must be allocated with `halcmd sets <signal> <size>` before it can be used.
+#[cfg(linuxcnc_hal_port)]
+#[derive(Debug, Copy, Clone)]
+pub struct Port;
+
+#[cfg(linuxcnc_hal_port)]
+impl SignalType for Port {
+    const HAL_TYPE: hal_type_t = hal_type_t_HAL_PORT;
+}
+
+/// A handle to a HAL signal
+///
+/// Like signals created with `halcmd net`, a signal outlives the process that created it.
+/// Dropping a `Signal` does not delete it; use [`Signal::delete`] to remove it from the HAL.
+#[derive(Debug)]
+pub struct Signal<T> {
+    name: String,
+    _type: PhantomData<T>,
+}
+
+impl<T> Signal<T>
+where
+    T: SignalType,
+{
+    /// Create a new signal with the given name
+    ///
+    /// Equivalent to `halcmd newsig <name> <type>`.
+    pub fn new(name: impl Into<String>) -> Result<Self, SignalError> {
+        let name = name.into();
+
+        let name_ffi = c_name(&name)?;
+
+        let ret = unsafe { hal_signal_new(name_ffi.as_ptr(), T::HAL_TYPE) };
+
+        check(&name, ret)?;
+
+        debug!("Created signal {}", name);
+
+        Ok(Self {
+            name,
+            _type: PhantomData,
+        })
+    }
+
+    /// Get the name of the signal
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Delete the signal, unlinking any pins connected to it
+    ///
+    /// Equivalent to `halcmd delsig <name>`.
+    pub fn delete(self) -> Result<(), SignalError> {
+        let name_ffi = c_name(&self.name)?;
+
+        let ret = unsafe { hal_signal_delete(name_ffi.as_ptr()) };
+
+        check(&self.name, ret)?;
+
+        debug!("Deleted signal {}", self.name);
+
+        Ok(())
+    }
+}
+
+/// Link the pin with the given full name to a signal
+///
+/// Equivalent to `halcmd linkps <pin_name> <signal>`. Linking a pin to the signal it is already
+/// linked to does nothing. The pin and signal types must match, a pin can only be linked to one
+/// signal and a signal can only have one output pin.
+pub fn link<T>(pin_name: &str, signal: &Signal<T>) -> Result<(), SignalError> {
+    let pin_name_ffi = c_name(pin_name)?;
+    let signal_name_ffi = c_name(&signal.name)?;
+
+    let ret = unsafe { hal_link(pin_name_ffi.as_ptr(), signal_name_ffi.as_ptr()) };
+
+    check(pin_name, ret)?;
+
+    debug!("Linked pin {} to signal {}", pin_name, signal.name);
+
+    Ok(())
+}
+
+/// Unlink the pin with the given full name from its signal
+///
+/// Equivalent to `halcmd unlinkp <pin_name>`. The pin keeps the last value of the signal. Unlinking
+/// a pin that isn't linked to a signal does nothing.
+pub fn unlink(pin_name: &str) -> Result<(), SignalError> {
+    let pin_name_ffi = c_name(pin_name)?;
+
+    let ret = unsafe { hal_unlink(pin_name_ffi.as_ptr()) };
+
+    check(pin_name, ret)?;
+
+    debug!("Unlinked pin {}", pin_name);
+
+    Ok(())
+}
+
+fn c_name(name: &str) -> Result<CString, SignalError> {
+    if name.len() > HAL_NAME_LEN as usize {
+        return Err(SignalError::NameLength {
+            name: name.to_string(),
+        });
+    }
+
+    CString::new(name).map_err(|e| {
+        error!("Failed to convert name to C string: {}", e);
+
+        SignalError::NameConversion {
+            name: name.to_string(),
+        }
+    })
+}
+
+fn check(name: &str, ret: i32) -> Result<(), SignalError> {
+    match ret {
+        0 => Ok(()),
+        code => Err(SignalError::from_errno(name, code)),
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::{
+        error::PinRegisterError,
+        hal_pin::{InputPin, InputPort, OutputPin, OutputPort},
+        mock::{self, Value},
+        prelude::*,
+        HalComponent, RegisterResources, Resources,
+    };
+
+    // Some pins are only used by name
+    #[allow(dead_code)]
+    struct Pins {
+        input: InputPin<f64>,
+        output: OutputPin<f64>,
+        other_output: OutputPin<f64>,
+        flag: InputPin<bool>,
+        rx: InputPort,
+        tx: OutputPort,
+    }
+
+    impl Resources for Pins {
+        type RegisterError = PinRegisterError;
+
+        fn register_resources(comp: &RegisterResources) -> Result<Self, Self::RegisterError> {
+            Ok(Pins {
+                input: comp.register_pin("input")?,
+                output: comp.register_pin("output")?,
+                other_output: comp.register_pin("other-output")?,
+                flag: comp.register_pin("flag")?,
+                rx: comp.register_pin("rx")?,
+                tx: comp.register_pin("tx")?,
+            })
+        }
+    }
+
+    #[test]
+    fn link_unlink() -> Result<(), Box<dyn std::error::Error>> {
+        let comp = HalComponent::<Pins>::new("signal-link")?;
+        let pins = comp.resources();
+
+        pins.output.set(1.5);
+
+        let signal = Signal::<f64>::new("signal-link-speed")?;
+
+        link("signal-link.output", &signal)?;
+        link("signal-link.input", &signal)?;
+        // Linking to the same signal again is fine
+        link("signal-link.input", &signal)?;
+
+        assert_eq!(
+            mock::pin_signal("signal-link.input"),
+            Some("signal-link-speed".to_string())
+        );
+        // The output's value is copied to the signal when linked
+        assert_eq!(pins.input.get(), 1.5);
+
+        pins.output.set(3.0);
+        assert_eq!(pins.input.get(), 3.0);
+        assert_eq!(
+            mock::signal_value("signal-link-speed"),
+            Some(Value::Float(3.0))
+        );
+
+        unlink("signal-link.input")?;
+        pins.output.set(4.0);
+
+        assert_eq!(mock::pin_signal("signal-link.input"), None);
+        assert_eq!(pins.input.get(), 3.0);
+
+        signal.delete()?;
+
+        assert!(!mock::signal_names().contains(&"signal-link-speed".to_string()));
+        assert_eq!(mock::pin_signal("signal-link.output"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_links() -> Result<(), Box<dyn std::error::Error>> {
+        let _comp = HalComponent::<Pins>::new("signal-invalid")?;
+
+        let signal = Signal::<f64>::new("signal-invalid-speed")?;
+        let other = Signal::<f64>::new("signal-invalid-other")?;
+
+        assert_eq!(
+            Signal::<f64>::new("signal-invalid-speed").map(|_| ()),
+            Err(SignalError::Invalid {
+                name: "signal-invalid-speed".to_string()
+            })
+        );
+        assert_eq!(
+            link("signal-invalid.flag", &signal),
+            Err(SignalError::Invalid {
+                name: "signal-invalid.flag".to_string()
+            })
+        );
+        assert_eq!(
+            link("signal-invalid.missing", &signal),
+            Err(SignalError::Invalid {
+                name: "signal-invalid.missing".to_string()
+            })
+        );
+
+        link("signal-invalid.output", &signal)?;
+
+        assert_eq!(
+            link("signal-invalid.other-output", &signal),
+            Err(SignalError::Invalid {
+                name: "signal-invalid.other-output".to_string()
+            })
+        );
+        assert_eq!(
+            link("signal-invalid.output", &other),
+            Err(SignalError::Invalid {
+                name: "signal-invalid.output".to_string()
+            })
+        );
+        assert_eq!(
+            Signal::<bool>::new("x".repeat(HAL_NAME_LEN as usize + 1)).map(|_| ()),
+            Err(SignalError::NameLength {
+                name: "x".repeat(HAL_NAME_LEN as usize + 1)
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn port_signal() -> Result<(), Box<dyn std::error::Error>> {
+        let mut comp = HalComponent::<Pins>::new("signal-port")?;
+        let mut pins = comp.resources_mut();
+
+        let signal = Signal::<Port>::new("signal-port-bytes")?;
+
+        link("signal-port.tx", &signal)?;
+        link("signal-port.rx", &signal)?;
+
+        // Both ends share the buffer attached to the signal
+        mock::attach_port("signal-port.rx", 16)?;
+
+        pins.tx.write(b"ping")?;
+
+        let mut buf = [0u8; 4];
+
+        pins.rx.read(&mut buf)?;
+        assert_eq!(&buf, b"ping");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,312 @@
+//! HAL threads
+//!
+//! Threads run exported [`HalFunction`](crate::HalFunction)s periodically, like `loadrt threads`
+//! and `addf` in a `.hal` file. A thread is created with [`HalThread::new`], functions are added to
+//! it by their full name with [`HalThread::add_function`] and all threads are started with
+//! [`start_threads`].
+//!
+//! Threads can only be created in a realtime context, i.e. from a component loaded with `loadrt`.
+//! See [`export_rt_component!`](crate::export_rt_component).
+//!
+//! # Examples
+//!
+//! Create a 1ms thread and run a realtime component's `update` function in it, equivalent to
+//! `loadrt threads name1=servo-thread period1=1000000` then `addf thread-setup.update servo-thread`
+//! and `start`.
+//!
+//! ```rust,no_run
+//! use linuxcnc_hal::{
+//!     export_rt_component,
+//!     hal_pin::OutputPin,
+//!     hal_thread::{start_threads, HalThread},
+//!     prelude::*,
+//!     HalFunction, Resources,
+//! };
+//! use std::time::Duration;
+//!
+//! #[derive(Resources)]
+//! struct Pins {
+//!     output: OutputPin<f64>,
+//! }
+//!
+//! fn update(pins: &Pins, period: Duration) {
+//!     pins.output.set(period.as_secs_f64());
+//! }
+//!
+//! export_rt_component! {
+//!     name: "thread-setup",
+//!     resources: Pins,
+//!     functions: [HalFunction::new("update", update)],
+//!     setup: |_comp| {
+//!         let thread = HalThread::new("servo-thread", Duration::from_millis(1), true)?;
+//!
+//!         thread.add_function("thread-setup.update", -1)?;
+//!
+//!         start_threads()?;
+//!
+//!         Ok(())
+//!     },
+//! }
+//! ```
+
+use crate::error::ThreadError;
+use linuxcnc_hal_sys::{
+    hal_add_funct_to_thread, hal_create_thread, hal_del_funct_from_thread, hal_start_threads,
+    hal_stop_threads, hal_thread_delete, HAL_NAME_LEN,
+};
+use std::{convert::TryFrom, ffi::CString, os::raw::c_long, time::Duration};
+
+/// A handle to a HAL thread
+///
+/// Like threads created with `loadrt threads`, a thread outlives the component that created it.
+/// Dropping a `HalThread` does not delete it; use [`HalThread::delete`] to remove it from the HAL.
+#[derive(Debug)]
+pub struct HalThread {
+    name: String,
+    period: Duration,
+    uses_fp: bool,
+}
+
+impl HalThread {
+    /// Create a new thread that runs every `period`
+    ///
+    /// If `uses_fp` is false, functions that use floating point can't be added to the thread.
+    pub fn new(
+        name: impl Into<String>,
+        period: Duration,
+        uses_fp: bool,
+    ) -> Result<Self, ThreadError> {
+        let name = name.into();
+
+        let name_ffi = c_name(&name)?;
+
+        let period_nsec = c_long::try_from(period.as_nanos())
+            .ok()
+            .filter(|nsec| *nsec > 0)
+            .ok_or_else(|| ThreadError::Period { name: name.clone() })?;
+
+        let ret = unsafe { hal_create_thread(name_ffi.as_ptr(), period_nsec as _, uses_fp as i32) };
+
+        check(&name, ret)?;
+
+        debug!("Created thread {} with period {:?}", name, period);
+
+        Ok(Self {
+            name,
+            period,
+            uses_fp,
+        })
+    }
+
+    /// Get the name of the thread
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the period of the thread
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Whether the thread supports functions that use floating point
+    pub fn uses_fp(&self) -> bool {
+        self.uses_fp
+    }
+
+    /// Add the function with the given full name to the thread
+    ///
+    /// Equivalent to `addf <funct_name> <thread> <position>`. Positive positions count from the
+    /// start of the thread's function list, so `1` runs the function first. Negative positions
+    /// count from the end, so `-1` runs the function last. A position of `0` is invalid.
+    pub fn add_function(&self, funct_name: &str, position: i32) -> Result<(), ThreadError> {
+        let funct_name_ffi = c_name(funct_name)?;
+        let name_ffi = c_name(&self.name)?;
+
+        let ret = unsafe {
+            hal_add_funct_to_thread(funct_name_ffi.as_ptr(), name_ffi.as_ptr(), position)
+        };
+
+        check(&self.name, ret)?;
+
+        debug!(
+            "Added function {} to thread {} at position {}",
+            funct_name, self.name, position
+        );
+
+        Ok(())
+    }
+
+    /// Remove the function with the given full name from the thread
+    ///
+    /// Equivalent to `delf <funct_name> <thread>`.
+    pub fn remove_function(&self, funct_name: &str) -> Result<(), ThreadError> {
+        let funct_name_ffi = c_name(funct_name)?;
+        let name_ffi = c_name(&self.name)?;
+
+        let ret = unsafe { hal_del_funct_from_thread(funct_name_ffi.as_ptr(), name_ffi.as_ptr()) };
+
+        check(&self.name, ret)?;
+
+        debug!("Removed function {} from thread {}", funct_name, self.name);
+
+        Ok(())
+    }
+
+    /// Delete the thread
+    pub fn delete(self) -> Result<(), ThreadError> {
+        let name_ffi = c_name(&self.name)?;
+
+        let ret = unsafe { hal_thread_delete(name_ffi.as_ptr()) };
+
+        check(&self.name, ret)?;
+
+        debug!("Deleted thread {}", self.name);
+
+        Ok(())
+    }
+}
+
+/// Start all HAL threads
+///
+/// Equivalent to `halcmd start`.
+pub fn start_threads() -> Result<(), ThreadError> {
+    check("all", unsafe { hal_start_threads() })
+}
+
+/// Stop all HAL threads
+///
+/// Equivalent to `halcmd stop`.
+pub fn stop_threads() -> Result<(), ThreadError> {
+    check("all", unsafe { hal_stop_threads() })
+}
+
+fn c_name(name: &str) -> Result<CString, ThreadError> {
+    if name.len() > HAL_NAME_LEN as usize {
+        return Err(ThreadError::NameLength {
+            name: name.to_string(),
+        });
+    }
+
+    CString::new(name).map_err(|e| {
+        error!("Failed to convert name to C string: {}", e);
+
+        ThreadError::NameConversion {
+            name: name.to_string(),
+        }
+    })
+}
+
+fn check(name: &str, ret: i32) -> Result<(), ThreadError> {
+    match ret {
+        0 => Ok(()),
+        code => Err(ThreadError::from_errno(name, code)),
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::{
+        error::PinRegisterError, hal_pin::BidirectionalPin, mock, prelude::*, HalComponent,
+        HalFunction, RegisterResources, Resources,
+    };
+
+    struct Counter {
+        count: BidirectionalPin<u32>,
+    }
+
+    impl Resources for Counter {
+        type RegisterError = PinRegisterError;
+
+        fn register_resources(comp: &RegisterResources) -> Result<Self, Self::RegisterError> {
+            Ok(Counter {
+                count: comp.register_pin("count")?,
+            })
+        }
+
+        fn functions() -> Vec<HalFunction<Self>> {
+            vec![
+                HalFunction::new("increment", |pins: &Self, _period| {
+                    let count = pins.count.get();
+
+                    pins.count.set(count + 1);
+                })
+                .uses_fp(false),
+                HalFunction::new("double", |pins: &Self, _period| {
+                    let count = pins.count.get();
+
+                    pins.count.set(count * 2);
+                })
+                .uses_fp(false),
+                HalFunction::new("float", |_pins: &Self, _period| {}),
+            ]
+        }
+    }
+
+    #[test]
+    fn schedule_functions() -> Result<(), Box<dyn std::error::Error>> {
+        let comp = HalComponent::<Counter>::new("thread-sched")?;
+
+        let thread = HalThread::new("thread-sched-thread", Duration::from_micros(100), false)?;
+
+        thread.add_function("thread-sched.increment", -1)?;
+        thread.add_function("thread-sched.double", 1)?;
+
+        assert_eq!(
+            mock::thread_functs("thread-sched-thread"),
+            Some(vec![
+                "thread-sched.double".to_string(),
+                "thread-sched.increment".to_string()
+            ])
+        );
+
+        comp.resources().count.set(3);
+        mock::run_thread("thread-sched-thread")?;
+        assert_eq!(comp.resources().count.get(), 7);
+
+        thread.remove_function("thread-sched.double")?;
+        mock::run_thread("thread-sched-thread")?;
+        assert_eq!(comp.resources().count.get(), 8);
+
+        start_threads()?;
+        assert!(mock::threads_running());
+        stop_threads()?;
+
+        thread.delete()?;
+        assert!(!mock::thread_names().contains(&"thread-sched-thread".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_schedule() -> Result<(), Box<dyn std::error::Error>> {
+        let _comp = HalComponent::<Counter>::new("thread-invalid")?;
+
+        assert_eq!(
+            HalThread::new("thread-invalid-zero", Duration::from_secs(0), true).map(|_| ()),
+            Err(ThreadError::Period {
+                name: "thread-invalid-zero".to_string()
+            })
+        );
+
+        let thread = HalThread::new("thread-invalid-thread", Duration::from_millis(1), false)?;
+
+        assert_eq!(
+            HalThread::new("thread-invalid-thread", Duration::from_millis(1), false).map(|_| ()),
+            Err(ThreadError::Invalid {
+                name: "thread-invalid-thread".to_string()
+            })
+        );
+        let invalid = Err(ThreadError::Invalid {
+            name: "thread-invalid-thread".to_string(),
+        });
+
+        // Floating point functions can't be added to a thread without FP support
+        assert_eq!(thread.add_function("thread-invalid.float", -1), invalid);
+        assert_eq!(thread.add_function("thread-invalid.increment", 0), invalid);
+        assert_eq!(thread.add_function("thread-invalid.missing", 1), invalid);
+        assert_eq!(thread.remove_function("thread-invalid.increment"), invalid);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+//! Indexed names for pin and parameter arrays
+
+use linuxcnc_hal_sys::HAL_NAME_LEN;
+
+/// Error generating indexed names
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum IndexedNameError {
+    /// The pattern does not contain exactly one run of `#` characters
+    Placeholder,
+
+    /// One or more full names are longer than [`HAL_NAME_LEN`]. Holds the first name that is too
+    /// long.
+    Length(String),
+}
+
+/// Generate `len` full names from a pattern like `in-#`, prefixed with `prefix`
+///
+/// The run of `#` characters in the pattern is replaced with the index. The number of `#`
+/// characters sets the minimum width of the index, which is padded with leading zeros, so `in-##`
+/// gives `in-00`, `in-01`, etc. This matches the names generated by `halcompile`.
+///
+/// Every name is checked against [`HAL_NAME_LEN`] before any are returned so that an array is
+/// never partially registered.
+pub(crate) fn indexed_names(
+    prefix: &str,
+    pattern: &str,
+    len: usize,
+) -> Result<Vec<String>, IndexedNameError> {
+    let start = pattern.find('#').ok_or(IndexedNameError::Placeholder)?;
+    let width = pattern[start..].chars().take_while(|c| *c == '#').count();
+
+    let (before, after) = (&pattern[..start], &pattern[start + width..]);
+
+    if after.contains('#') {
+        return Err(IndexedNameError::Placeholder);
+    }
+
+    let names = (0..len)
+        .map(|index| {
+            format!(
+                "{}.{}{:0width$}{}",
+                prefix,
+                before,
+                index,
+                after,
+                width = width
+            )
+        })
+        .collect::<Vec<_>>();
+
+    if let Some(name) = names.iter().find(|name| name.len() > HAL_NAME_LEN as usize) {
+        error!(
+            "Names generated from {} must be no longer than {} bytes",
+            pattern, HAL_NAME_LEN
+        );
+
+        return Err(IndexedNameError::Length(name.clone()));
+    }
+
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_placeholder() {
+        assert_eq!(
+            indexed_names("comp", "in-#", 3),
+            Ok(vec![
+                "comp.in-0".to_string(),
+                "comp.in-1".to_string(),
+                "comp.in-2".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn padded_placeholder() {
+        let names = indexed_names("comp", "axis.##.pos", 12).unwrap();
+
+        assert_eq!(names[0], "comp.axis.00.pos");
+        assert_eq!(names[11], "comp.axis.11.pos");
+    }
+
+    #[test]
+    fn invalid_placeholder() {
+        assert_eq!(
+            indexed_names("comp", "in", 2),
+            Err(IndexedNameError::Placeholder)
+        );
+        assert_eq!(
+            indexed_names("comp", "in-#.#", 2),
+            Err(IndexedNameError::Placeholder)
+        );
+    }
+
+    #[test]
+    fn name_too_long() {
+        // Indexes up to 9 fit, but index 10 adds another digit
+        let pattern = "x".repeat(HAL_NAME_LEN as usize - "comp.#".len()) + "#";
+
+        assert!(indexed_names("comp", &pattern, 10).is_ok());
+        assert_eq!(
+            indexed_names("comp", &pattern, 11),
+            Err(IndexedNameError::Length(format!(
+                "comp.{}10",
+                &pattern[..pattern.len() - 1]
+            )))
+        );
+    }
+}
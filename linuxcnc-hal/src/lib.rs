@@ -97,10 +97,10 @@
 //!         let time = start.elapsed().as_secs() as i32;
 //!
 //!         // Set output pin to elapsed seconds since component started
-//!         pins.output_1.set_value(time.into())?;
+//!         pins.output_1.set(time.into());
 //!
 //!         // Print the current value of the input pin
-//!         println!("Input: {:?}", pins.input_1.value());
+//!         println!("Input: {:?}", pins.input_1.get());
 //!
 //!         // Sleep for 1000ms. This should be a lower time if the component needs to update more
 //!         // frequently.
@@ -113,6 +113,186 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! ## Deriving `Resources`
+//!
+//! The `Resources` trait can be derived for a struct of pins and parameters instead of implementing
+//! it by hand. Each field is registered using its name converted to `kebab-case`. Use
+//! `#[hal(name = "...")]` to choose a different name, and `#[hal(readonly)]` to register a read
+//! only parameter.
+//!
+//! ```rust,no_run
+//! use linuxcnc_hal::{
+//!     hal_pin::{InputPin, OutputPin},
+//!     HalComponent, Parameter, Resources,
+//! };
+//!
+//! #[derive(Resources)]
+//! struct Pins {
+//!     /// Registered as `rust-comp.input-1`
+//!     input_1: InputPin<f64>,
+//!
+//!     /// Registered as `rust-comp.out`
+//!     #[hal(name = "out")]
+//!     output: OutputPin<f64>,
+//!
+//!     /// Registered as the read only parameter `rust-comp.max-speed`
+//!     #[hal(readonly)]
+//!     max_speed: Parameter<f64>,
+//! }
+//!
+//! let comp: HalComponent<Pins> = HalComponent::new("rust-comp").unwrap();
+//! ```
+//!
+//! ## Running at a fixed rate
+//!
+//! Instead of sleeping at the end of each iteration of a `while !comp.should_exit()` loop, which
+//! drifts by however long the loop body takes, [`HalComponent::run_at`] calls a closure every
+//! period against absolute deadlines until the component is signalled to exit. Adding a `LoopStats`
+//! field exports the loop's timing as `loop-time`, `loop-tmax` and `overruns` parameters, similar to
+//! the `.time` and `.tmax` parameters of realtime functions. See the `run_loop` module for details.
+//!
+//! ```rust,no_run
+//! use linuxcnc_hal::{
+//!     hal_pin::{InputPin, OutputPin},
+//!     prelude::*,
+//!     run_loop::LoopStats,
+//!     HalComponent, Resources,
+//! };
+//! use std::{error::Error, time::Duration};
+//!
+//! #[derive(Resources)]
+//! struct Pins {
+//!     input_1: InputPin<f64>,
+//!     output_1: OutputPin<f64>,
+//!     stats: LoopStats,
+//! }
+//!
+//! fn main() -> Result<(), Box<dyn Error>> {
+//!     let comp: HalComponent<Pins> = HalComponent::new("rust-comp")?;
+//!
+//!     comp.run_at(Duration::from_millis(10), |pins, _ctx| {
+//!         pins.output_1.set(pins.input_1.get() * 2.0);
+//!
+//!         Ok::<_, Box<dyn Error>>(())
+//!     })?;
+//!
+//!     Ok(())
+//! }
+//! ```
+//!
+//! ## Sharing resources between threads
+//!
+//! Pins and parameters are `Send` and `Sync`. Their values are read and written with atomic
+//! operations, so a component can be shared between threads with an `Arc`, for example to talk to a
+//! VFD over a serial port on a worker thread while the main thread does other work.
+//!
+//! ```rust,no_run
+//! use linuxcnc_hal::{
+//!     hal_pin::{InputPin, OutputPin},
+//!     prelude::*,
+//!     HalComponent, Resources,
+//! };
+//! use std::{sync::Arc, thread, time::Duration};
+//!
+//! #[derive(Resources)]
+//! struct Pins {
+//!     speed_command: InputPin<f64>,
+//!     speed_feedback: OutputPin<f64>,
+//! }
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let comp = Arc::new(HalComponent::<Pins>::new("vfd")?);
+//!
+//!     let worker = {
+//!         let comp = Arc::clone(&comp);
+//!
+//!         thread::spawn(move || {
+//!             let pins = comp.resources();
+//!
+//!             while !comp.should_exit() {
+//!                 // Send the command to the VFD and read back its speed here
+//!                 pins.speed_feedback.set(pins.speed_command.get());
+//!
+//!                 thread::sleep(Duration::from_millis(10));
+//!             }
+//!         })
+//!     };
+//!
+//!     worker.join().unwrap();
+//!
+//!     Ok(())
+//! }
+//! ```
+//!
+//! ## Component state
+//!
+//! Components often need state that isn't a pin or parameter, like a filter's previous output or a
+//! counter. Instead of keeping it in separate variables or `Cell`s, a component created with
+//! [`HalComponent::with_state`] owns a value of any type alongside its resources.
+//! [`HalComponent::split`] borrows the resources and mutably borrows the state at the same time.
+//! Realtime functions created with [`HalFunction::with_state`] are passed the state too.
+//!
+//! ```rust,no_run
+//! use linuxcnc_hal::{
+//!     hal_pin::{InputPin, OutputPin},
+//!     prelude::*,
+//!     HalComponent, Resources,
+//! };
+//! use std::{error::Error, thread, time::Duration};
+//!
+//! #[derive(Resources)]
+//! struct Pins {
+//!     input: InputPin<f64>,
+//!     max: OutputPin<f64>,
+//! }
+//!
+//! fn main() -> Result<(), Box<dyn Error>> {
+//!     let mut comp = HalComponent::<Pins, f64>::with_state("peak", f64::MIN)?;
+//!
+//!     while !comp.should_exit() {
+//!         let (pins, mut peak) = comp.split();
+//!
+//!         *peak = peak.max(pins.input.get());
+//!         pins.max.set(*peak);
+//!
+//!         thread::sleep(Duration::from_millis(10));
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+//!
+//! ## Async components
+//!
+//! Components that talk to network or serial devices with [tokio](https://tokio.rs) can enable the
+//! `tokio` feature. This adds an async `HalComponent::shutdown` future, a `HalComponent::updates`
+//! stream which yields the component's resources on an interval, and `InputPin::watch`, a stream
+//! which yields an input pin's value whenever it changes. See the `asynchronous` module for an
+//! example.
+//!
+//! ```toml
+//! [dependencies]
+//! linuxcnc-hal = { version = "0.2.0", features = [ "tokio" ] }
+//! ```
+//!
+//! # Testing
+//!
+//! Components can be tested without a LinuxCNC installation by enabling the `mock` feature. This
+//! replaces the HAL with an in-process implementation that tracks components, pins and parameters
+//! and their values. The rest of this crate's API works unchanged.
+//!
+//! ```toml
+//! [dev-dependencies]
+//! linuxcnc-hal = { version = "0.2.0", features = [ "mock" ] }
+//! ```
+//!
+//! Pin and parameter values can be set and inspected by name using the functions in the `mock`
+//! module, which is only available when the feature is enabled. Component and pin names are
+//! global to the test process, so each test should create a component with a unique name.
+//!
+//! The `HalTestBench` builds on the mock HAL to drive a component's input pins, run its logic for
+//! a number of cycles and check the resulting output pin and parameter values.
 
 #![deny(missing_docs)]
 #![deny(broken_intra_doc_links)]
@@ -120,22 +300,53 @@
 #[macro_use]
 extern crate log;
 
+// Allows `#[derive(Resources)]` to be used inside this crate
+extern crate self as linuxcnc_hal;
+
+#[cfg(feature = "tokio")]
+pub mod asynchronous;
+mod builder;
 mod check_readme;
 mod component;
 pub mod error;
+pub mod event;
+mod hal_function;
 mod hal_parameter;
 pub mod hal_pin;
+pub mod hal_signal;
+pub mod hal_thread;
+mod indexed_name;
+pub mod module_param;
 pub mod prelude;
+mod rt_component;
+pub mod run_loop;
+mod shared_value;
+#[cfg(feature = "mock")]
+mod test_bench;
+
+#[cfg(feature = "mock")]
+pub use linuxcnc_hal_sys::mock;
 
 use hal_parameter::ParameterPermissions;
 
-pub use crate::component::HalComponent;
+pub use crate::builder::{HalComponentBuilder, UnreadyHalComponent};
+pub use crate::component::{HalComponent, Instance, InstanceRef, InstanceRefMut};
+pub use crate::hal_function::HalFunction;
 pub use crate::hal_parameter::Parameter;
+#[doc(hidden)]
+pub use crate::rt_component::{rt_instances, RtComponent, RtSetup};
+pub use crate::shared_value::SharedValue;
+#[cfg(feature = "mock")]
+pub use crate::test_bench::HalTestBench;
 use crate::{
+    component::ComponentHandle,
     error::{ParameterRegisterError, PinRegisterError, ResourcesError},
     hal_parameter::HalParameter,
     hal_pin::HalPin,
+    indexed_name::{indexed_names, IndexedNameError},
 };
+pub use linuxcnc_hal_derive::{ProcessImage, Resources};
+use std::{convert::TryInto, sync::Arc};
 
 /// Resources for a component
 pub trait Resources: Sized {
@@ -146,65 +357,503 @@ pub trait Resources: Sized {
 
     /// Register resources against a component
     fn register_resources(comp: &RegisterResources) -> Result<Self, Self::RegisterError>;
+
+    /// Functions to export to the HAL for use in realtime threads
+    ///
+    /// Functions are exported after [`Resources::register_resources`] is called and before the
+    /// component is marked as ready. No functions are exported by default.
+    fn functions() -> Vec<HalFunction<Self>> {
+        Vec::new()
+    }
+
+    /// Parameters to record the timing of [`HalComponent::run_at`] in
+    ///
+    /// Return the [`LoopStats`](run_loop::LoopStats) registered with
+    /// [`RegisterResources::register_loop_stats`] to export them. No statistics are recorded by
+    /// default.
+    fn loop_stats(&self) -> Option<&run_loop::LoopStats> {
+        None
+    }
+}
+
+/// Plain value snapshots of a component's inputs and outputs
+///
+/// PLC style logic runs as a scan: read every input, compute every output from those values, then
+/// write every output. Reading pins one at a time with
+/// [`PinRead::get`](crate::hal_pin::PinRead::get) means another component may change an input part
+/// way through a scan. Instead, take a snapshot of all inputs at the start of a cycle with
+/// [`ProcessImage::snapshot_inputs`] and write all outputs at the end with
+/// [`ProcessImage::commit_outputs`], so the logic in between is a pure function of plain values
+/// which is easy to test without a HAL.
+///
+/// This trait is normally derived with `#[derive(ProcessImage)]`, which generates the `Inputs`
+/// and `Outputs` structs. For a struct called `Pins`, these are `PinsInputs` and `PinsOutputs`.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use linuxcnc_hal::{
+///     hal_pin::{InputPin, OutputPin},
+///     HalComponent, Parameter, ProcessImage, Resources,
+/// };
+///
+/// #[derive(Resources, ProcessImage)]
+/// struct Pins {
+///     enable: InputPin<bool>,
+///     position: InputPin<f64>,
+///     scale: Parameter<f64>,
+///     output: OutputPin<f64>,
+/// }
+///
+/// fn scan(inputs: PinsInputs) -> PinsOutputs {
+///     PinsOutputs {
+///         output: if inputs.enable {
+///             inputs.position * inputs.scale
+///         } else {
+///             0.0
+///         },
+///     }
+/// }
+///
+/// let comp: HalComponent<Pins> = HalComponent::new("scaler").unwrap();
+///
+/// comp.commit_outputs(scan(comp.snapshot_inputs()));
+/// ```
+pub trait ProcessImage {
+    /// Values of every input pin, bidirectional pin and read/write parameter
+    type Inputs;
+
+    /// Values of every output pin, bidirectional pin and read only parameter
+    type Outputs;
+
+    /// Read the value of every input into a new snapshot
+    fn snapshot_inputs(&self) -> Self::Inputs;
+
+    /// Write the value of every output
+    fn commit_outputs(&self, outputs: Self::Outputs);
 }
 
 /// Component metadata used when registering resources
+///
+/// Every resource registered through this holds a handle to the component, so the component is
+/// only removed from the HAL once it and all of its resources have been dropped.
 pub struct RegisterResources {
-    /// Component name
-    name: &'static str,
+    /// Component registration shared with each registered resource
+    component: Arc<ComponentHandle>,
 
-    /// Component ID
-    id: i32,
+    /// Prefix added to the name of every resource, which is the component name by default
+    prefix: String,
 }
 
 impl RegisterResources {
+    /// Get the component resources are registered against
+    pub(crate) fn component(&self) -> &Arc<ComponentHandle> {
+        &self.component
+    }
+
+    /// Get the prefix added to the names of resources registered through this
+    ///
+    /// This is the component name unless a different prefix was set with
+    /// [`HalComponentBuilder::prefix`].
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
     /// Register a pin with this component.
     ///
-    /// The pin name will be prefixed with the component name
-    pub fn register_pin<P>(&self, pin_name: &'static str) -> Result<P, PinRegisterError>
+    /// The pin name will be prefixed with the component's [prefix](RegisterResources::prefix)
+    pub fn register_pin<P>(&self, pin_name: &str) -> Result<P, PinRegisterError>
     where
         P: HalPin,
     {
-        let full_name = format!("{}.{}", self.name, pin_name);
+        let full_name = format!("{}.{}", self.prefix, pin_name);
 
-        let pin = P::register(&full_name, self.id)?;
+        let pin = P::register(&full_name, self)?;
 
         Ok(pin)
     }
 
     /// Register a read/write parameter with this component.
     ///
-    /// The parameter name will be prefixed with the component name.
+    /// The parameter name will be prefixed with the component's
+    /// [prefix](RegisterResources::prefix).
     ///
     /// To register a pin that LinuxCNC cannot write to, call [`RegisterResources::register_readonly_parameter`].
-    pub fn register_parameter<P>(
-        &self,
-        parameter_name: &'static str,
-    ) -> Result<P, ParameterRegisterError>
+    pub fn register_parameter<P>(&self, parameter_name: &str) -> Result<P, ParameterRegisterError>
     where
         P: HalParameter,
     {
-        let full_name = format!("{}.{}", self.name, parameter_name);
+        let full_name = format!("{}.{}", self.prefix, parameter_name);
 
-        let parameter = P::register(&full_name, self.id, ParameterPermissions::ReadWrite)?;
+        let parameter = P::register(&full_name, self, ParameterPermissions::ReadWrite)?;
 
         Ok(parameter)
     }
 
     /// Register a read only parameter with this component.
     ///
-    /// The parameter name will be prefixed with the component name
+    /// The parameter name will be prefixed with the component's [prefix](RegisterResources::prefix)
     pub fn register_readonly_parameter<P>(
         &self,
-        parameter_name: &'static str,
+        parameter_name: &str,
     ) -> Result<P, ParameterRegisterError>
     where
         P: HalParameter,
     {
-        let full_name = format!("{}.{}", self.name, parameter_name,);
+        let full_name = format!("{}.{}", self.prefix, parameter_name);
 
-        let parameter = P::register(&full_name, self.id, ParameterPermissions::ReadOnly)?;
+        let parameter = P::register(&full_name, self, ParameterPermissions::ReadOnly)?;
 
         Ok(parameter)
     }
+
+    /// Register the `loop-time`, `loop-tmax` and `overruns` parameters with this component.
+    ///
+    /// Return the result from [`Resources::loop_stats`] so [`HalComponent::run_at`] records its
+    /// timing in them. See the [`run_loop`] module for details.
+    pub fn register_loop_stats(&self) -> Result<run_loop::LoopStats, ParameterRegisterError> {
+        run_loop::LoopStats::register(self)
+    }
+
+    /// Register a fixed size array of pins with this component.
+    ///
+    /// Pin names are generated from `pattern` by replacing the `#` placeholder with the index of
+    /// each pin, starting from zero. Each `#` sets the minimum number of digits, padded with
+    /// leading zeros, so `in-#` gives `in-0`, `in-1`, ... and `in-##` gives `in-00`, `in-01`, ...
+    /// in the same way as `halcompile`. The names will be prefixed with the component's
+    /// [prefix](RegisterResources::prefix).
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use linuxcnc_hal::{
+    ///     error::PinRegisterError, hal_pin::InputPin, RegisterResources, Resources,
+    /// };
+    ///
+    /// struct Pins {
+    ///     /// Registered as `in-0` to `in-7`
+    ///     inputs: [InputPin<f64>; 8],
+    /// }
+    ///
+    /// impl Resources for Pins {
+    ///     type RegisterError = PinRegisterError;
+    ///
+    ///     fn register_resources(comp: &RegisterResources) -> Result<Self, Self::RegisterError> {
+    ///         Ok(Pins {
+    ///             inputs: comp.register_pin_array("in-#")?,
+    ///         })
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`PinRegisterError::NameFormat`] - If `pattern` does not contain exactly one run of `#`
+    ///   characters
+    /// * [`PinRegisterError::NameLength`] - If any full pin name is longer than
+    ///   [`HAL_NAME_LEN`](linuxcnc_hal_sys::HAL_NAME_LEN). No pins are registered in this case.
+    ///
+    /// Any other error returned when registering an individual pin is also returned.
+    pub fn register_pin_array<P, const N: usize>(
+        &self,
+        pattern: &str,
+    ) -> Result<[P; N], PinRegisterError>
+    where
+        P: HalPin,
+    {
+        let pins = self.register_pin_vec(pattern, N)?;
+
+        // NOTE: Conversion cannot fail as exactly `N` pins were registered
+        Ok(pins.try_into().unwrap_or_else(|_| unreachable!()))
+    }
+
+    /// Register `len` pins with this component, with names generated from `pattern`.
+    ///
+    /// This is the same as [`RegisterResources::register_pin_array`] but the number of pins can be
+    /// chosen at runtime.
+    pub fn register_pin_vec<P>(&self, pattern: &str, len: usize) -> Result<Vec<P>, PinRegisterError>
+    where
+        P: HalPin,
+    {
+        indexed_names(&self.prefix, pattern, len)
+            .map_err(|e| match e {
+                IndexedNameError::Placeholder => PinRegisterError::NameFormat {
+                    pattern: pattern.to_string(),
+                },
+                IndexedNameError::Length(name) => PinRegisterError::NameLength { name },
+            })?
+            .iter()
+            .map(|full_name| P::register(full_name, self))
+            .collect()
+    }
+
+    /// Register a fixed size array of read/write parameters with this component.
+    ///
+    /// Names are generated from `pattern` in the same way as
+    /// [`RegisterResources::register_pin_array`].
+    pub fn register_parameter_array<P, const N: usize>(
+        &self,
+        pattern: &str,
+    ) -> Result<[P; N], ParameterRegisterError>
+    where
+        P: HalParameter,
+    {
+        let parameters = self.register_parameters(pattern, N, ParameterPermissions::ReadWrite)?;
+
+        // NOTE: Conversion cannot fail as exactly `N` parameters were registered
+        Ok(parameters.try_into().unwrap_or_else(|_| unreachable!()))
+    }
+
+    /// Register `len` read/write parameters with this component, with names generated from
+    /// `pattern`.
+    pub fn register_parameter_vec<P>(
+        &self,
+        pattern: &str,
+        len: usize,
+    ) -> Result<Vec<P>, ParameterRegisterError>
+    where
+        P: HalParameter,
+    {
+        self.register_parameters(pattern, len, ParameterPermissions::ReadWrite)
+    }
+
+    /// Register a fixed size array of read only parameters with this component.
+    ///
+    /// Names are generated from `pattern` in the same way as
+    /// [`RegisterResources::register_pin_array`].
+    pub fn register_readonly_parameter_array<P, const N: usize>(
+        &self,
+        pattern: &str,
+    ) -> Result<[P; N], ParameterRegisterError>
+    where
+        P: HalParameter,
+    {
+        let parameters = self.register_parameters(pattern, N, ParameterPermissions::ReadOnly)?;
+
+        // NOTE: Conversion cannot fail as exactly `N` parameters were registered
+        Ok(parameters.try_into().unwrap_or_else(|_| unreachable!()))
+    }
+
+    /// Register `len` read only parameters with this component, with names generated from
+    /// `pattern`.
+    pub fn register_readonly_parameter_vec<P>(
+        &self,
+        pattern: &str,
+        len: usize,
+    ) -> Result<Vec<P>, ParameterRegisterError>
+    where
+        P: HalParameter,
+    {
+        self.register_parameters(pattern, len, ParameterPermissions::ReadOnly)
+    }
+
+    fn register_parameters<P>(
+        &self,
+        pattern: &str,
+        len: usize,
+        permissions: ParameterPermissions,
+    ) -> Result<Vec<P>, ParameterRegisterError>
+    where
+        P: HalParameter,
+    {
+        indexed_names(&self.prefix, pattern, len)
+            .map_err(|e| match e {
+                IndexedNameError::Placeholder => ParameterRegisterError::NameFormat {
+                    pattern: pattern.to_string(),
+                },
+                IndexedNameError::Length(name) => ParameterRegisterError::NameLength { name },
+            })?
+            .iter()
+            .map(|full_name| P::register(full_name, self, permissions))
+            .collect()
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use crate::{
+        error::{ComponentInitError, PinRegisterError, ResourcesError},
+        hal_parameter::HalParameter,
+        hal_pin::{HalPin, InputPin, OutputPin},
+        mock::{self, MockError, Value},
+        HalComponent, Parameter, ProcessImage, RegisterResources, Resources,
+    };
+
+    // Resources are checked by name through the mock
+    #[allow(dead_code)]
+    #[derive(Resources)]
+    struct Derived {
+        input_1: InputPin<f64>,
+        #[hal(name = "out")]
+        output: OutputPin<bool>,
+        #[hal(readonly)]
+        max_speed: Parameter<f64>,
+        gain: Parameter<u32>,
+        #[hal(name = "in.#")]
+        inputs: [InputPin<bool>; 2],
+    }
+
+    #[test]
+    fn derive_resources() {
+        let _comp = HalComponent::<Derived>::new("derive").unwrap();
+
+        let pins = mock::pin_names();
+        let params = mock::param_names();
+
+        assert!(pins.contains(&"derive.input-1".to_string()));
+        assert!(pins.contains(&"derive.out".to_string()));
+        assert!(params.contains(&"derive.max-speed".to_string()));
+        assert!(params.contains(&"derive.gain".to_string()));
+        assert!(pins.contains(&"derive.in.1".to_string()));
+
+        assert_eq!(
+            mock::set_param_value("derive.max-speed", 1.0),
+            Err(MockError::ReadOnly("derive.max-speed".to_string()))
+        );
+        assert_eq!(mock::set_param_value("derive.gain", 2u32), Ok(()));
+    }
+
+    #[allow(dead_code)]
+    struct Arrays {
+        inputs: [InputPin<f64>; 3],
+        outputs: Vec<OutputPin<bool>>,
+        gains: [Parameter<f64>; 2],
+        limits: Vec<Parameter<u32>>,
+    }
+
+    impl Resources for Arrays {
+        type RegisterError = ResourcesError;
+
+        fn register_resources(comp: &RegisterResources) -> Result<Self, Self::RegisterError> {
+            Ok(Self {
+                inputs: comp.register_pin_array("in-#")?,
+                outputs: comp.register_pin_vec("out.##", 11)?,
+                gains: comp.register_parameter_array("gain-#")?,
+                limits: comp.register_readonly_parameter_vec("limit-#", 2)?,
+            })
+        }
+    }
+
+    #[test]
+    fn arrays() {
+        let comp = HalComponent::<Arrays>::new("arrays").unwrap();
+        let resources = comp.resources();
+
+        assert_eq!(
+            resources
+                .inputs
+                .iter()
+                .map(|pin| pin.name())
+                .collect::<Vec<_>>(),
+            vec!["arrays.in-0", "arrays.in-1", "arrays.in-2"]
+        );
+        assert_eq!(resources.outputs.len(), 11);
+        assert_eq!(resources.outputs[0].name(), "arrays.out.00");
+        assert_eq!(resources.outputs[10].name(), "arrays.out.10");
+        assert_eq!(resources.gains[1].name(), "arrays.gain-1");
+        assert_eq!(
+            mock::set_param_value("arrays.limit-1", 1u32),
+            Err(MockError::ReadOnly("arrays.limit-1".to_string()))
+        );
+    }
+
+    struct BadPattern {
+        _inputs: Vec<InputPin<f64>>,
+    }
+
+    impl Resources for BadPattern {
+        type RegisterError = PinRegisterError;
+
+        fn register_resources(comp: &RegisterResources) -> Result<Self, Self::RegisterError> {
+            Ok(Self {
+                _inputs: comp.register_pin_vec("in", 2)?,
+            })
+        }
+    }
+
+    #[test]
+    fn array_bad_pattern() {
+        let comp = HalComponent::<BadPattern>::new("arrays-bad-pattern");
+
+        assert!(matches!(
+            comp,
+            Err(ComponentInitError::ResourceRegistration(
+                ResourcesError::Pin(PinRegisterError::NameFormat { .. })
+            ))
+        ));
+        assert!(!mock::pin_names()
+            .iter()
+            .any(|name| name.starts_with("arrays-bad-pattern.")));
+    }
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn resources_are_send_sync() {
+        assert_send_sync::<InputPin<f64>>();
+        assert_send_sync::<OutputPin<bool>>();
+        assert_send_sync::<crate::hal_pin::BidirectionalPin<u32>>();
+        assert_send_sync::<crate::hal_pin::InputPort>();
+        assert_send_sync::<crate::hal_pin::OutputPort>();
+        assert_send_sync::<Parameter<i32>>();
+        assert_send_sync::<Derived>();
+        assert_send_sync::<HalComponent<Derived>>();
+    }
+
+    #[derive(Resources, ProcessImage)]
+    struct Image {
+        enable: InputPin<bool>,
+        positions: [InputPin<f64>; 2],
+        count: crate::hal_pin::BidirectionalPin<u32>,
+        scale: Parameter<f64>,
+        outputs: [OutputPin<f64>; 2],
+        #[hal(readonly)]
+        status: Parameter<i32>,
+    }
+
+    fn scan(inputs: ImageInputs) -> ImageOutputs {
+        ImageOutputs {
+            count: inputs.count + inputs.enable as u32,
+            outputs: [
+                inputs.positions[0] * inputs.scale,
+                inputs.positions[1] * inputs.scale,
+            ],
+            status: 1,
+        }
+    }
+
+    #[test]
+    fn process_image() -> Result<(), Box<dyn std::error::Error>> {
+        let comp = HalComponent::<Image>::new("image")?;
+
+        mock::set_pin_value("image.enable", true)?;
+        mock::set_pin_value("image.positions-0", 1.5)?;
+        mock::set_pin_value("image.positions-1", -2.0)?;
+        mock::set_pin_value("image.count", 4u32)?;
+        mock::set_param_value("image.scale", 2.0)?;
+
+        let inputs = comp.snapshot_inputs();
+
+        assert_eq!(
+            inputs,
+            ImageInputs {
+                enable: true,
+                positions: [1.5, -2.0],
+                count: 4,
+                scale: 2.0,
+            }
+        );
+
+        // Changes after the snapshot are not seen by this scan
+        mock::set_pin_value("image.enable", false)?;
+
+        comp.commit_outputs(scan(inputs));
+
+        assert_eq!(mock::pin_value("image.count"), Some(Value::U32(5)));
+        assert_eq!(mock::pin_value("image.outputs-0"), Some(Value::Float(3.0)));
+        assert_eq!(mock::pin_value("image.outputs-1"), Some(Value::Float(-4.0)));
+        assert_eq!(mock::param_value("image.status"), Some(Value::S32(1)));
+
+        Ok(())
+    }
 }
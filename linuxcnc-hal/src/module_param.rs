@@ -0,0 +1,473 @@
+//! Module parameters for realtime components
+//!
+//! Realtime components written in C declare module parameters with the `RTAPI_MP_INT`,
+//! `RTAPI_MP_STRING` and `RTAPI_MP_ARRAY_*` macros, which LinuxCNC fills in from the arguments
+//! given to `loadrt`, e.g. `loadrt my-comp steps=200 port=/dev/ttyS0`.
+//! [`rtapi_module_params!`](crate::rtapi_module_params) declares the same parameters for a Rust
+//! component as statics of type [`ModuleParam`], and exports the symbols LinuxCNC looks up to set
+//! them.
+//!
+//! Parameters are set when the component is loaded, before `rtapi_app_main` is called, so they can
+//! be read with [`ModuleParam::get`] from anywhere in the component, e.g. in the `setup` function
+//! passed to [`export_rt_component!`](crate::export_rt_component) or while registering resources.
+//!
+//! Each parameter has one of these types:
+//!
+//! | Type          | C macro                 | Value returned by `get`                           |
+//! | ------------- | ----------------------- | ------------------------------------------------- |
+//! | `int`         | `RTAPI_MP_INT`          | `c_int`                                           |
+//! | `string`      | `RTAPI_MP_STRING`       | `Option<String>`, `None` if not set               |
+//! | `[int; N]`    | `RTAPI_MP_ARRAY_INT`    | `[c_int; N]`                                      |
+//! | `[string; N]` | `RTAPI_MP_ARRAY_STRING` | `Vec<String>` of the strings before the first gap |
+//!
+//! Array values are given to `loadrt` separated by commas, e.g. `pins=2,3,4`. Elements that aren't
+//! given keep their default value.
+//!
+//! # Examples
+//!
+//! A component with a configurable step count, device and axes:
+//!
+//! ```rust,no_run
+//! use linuxcnc_hal::{
+//!     export_rt_component, hal_pin::OutputPin, prelude::*, rtapi_module_params, Resources,
+//! };
+//!
+//! rtapi_module_params! {
+//!     /// Steps per revolution
+//!     static steps: int = 200;
+//!
+//!     /// Serial device to open
+//!     static port: string = "/dev/ttyS0";
+//!
+//!     /// Output pin numbers
+//!     static pins: [int; 4] = [2, 3, 4, 5];
+//!
+//!     /// Axis names
+//!     static axes: [string; 8];
+//! }
+//!
+//! #[derive(Resources)]
+//! struct Pins {
+//!     steps: OutputPin<i32>,
+//! }
+//!
+//! export_rt_component! {
+//!     name: "stepper",
+//!     resources: Pins,
+//!     setup: |comp| {
+//!         comp.resources().steps.set(steps.get());
+//!
+//!         println!("Opening {:?} for axes {:?}", port.get(), axes.get());
+//!
+//!         Ok(())
+//!     },
+//! }
+//! ```
+//!
+//! Loading the component with `loadrt stepper steps=400 axes=x,y` sets `steps` to 400 and `axes` to
+//! `["x", "y"]`, leaving `port` and `pins` at their defaults.
+
+use std::{
+    cell::UnsafeCell,
+    ffi::CStr,
+    fmt,
+    os::raw::{c_char, c_int},
+};
+
+/// A module parameter declared with [`rtapi_module_params!`](crate::rtapi_module_params)
+///
+/// `T` is the parameter's storage as seen by LinuxCNC: `c_int` for an `int`, a C string pointer
+/// for a `string`, or an array of either.
+#[repr(transparent)]
+pub struct ModuleParam<T> {
+    value: UnsafeCell<T>,
+}
+
+// SAFETY: LinuxCNC only writes parameters while loading the component, before `rtapi_app_main` is
+// called and before any Rust code in the component can read them. String pointers are only read
+// through `get`, which copies the string.
+unsafe impl Sync for ModuleParam<c_int> {}
+unsafe impl Sync for ModuleParam<*mut c_char> {}
+unsafe impl<const N: usize> Sync for ModuleParam<[c_int; N]> {}
+unsafe impl<const N: usize> Sync for ModuleParam<[*mut c_char; N]> {}
+
+impl<T> ModuleParam<T> {
+    /// Create a parameter with a default value
+    ///
+    /// This is an implementation detail of [`rtapi_module_params!`](crate::rtapi_module_params)
+    /// and should not be used directly.
+    ///
+    /// # Safety
+    ///
+    /// Every string pointer in `default` must be null or point to a nul terminated string that
+    /// lives for the rest of the program.
+    #[doc(hidden)]
+    pub const unsafe fn new(default: T) -> Self {
+        Self {
+            value: UnsafeCell::new(default),
+        }
+    }
+
+    /// Get a pointer to the parameter's storage
+    ///
+    /// This is the address LinuxCNC writes the parameter's value to. Any value written through it
+    /// must be valid for `T`, and string pointers must be null or point to a nul terminated string
+    /// that lives until the component is unloaded.
+    pub const fn as_ptr(&self) -> *mut T {
+        self.value.get()
+    }
+}
+
+impl ModuleParam<c_int> {
+    /// Get the parameter's value
+    pub fn get(&self) -> c_int {
+        unsafe { *self.value.get() }
+    }
+}
+
+impl ModuleParam<*mut c_char> {
+    /// Get the parameter's value, or `None` if it wasn't set and has no default
+    pub fn get(&self) -> Option<String> {
+        unsafe { string(*self.value.get()) }
+    }
+}
+
+impl<const N: usize> ModuleParam<[c_int; N]> {
+    /// Get the parameter's values
+    pub fn get(&self) -> [c_int; N] {
+        unsafe { *self.value.get() }
+    }
+}
+
+impl<const N: usize> ModuleParam<[*mut c_char; N]> {
+    /// Get the parameter's values
+    ///
+    /// Like the `names` parameter of a `halcompile` component, values after the first one that
+    /// wasn't set are ignored.
+    pub fn get(&self) -> Vec<String> {
+        unsafe { &*self.value.get() }
+            .iter()
+            .map_while(|value| unsafe { string(*value) })
+            .collect()
+    }
+}
+
+impl fmt::Debug for ModuleParam<c_int> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ModuleParam").field(&self.get()).finish()
+    }
+}
+
+impl fmt::Debug for ModuleParam<*mut c_char> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ModuleParam").field(&self.get()).finish()
+    }
+}
+
+impl<const N: usize> fmt::Debug for ModuleParam<[c_int; N]> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ModuleParam").field(&self.get()).finish()
+    }
+}
+
+impl<const N: usize> fmt::Debug for ModuleParam<[*mut c_char; N]> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ModuleParam").field(&self.get()).finish()
+    }
+}
+
+/// Copy a string parameter, which is null if it wasn't set
+unsafe fn string(value: *mut c_char) -> Option<String> {
+    if value.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(value).to_string_lossy().into_owned())
+    }
+}
+
+/// Get the length of the description [`description`] builds from `lines`, including the nul
+/// terminator
+#[doc(hidden)]
+pub const fn description_len(lines: &[&str]) -> usize {
+    let mut len = 0;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let (start, end) = trim(lines[i].as_bytes());
+
+        if start < end {
+            if len > 0 {
+                len += 1;
+            }
+
+            len += end - start;
+        }
+
+        i += 1;
+    }
+
+    len + 1
+}
+
+/// Build a nul terminated parameter description from the lines of its doc comment
+///
+/// Each line is trimmed, empty lines are skipped and the rest are joined with spaces. `LEN` must be
+/// the length returned by [`description_len`].
+#[doc(hidden)]
+pub const fn description<const LEN: usize>(lines: &[&str]) -> [u8; LEN] {
+    let mut description = [0; LEN];
+    let mut len = 0;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i].as_bytes();
+        let (mut start, end) = trim(line);
+
+        if start < end {
+            if len > 0 {
+                description[len] = b' ';
+                len += 1;
+            }
+
+            while start < end {
+                description[len] = line[start];
+                len += 1;
+                start += 1;
+            }
+        }
+
+        i += 1;
+    }
+
+    description
+}
+
+/// Get the range of `line` without leading or trailing whitespace
+const fn trim(line: &[u8]) -> (usize, usize) {
+    let mut start = 0;
+    let mut end = line.len();
+
+    while start < end && line[start].is_ascii_whitespace() {
+        start += 1;
+    }
+
+    while end > start && line[end - 1].is_ascii_whitespace() {
+        end -= 1;
+    }
+
+    (start, end)
+}
+
+/// Declare module parameters for a realtime component
+///
+/// Each parameter is declared as a `static` with a type of `int`, `string`, `[int; N]` or
+/// `[string; N]` and an optional default value. Integers default to zero, and strings to not
+/// being set. String arrays can't have a default. The static's name is the name of the parameter
+/// given to `loadrt`, and its doc comment is used as the parameter's description, with the lines
+/// joined by spaces.
+///
+/// Parameters can be declared in any module of the component's crate, but their names must be
+/// unique across the crate. See the [`module_param`](crate::module_param) module for more
+/// details and an example.
+///
+/// ```rust,no_run
+/// use linuxcnc_hal::rtapi_module_params;
+///
+/// rtapi_module_params! {
+///     /// Number of channels
+///     static channels: int = 1;
+///
+///     /// Serial device to open
+///     pub static port: string;
+/// }
+/// ```
+#[macro_export]
+macro_rules! rtapi_module_params {
+    () => {};
+    (@param [$($doc:literal)*] $vis:vis $name:ident, $ty:ty, $rtapi_type:expr, $default:expr) => {
+        $(#[doc = $doc])*
+        #[allow(non_upper_case_globals)]
+        $vis static $name: $crate::module_param::ModuleParam<$ty> = {
+            const DEFAULT: $ty = $default;
+
+            // SAFETY: String defaults are either null or a nul terminated string literal
+            unsafe { $crate::module_param::ModuleParam::new(DEFAULT) }
+        };
+
+        $crate::__rtapi_module_param_symbols!(
+            $name,
+            $rtapi_type,
+            [$($doc),*],
+            ::std::ptr::addr_of!($name)
+        );
+    };
+    (@default [] $default:expr) => { $default };
+    (@default [$value:expr] $default:expr) => { $value };
+    (
+        $(#[doc = $doc:literal])*
+        $vis:vis static $name:ident: int $(= $default:expr)?;
+        $($rest:tt)*
+    ) => {
+        $crate::rtapi_module_params!(
+            @param [$($doc)*] $vis $name,
+            ::std::os::raw::c_int,
+            "i",
+            $crate::rtapi_module_params!(@default [$($default)?] 0)
+        );
+
+        $crate::rtapi_module_params!($($rest)*);
+    };
+    (
+        $(#[doc = $doc:literal])*
+        $vis:vis static $name:ident: string $(= $default:literal)?;
+        $($rest:tt)*
+    ) => {
+        $crate::rtapi_module_params!(
+            @param [$($doc)*] $vis $name,
+            *mut ::std::os::raw::c_char,
+            "s",
+            $crate::rtapi_module_params!(
+                @default [$(concat!($default, "\0").as_ptr() as *mut _)?]
+                ::std::ptr::null_mut()
+            )
+        );
+
+        $crate::rtapi_module_params!($($rest)*);
+    };
+    (
+        $(#[doc = $doc:literal])*
+        $vis:vis static $name:ident: [int; $len:literal] $(= $default:expr)?;
+        $($rest:tt)*
+    ) => {
+        $crate::rtapi_module_params!(
+            @param [$($doc)*] $vis $name,
+            [::std::os::raw::c_int; $len],
+            concat!("1-", $len, "i"),
+            $crate::rtapi_module_params!(@default [$($default)?] [0; $len])
+        );
+
+        $crate::rtapi_module_params!($($rest)*);
+    };
+    (
+        $(#[doc = $doc:literal])*
+        $vis:vis static $name:ident: [string; $len:literal];
+        $($rest:tt)*
+    ) => {
+        $crate::rtapi_module_params!(
+            @param [$($doc)*] $vis $name,
+            [*mut ::std::os::raw::c_char; $len],
+            concat!("1-", $len, "s"),
+            [::std::ptr::null_mut(); $len]
+        );
+
+        $crate::rtapi_module_params!($($rest)*);
+    };
+}
+
+/// Export the symbols `rtapi_app` looks up to set a module parameter from `loadrt`
+///
+/// For a parameter called `name`, `rtapi_info_address_name` points to the variable to set,
+/// `rtapi_info_type_name` holds its type, e.g. `i` for an `int` or `1-16s` for an array of up to 16
+/// strings, and `rtapi_info_description_name` holds a description built from the lines in
+/// `$description`. These match the symbols created by the `RTAPI_MP_*` macros in `rtapi_app.h`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __rtapi_module_param_symbols {
+    ($name:ident, $ty:expr, [$($description:expr),*], $address:expr) => {
+        const _: () = {
+            const LINES: &[&str] = &[$($description),*];
+
+            static DESCRIPTION_TEXT: [u8; $crate::module_param::description_len(LINES)] =
+                $crate::module_param::description(LINES);
+
+            #[used]
+            #[export_name = concat!("rtapi_info_address_", stringify!($name))]
+            static mut ADDRESS: *mut ::std::os::raw::c_void = $address as *mut _;
+
+            #[used]
+            #[export_name = concat!("rtapi_info_type_", stringify!($name))]
+            static mut TYPE: *const ::std::os::raw::c_char =
+                concat!($ty, "\0").as_ptr() as *const _;
+
+            #[used]
+            #[export_name = concat!("rtapi_info_description_", stringify!($name))]
+            static mut DESCRIPTION: *const ::std::os::raw::c_char =
+                DESCRIPTION_TEXT.as_ptr() as *const _;
+        };
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        ffi::{c_void, CStr},
+        os::raw::c_char,
+    };
+
+    rtapi_module_params! {
+        /// An int
+        static test_int: int = 7;
+
+        static test_int_unset: int;
+
+        /// A string
+        static test_string: string = "default";
+
+        static test_string_unset: string;
+
+        /// An int
+        ///
+        ///   array
+        static test_ints: [int; 3] = [1, 2, 3];
+
+        /// A string array
+        static test_strings: [string; 4];
+    }
+
+    // Symbols LinuxCNC looks up to set the parameters
+    extern "C" {
+        static rtapi_info_address_test_strings: *mut c_void;
+        static rtapi_info_type_test_strings: *const c_char;
+        static rtapi_info_description_test_strings: *const c_char;
+        static rtapi_info_type_test_int: *const c_char;
+        static rtapi_info_type_test_string: *const c_char;
+        static rtapi_info_type_test_ints: *const c_char;
+        static rtapi_info_description_test_ints: *const c_char;
+        static rtapi_info_description_test_int_unset: *const c_char;
+    }
+
+    unsafe fn str(value: *const c_char) -> &'static str {
+        CStr::from_ptr(value).to_str().unwrap()
+    }
+
+    #[test]
+    fn defaults() {
+        assert_eq!(test_int.get(), 7);
+        assert_eq!(test_int_unset.get(), 0);
+        assert_eq!(test_string.get(), Some("default".to_string()));
+        assert_eq!(test_string_unset.get(), None);
+        assert_eq!(test_ints.get(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn symbols() {
+        unsafe {
+            assert_eq!(str(rtapi_info_type_test_int), "i");
+            assert_eq!(str(rtapi_info_type_test_string), "s");
+            assert_eq!(str(rtapi_info_type_test_ints), "1-3i");
+            assert_eq!(str(rtapi_info_type_test_strings), "1-4s");
+            assert_eq!(str(rtapi_info_description_test_strings), "A string array");
+            assert_eq!(str(rtapi_info_description_test_ints), "An int array");
+            assert_eq!(str(rtapi_info_description_test_int_unset), "");
+
+            // Set the array the way `loadrt test_strings=x,y` would
+            let values = rtapi_info_address_test_strings as *mut *mut c_char;
+
+            *values = b"x\0".as_ptr() as *mut _;
+            *values.add(1) = b"y\0".as_ptr() as *mut _;
+            *values.add(3) = b"ignored\0".as_ptr() as *mut _;
+        }
+
+        assert_eq!(test_strings.get(), vec!["x", "y"]);
+    }
+}
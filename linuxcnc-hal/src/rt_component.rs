@@ -0,0 +1,551 @@
+//! Realtime component entry points
+
+use crate::{error::ComponentInitError, HalComponent, HalComponentBuilder, Resources};
+use linuxcnc_hal_sys::EINVAL;
+use std::{
+    cell::UnsafeCell,
+    error::Error,
+    os::raw::c_int,
+    panic::{self, AssertUnwindSafe},
+};
+
+/// Setup function called once a realtime component is created
+#[doc(hidden)]
+pub type RtSetup<R, S = ()> = fn(&mut HalComponent<R, S>) -> Result<(), Box<dyn Error>>;
+
+/// Export a realtime component
+///
+/// Realtime components are loaded into LinuxCNC with `loadrt`, which calls the
+/// `rtapi_app_main` function exported by the component's library on load and `rtapi_app_exit` on
+/// unload. This macro generates both functions for a component with the given name and
+/// [`Resources`](crate::Resources) type. The crate type of any realtime component must be
+/// `crate-type = [ "cdylib" ]`.
+///
+/// On load, the component is built with a [`HalComponentBuilder`](crate::HalComponentBuilder),
+/// marked ready and kept in a static until the component is unloaded, at which point it is
+/// dropped and [`hal_exit`](linuxcnc_hal_sys::hal_exit) is called. The component has a single
+/// instance, with resources prefixed by its name, unless `default_count` is set. The optional
+/// `setup` function is called once the component is ready and can be used to set initial pin or
+/// parameter values or initialise the component's state.
+///
+/// The optional `state` type is created with `Default` and owned by the component. Functions that
+/// use it are created with [`HalFunction::with_state`](crate::HalFunction::with_state) and listed
+/// in `functions`, and are exported along with the functions from
+/// [`Resources::functions`](crate::Resources::functions).
+///
+/// Setting `default_count` lets the component be loaded more than once, like a component built
+/// with `halcompile`. The macro then declares the `count` and `names` module parameters, so
+/// `loadrt rust-comp count=2` registers two instances prefixed with `rust-comp.0` and
+/// `rust-comp.1`, and `loadrt rust-comp names=x,y` registers two instances prefixed with `x` and
+/// `y`. Up to 16 names can be given. If neither parameter is given, `default_count` instances are
+/// registered. The state type must implement `Clone` so each instance can have its own copy. See
+/// [`HalComponentBuilder::count`](crate::HalComponentBuilder::count).
+///
+/// Other module parameters can be declared with
+/// [`rtapi_module_params!`](crate::rtapi_module_params) and read in `setup`.
+///
+/// Realtime components don't register any Unix signal handlers, as they're loaded into LinuxCNC's
+/// `rtapi_app` process which handles signals itself.
+///
+/// Realtime components should do their work in functions exported with
+/// [`Resources::functions`](crate::Resources::functions) which are then added to a HAL thread.
+/// Neither the constructor nor `setup` should block.
+///
+/// If creating the component or calling `setup` fails, the error is logged and a negative errno
+/// code is returned to LinuxCNC. See [`ComponentInitError::errno`].
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use linuxcnc_hal::{
+///     error::PinRegisterError, export_rt_component, hal_pin::OutputPin, prelude::*,
+///     RegisterResources, Resources,
+/// };
+///
+/// struct Pins {
+///     output: OutputPin<f64>,
+/// }
+///
+/// impl Resources for Pins {
+///     type RegisterError = PinRegisterError;
+///
+///     fn register_resources(comp: &RegisterResources) -> Result<Self, Self::RegisterError> {
+///         Ok(Pins {
+///             output: comp.register_pin::<OutputPin<f64>>("output")?,
+///         })
+///     }
+/// }
+///
+/// export_rt_component! {
+///     name: "rust-comp",
+///     resources: Pins,
+///     setup: |comp| {
+///         comp.resources().output.set(1.0);
+///
+///         Ok(())
+///     },
+/// }
+/// ```
+///
+/// A component that counts how many times its function has been called:
+///
+/// ```rust,no_run
+/// use linuxcnc_hal::{export_rt_component, hal_pin::OutputPin, prelude::*, HalFunction, Resources};
+/// use std::time::Duration;
+///
+/// #[derive(Resources)]
+/// struct Pins {
+///     count: OutputPin<u32>,
+/// }
+///
+/// #[derive(Default)]
+/// struct Counter {
+///     calls: u32,
+/// }
+///
+/// fn update(pins: &Pins, counter: &mut Counter, _period: Duration) {
+///     counter.calls = counter.calls.wrapping_add(1);
+///
+///     pins.count.set(counter.calls);
+/// }
+///
+/// export_rt_component! {
+///     name: "counter",
+///     resources: Pins,
+///     state: Counter,
+///     functions: [HalFunction::with_state("update", update)],
+/// }
+/// ```
+///
+/// A component that registers one instance by default, or more with `count=` or `names=`:
+///
+/// ```rust,no_run
+/// use linuxcnc_hal::{export_rt_component, hal_pin::InputPin, Resources};
+///
+/// #[derive(Resources)]
+/// struct Pins {
+///     input: InputPin<f64>,
+/// }
+///
+/// export_rt_component! {
+///     name: "multi",
+///     resources: Pins,
+///     default_count: 1,
+/// }
+/// ```
+#[macro_export]
+macro_rules! export_rt_component {
+    (
+        name: $name:expr,
+        resources: $resources:ty
+        $(, default_count: $default_count:expr)?
+        $(, state: $state:ty)?
+        $(, functions: [$($function:expr),* $(,)?])?
+        $(, setup: $setup:expr)?
+        $(,)?
+    ) => {
+        #[doc(hidden)]
+        static __LINUXCNC_HAL_RT_COMPONENT: $crate::RtComponent<
+            $resources,
+            $crate::export_rt_component!(@state $($state)?),
+        > = $crate::RtComponent::new();
+
+        $($crate::export_rt_component!(@instance_params $default_count);)?
+
+        /// Realtime component entry point, called by LinuxCNC on `loadrt`
+        #[no_mangle]
+        pub extern "C" fn rtapi_app_main() -> i32 {
+            let name = $name;
+            let functions = vec![$($($function),*)?];
+            let setup: $crate::RtSetup<
+                $resources,
+                $crate::export_rt_component!(@state $($state)?),
+            > = $crate::export_rt_component!(@setup $($setup)?);
+
+            // Signals are handled by `rtapi_app`, which hosts every realtime module
+            let builder = functions.into_iter().fold(
+                $crate::HalComponentBuilder::new(name).signals(&[]),
+                |builder, function| builder.function(function),
+            );
+
+            let builder = Ok::<_, $crate::error::ComponentInitError>(builder);
+
+            $(
+                let default_count: usize = $default_count;
+
+                let builder = builder.and_then(|builder| {
+                    $crate::rt_instances(
+                        builder,
+                        default_count,
+                        __linuxcnc_hal_rt_instances::count.get(),
+                        __linuxcnc_hal_rt_instances::names.get(),
+                    )
+                });
+            )?
+
+            unsafe { __LINUXCNC_HAL_RT_COMPONENT.init(name, builder, setup) }
+        }
+
+        /// Realtime component exit point, called by LinuxCNC on `unloadrt`
+        #[no_mangle]
+        pub extern "C" fn rtapi_app_exit() {
+            unsafe { __LINUXCNC_HAL_RT_COMPONENT.exit() }
+        }
+    };
+    (@state) => { () };
+    (@state $state:ty) => { $state };
+    (@setup) => { |_comp| Ok(()) };
+    (@setup $setup:expr) => { $setup };
+    (@instance_params $default_count:expr) => {
+        // In a module so the statics don't clash with anything called `count` or `names`
+        #[doc(hidden)]
+        mod __linuxcnc_hal_rt_instances {
+            $crate::rtapi_module_params! {
+                /// number of instances
+                pub static count: int;
+
+                /// names of instances
+                pub static names: [string; 16];
+            }
+        }
+    };
+}
+
+/// Storage for a component created by [`export_rt_component!`]
+///
+/// This is an implementation detail of the macro and should not be used directly.
+#[doc(hidden)]
+pub struct RtComponent<R, S = ()> {
+    comp: UnsafeCell<Option<HalComponent<R, S>>>,
+}
+
+// SAFETY: LinuxCNC calls `rtapi_app_main` and `rtapi_app_exit` once each from the thread loading
+// the module, so the component itself is never accessed concurrently. Its exported functions are
+// called from HAL threads though, which use the resources and state, so they must be thread safe.
+unsafe impl<R, S> Sync for RtComponent<R, S>
+where
+    R: Send + Sync,
+    S: Send,
+{
+}
+
+impl<R, S> RtComponent<R, S> {
+    /// Create empty component storage
+    pub const fn new() -> Self {
+        Self {
+            comp: UnsafeCell::new(None),
+        }
+    }
+}
+
+impl<R, S> Default for RtComponent<R, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R, S> RtComponent<R, S>
+where
+    R: Resources,
+    S: Default,
+{
+    /// Build the component and call the setup function, returning 0 or a negative errno code
+    ///
+    /// `builder` is an error if the component's module parameters were invalid.
+    ///
+    /// # Safety
+    ///
+    /// Must not be called at the same time as [`RtComponent::exit`].
+    pub unsafe fn init(
+        &self,
+        name: impl Into<String>,
+        builder: Result<HalComponentBuilder<R, S>, ComponentInitError>,
+        setup: RtSetup<R, S>,
+    ) -> i32 {
+        let name = name.into();
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut comp = builder
+                .and_then(HalComponentBuilder::build)
+                .and_then(|comp| comp.ready())
+                .map_err(|e| {
+                    error!("Failed to create realtime component {}: {}", name, e);
+
+                    e.errno()
+                })?;
+
+            setup(&mut comp).map_err(|e| {
+                error!("Failed to set up realtime component {}: {}", name, e);
+
+                e.downcast_ref::<ComponentInitError>()
+                    .map(ComponentInitError::errno)
+                    .unwrap_or(-(EINVAL as i32))
+            })?;
+
+            Ok(comp)
+        }));
+
+        match result {
+            Ok(Ok(comp)) => {
+                *self.comp.get() = Some(comp);
+
+                0
+            }
+            Ok(Err(code)) => code,
+            Err(_) => {
+                error!("Realtime component {} panicked during init", name);
+
+                -(EINVAL as i32)
+            }
+        }
+    }
+
+    /// Drop the component, calling [`hal_exit`](linuxcnc_hal_sys::hal_exit)
+    ///
+    /// # Safety
+    ///
+    /// Must not be called at the same time as [`RtComponent::init`].
+    pub unsafe fn exit(&self) {
+        if let Some(comp) = (*self.comp.get()).take() {
+            debug!("Exiting realtime component {}", comp.name());
+        }
+    }
+}
+
+/// Configure a realtime component's instances from its `count` and `names` module parameters
+///
+/// Like `halcompile`, `count=` and `names=` can't both be given, and `default_count` instances are
+/// registered if neither is. This is an implementation detail of [`export_rt_component!`] and
+/// should not be used directly.
+#[doc(hidden)]
+pub fn rt_instances<R, S>(
+    builder: HalComponentBuilder<R, S>,
+    default_count: usize,
+    count: c_int,
+    names: Vec<String>,
+) -> Result<HalComponentBuilder<R, S>, ComponentInitError>
+where
+    R: Resources,
+    S: Clone,
+{
+    let error = |reason: &str| ComponentInitError::Instances {
+        name: builder.name().to_string(),
+        reason: reason.to_string(),
+    };
+
+    match (count, names.is_empty()) {
+        (count, _) if count < 0 => Err(error("count must not be negative")),
+        (0, true) => Ok(builder.count(default_count)),
+        (0, false) => Ok(builder.names(names)),
+        (count, true) => Ok(builder.count(count as usize)),
+        (_, false) => Err(error("count= and names= are mutually exclusive")),
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::{
+        error::PinRegisterError, hal_pin::OutputPin, mock, prelude::*, HalFunction,
+        RegisterResources,
+    };
+    use std::time::Duration;
+
+    struct Pins {
+        output: OutputPin<u32>,
+    }
+
+    impl crate::Resources for Pins {
+        type RegisterError = PinRegisterError;
+
+        fn register_resources(comp: &RegisterResources) -> Result<Self, Self::RegisterError> {
+            Ok(Pins {
+                output: comp.register_pin("output")?,
+            })
+        }
+    }
+
+    export_rt_component! {
+        name: "rt-export",
+        resources: Pins,
+        setup: |comp| {
+            comp.resources().output.set(5);
+
+            Ok(())
+        },
+    }
+
+    #[test]
+    fn load_unload() {
+        assert_eq!(rtapi_app_main(), 0);
+        assert_eq!(mock::component_is_ready("rt-export"), Some(true));
+        assert_eq!(
+            mock::pin_value("rt-export.output"),
+            Some(mock::Value::U32(5))
+        );
+
+        // Signals are left to `rtapi_app`
+        let comp = unsafe { (*__LINUXCNC_HAL_RT_COMPONENT.comp.get()).as_ref() }.unwrap();
+        assert_eq!(comp.signal_events().signals().count(), 0);
+
+        rtapi_app_exit();
+        assert_eq!(mock::component_id("rt-export"), None);
+    }
+
+    #[test]
+    fn multiple_instances() {
+        static COMP: crate::RtComponent<Pins> = crate::RtComponent::new();
+
+        // What the macro builds with `default_count: 1` and `loadrt rt-multi count=2`
+        let builder = rt_instances(
+            HalComponentBuilder::new("rt-multi").signals(&[]),
+            1,
+            2,
+            Vec::new(),
+        );
+
+        let ret = unsafe {
+            COMP.init("rt-multi", builder, |comp| {
+                for instance in comp.instances() {
+                    instance.resources().output.set(5);
+                }
+
+                Ok(())
+            })
+        };
+
+        assert_eq!(ret, 0);
+        assert_eq!(mock::pin_value("rt-multi.output"), None);
+        assert_eq!(
+            mock::pin_value("rt-multi.0.output"),
+            Some(mock::Value::U32(5))
+        );
+        assert_eq!(
+            mock::pin_value("rt-multi.1.output"),
+            Some(mock::Value::U32(5))
+        );
+
+        unsafe { COMP.exit() };
+        assert_eq!(mock::component_id("rt-multi"), None);
+    }
+
+    #[derive(Default)]
+    struct Counter {
+        calls: u32,
+    }
+
+    fn update(pins: &Pins, counter: &mut Counter, _period: Duration) {
+        counter.calls += 1;
+
+        pins.output.set(counter.calls);
+    }
+
+    #[test]
+    fn state_functions() {
+        static COMP: crate::RtComponent<Pins, Counter> = crate::RtComponent::new();
+
+        let builder = HalComponentBuilder::new("rt-state")
+            .function(HalFunction::with_state("update", update));
+
+        let ret = unsafe {
+            COMP.init("rt-state", Ok(builder), |comp| {
+                comp.state_mut().calls = 10;
+
+                Ok(())
+            })
+        };
+
+        assert_eq!(ret, 0);
+
+        mock::call_funct("rt-state.update", 1_000_000).unwrap();
+        assert_eq!(
+            mock::pin_value("rt-state.output"),
+            Some(mock::Value::U32(11))
+        );
+
+        unsafe { COMP.exit() };
+        assert_eq!(mock::component_id("rt-state"), None);
+    }
+
+    #[test]
+    fn setup_error() {
+        static COMP: crate::RtComponent<Pins> = crate::RtComponent::new();
+
+        let builder = HalComponentBuilder::new("rt-setup-error");
+
+        let ret = unsafe { COMP.init("rt-setup-error", Ok(builder), |_comp| Err("nope".into())) };
+
+        assert_eq!(ret, -(EINVAL as i32));
+        assert_eq!(mock::component_id("rt-setup-error"), None);
+    }
+
+    #[test]
+    fn invalid_params() {
+        static COMP: crate::RtComponent<Pins> = crate::RtComponent::new();
+
+        let builder = Err(ComponentInitError::Instances {
+            name: "rt-invalid-params".to_string(),
+            reason: "count= and names= are mutually exclusive".to_string(),
+        });
+
+        let ret = unsafe { COMP.init("rt-invalid-params", builder, |_comp| Ok(())) };
+
+        assert_eq!(ret, -(EINVAL as i32));
+        assert_eq!(mock::component_id("rt-invalid-params"), None);
+    }
+
+    /// Build a component from `count=` and `names=` parameters, returning its pin names
+    fn instance_pins(
+        name: &str,
+        count: c_int,
+        names: &[&str],
+    ) -> Result<Vec<String>, ComponentInitError> {
+        let names = names.iter().map(|name| name.to_string()).collect();
+
+        let builder = HalComponentBuilder::<Pins>::new(name);
+        let comp = rt_instances(builder, 2, count, names)?.build()?.ready()?;
+
+        let mut pins = mock::pin_names()
+            .into_iter()
+            .filter(|pin| {
+                comp.instances()
+                    .any(|i| pin.starts_with(&format!("{}.", i.name())))
+            })
+            .collect::<Vec<_>>();
+
+        pins.sort();
+
+        Ok(pins)
+    }
+
+    #[test]
+    fn instance_params() -> Result<(), ComponentInitError> {
+        assert_eq!(
+            instance_pins("rt-default-count", 0, &[])?,
+            vec!["rt-default-count.0.output", "rt-default-count.1.output"]
+        );
+        assert_eq!(
+            instance_pins("rt-count", 3, &[])?,
+            vec![
+                "rt-count.0.output",
+                "rt-count.1.output",
+                "rt-count.2.output"
+            ]
+        );
+        assert_eq!(
+            instance_pins("rt-names", 0, &["rt-name-x", "rt-name-y"])?,
+            vec!["rt-name-x.output", "rt-name-y.output"]
+        );
+
+        assert!(matches!(
+            instance_pins("rt-count-and-names", 1, &["rt-name-z"]),
+            Err(ComponentInitError::Instances { name, .. }) if name == "rt-count-and-names"
+        ));
+        assert!(matches!(
+            instance_pins("rt-negative-count", -1, &[]),
+            Err(ComponentInitError::Instances { .. })
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,234 @@
+//! Fixed rate loops for non-realtime components
+//!
+//! [`HalComponent::run_at`](crate::HalComponent::run_at) calls a closure once every period until
+//! the component is signalled to exit. Wakeups are scheduled against absolute deadlines from when
+//! the loop started, so unlike sleeping for the period at the end of each iteration, the loop
+//! doesn't drift by the time the closure takes to run.
+//!
+//! If an iteration finishes after the next one was due to start, it has overrun. The loop doesn't
+//! try to catch up by running the missed iterations back to back; the next iteration starts at the
+//! next deadline that hasn't passed yet.
+//!
+//! # Loop statistics
+//!
+//! Like the `.time` and `.tmax` parameters LinuxCNC exports for every realtime function, a
+//! component can export the timing of its loop by registering [`LoopStats`] with
+//! [`RegisterResources::register_loop_stats`](crate::RegisterResources::register_loop_stats) and
+//! returning it from [`Resources::loop_stats`](crate::Resources::loop_stats). This creates three
+//! parameters:
+//!
+//! | Parameter          | Type  | Direction | Description                                     |
+//! | ------------------ | ----- | --------- | ----------------------------------------------- |
+//! | `<comp>.loop-time` | `s32` | RO        | Time taken by the last iteration in nanoseconds |
+//! | `<comp>.loop-tmax` | `s32` | RW        | Longest `loop-time` seen, can be reset to zero  |
+//! | `<comp>.overruns`  | `u32` | RO        | Number of iterations that overran their period  |
+//!
+//! Times longer than `i32::MAX` nanoseconds (about 2.1 seconds) are saturated.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use linuxcnc_hal::{
+//!     hal_pin::{InputPin, OutputPin},
+//!     prelude::*,
+//!     run_loop::LoopStats,
+//!     HalComponent, Resources,
+//! };
+//! use std::{error::Error, time::Duration};
+//!
+//! #[derive(Resources)]
+//! struct Pins {
+//!     input: InputPin<f64>,
+//!     output: OutputPin<f64>,
+//!
+//!     /// Registers `rust-comp.loop-time`, `rust-comp.loop-tmax` and `rust-comp.overruns`
+//!     stats: LoopStats,
+//! }
+//!
+//! fn main() -> Result<(), Box<dyn Error>> {
+//!     let comp: HalComponent<Pins> = HalComponent::new("rust-comp")?;
+//!
+//!     // Runs every 10ms until the component receives `SIGINT` or `SIGTERM`
+//!     comp.run_at(Duration::from_millis(10), |pins, ctx| {
+//!         pins.output.set(pins.input.get() * 2.0);
+//!
+//!         if ctx.overruns() > 100 {
+//!             ctx.stop();
+//!         }
+//!
+//!         Ok::<_, Box<dyn Error>>(())
+//!     })?;
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::{error::ParameterRegisterError, Parameter, RegisterResources};
+use std::{
+    convert::TryFrom,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Timing parameters for a component's run loop
+///
+/// See the [module documentation](self) for the parameters this registers.
+#[derive(Debug)]
+pub struct LoopStats {
+    time: Parameter<i32>,
+    tmax: Parameter<i32>,
+    overruns: Parameter<u32>,
+}
+
+impl LoopStats {
+    /// Register the `loop-time`, `loop-tmax` and `overruns` parameters
+    pub(crate) fn register(comp: &RegisterResources) -> Result<Self, ParameterRegisterError> {
+        Ok(Self {
+            time: comp.register_readonly_parameter("loop-time")?,
+            tmax: comp.register_parameter("loop-tmax")?,
+            overruns: comp.register_readonly_parameter("overruns")?,
+        })
+    }
+
+    /// Get the time taken by the last iteration, in nanoseconds
+    pub fn time(&self) -> i32 {
+        self.time.get()
+    }
+
+    /// Get the longest time taken by an iteration since `loop-tmax` was last reset, in nanoseconds
+    pub fn tmax(&self) -> i32 {
+        self.tmax.get()
+    }
+
+    /// Get the number of iterations that overran their period
+    pub fn overruns(&self) -> u32 {
+        self.overruns.get()
+    }
+
+    /// Update the parameters after an iteration
+    fn record(&self, time: Duration, overran: bool) {
+        let time = i32::try_from(time.as_nanos()).unwrap_or(i32::MAX);
+
+        self.time.set(time);
+
+        if time > self.tmax.get() {
+            self.tmax.set(time);
+        }
+
+        if overran {
+            self.overruns.set(self.overruns.get().wrapping_add(1));
+        }
+    }
+}
+
+/// The state of a run loop, passed to the loop closure on every iteration
+#[derive(Debug, Clone)]
+pub struct LoopContext {
+    period: Duration,
+    deadline: Instant,
+    iteration: u64,
+    last_time: Duration,
+    overruns: u64,
+    stop: bool,
+}
+
+impl LoopContext {
+    /// Get the loop period
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Get the number of iterations run before this one
+    pub fn iteration(&self) -> u64 {
+        self.iteration
+    }
+
+    /// Get the time by which this iteration should finish, which is when the next one is due
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    /// Get the time taken by the previous iteration
+    ///
+    /// This is zero on the first iteration.
+    pub fn last_time(&self) -> Duration {
+        self.last_time
+    }
+
+    /// Get the number of iterations that overran their period since the loop started
+    pub fn overruns(&self) -> u64 {
+        self.overruns
+    }
+
+    /// Exit the loop once this iteration finishes
+    pub fn stop(&mut self) {
+        self.stop = true;
+    }
+}
+
+/// Call `f` every `period` until `should_exit` returns true, `f` returns an error or the loop is
+/// stopped with [`LoopContext::stop`]
+pub(crate) fn run<R, F, E>(
+    resources: &R,
+    stats: Option<&LoopStats>,
+    period: Duration,
+    should_exit: impl Fn() -> bool,
+    mut f: F,
+) -> Result<(), E>
+where
+    F: FnMut(&R, &mut LoopContext) -> Result<(), E>,
+{
+    assert!(
+        period > Duration::from_secs(0),
+        "Loop period must not be zero"
+    );
+
+    let mut ctx = LoopContext {
+        period,
+        deadline: Instant::now() + period,
+        iteration: 0,
+        last_time: Duration::from_secs(0),
+        overruns: 0,
+        stop: false,
+    };
+
+    while !should_exit() {
+        let start = Instant::now();
+
+        f(resources, &mut ctx)?;
+
+        let end = Instant::now();
+        let time = end - start;
+        let overran = end > ctx.deadline;
+
+        // Skip any deadlines that have already passed instead of running late iterations back to
+        // back
+        if overran {
+            let period_ns = period.as_nanos();
+            let behind_ns = (end - ctx.deadline).as_nanos();
+            let skip_ns = (behind_ns / period_ns + 1) * period_ns;
+
+            ctx.deadline += Duration::from_nanos(u64::try_from(skip_ns).unwrap_or(u64::MAX));
+            ctx.overruns += 1;
+        }
+
+        if let Some(stats) = stats {
+            stats.record(time, overran);
+        }
+
+        ctx.iteration += 1;
+        ctx.last_time = time;
+
+        if ctx.stop {
+            break;
+        }
+
+        if let Some(remaining) = ctx.deadline.checked_duration_since(Instant::now()) {
+            thread::sleep(remaining);
+        }
+
+        ctx.deadline += period;
+    }
+
+    Ok(())
+}
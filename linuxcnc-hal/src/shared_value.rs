@@ -0,0 +1,157 @@
+//! Access to values in HAL shared memory
+
+#[cfg(linuxcnc_hal_64bit)]
+use std::sync::atomic::AtomicI64;
+use std::{
+    cell::UnsafeCell,
+    fmt::Debug,
+    sync::atomic::{AtomicBool, AtomicI32, AtomicPtr, AtomicU32, AtomicU64, Ordering},
+};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// A value that can be stored in a pin or parameter
+///
+/// Pin and parameter values live in shared memory which other components, `halcmd` and other
+/// threads in this process may read and write at any time. Values are always copied in and out with
+/// relaxed atomic loads and stores, which compile to the same plain loads and stores as the
+/// `volatile` accesses used by LinuxCNC's C components but never cause a data race. This is what
+/// allows pins and parameters to be shared between threads.
+///
+/// This trait is sealed and is implemented for every type the HAL supports.
+///
+/// | Type   | Atomic type                   |
+/// | ------ | ----------------------------- |
+/// | `f64`  | `AtomicU64`, using `to_bits`  |
+/// | `u32`  | `AtomicU32`                   |
+/// | `i32`  | `AtomicI32`                   |
+/// | `bool` | `AtomicBool`                  |
+/// | `i64`  | `AtomicI64` (LinuxCNC 2.9+)   |
+/// | `u64`  | `AtomicU64` (LinuxCNC 2.9+)   |
+pub trait SharedValue: Debug + Copy + sealed::Sealed {
+    /// Read a value from shared memory
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be non-null and aligned, and point to memory that lives as long as the pin or
+    /// parameter.
+    #[doc(hidden)]
+    unsafe fn load(ptr: *const Self) -> Self;
+
+    /// Write a value to shared memory
+    ///
+    /// # Safety
+    ///
+    /// See [`SharedValue::load`].
+    #[doc(hidden)]
+    unsafe fn store(ptr: *mut Self, value: Self);
+
+    /// Write a value to shared memory, returning the previous value
+    ///
+    /// # Safety
+    ///
+    /// See [`SharedValue::load`].
+    #[doc(hidden)]
+    unsafe fn swap(ptr: *mut Self, value: Self) -> Self;
+
+    /// Values remembered by the edge detection methods of an [`InputPin`](crate::hal_pin::InputPin)
+    ///
+    /// Only `InputPin<bool>` detects edges, so pins of other types don't store anything.
+    #[doc(hidden)]
+    type Edges: Debug;
+}
+
+macro_rules! impl_shared_value {
+    ($type:ty, $atomic:ty, $edges:ty) => {
+        impl sealed::Sealed for $type {}
+
+        impl SharedValue for $type {
+            type Edges = $edges;
+
+            #[inline]
+            unsafe fn load(ptr: *const Self) -> Self {
+                (*(ptr as *const $atomic)).load(Ordering::Relaxed)
+            }
+
+            #[inline]
+            unsafe fn store(ptr: *mut Self, value: Self) {
+                (*(ptr as *const $atomic)).store(value, Ordering::Relaxed)
+            }
+
+            #[inline]
+            unsafe fn swap(ptr: *mut Self, value: Self) -> Self {
+                (*(ptr as *const $atomic)).swap(value, Ordering::Relaxed)
+            }
+        }
+    };
+}
+
+impl_shared_value!(u32, AtomicU32, ());
+impl_shared_value!(i32, AtomicI32, ());
+impl_shared_value!(bool, AtomicBool, [AtomicCell<bool>; 2]);
+#[cfg(linuxcnc_hal_64bit)]
+impl_shared_value!(u64, AtomicU64, ());
+#[cfg(linuxcnc_hal_64bit)]
+impl_shared_value!(i64, AtomicI64, ());
+
+impl sealed::Sealed for f64 {}
+
+impl SharedValue for f64 {
+    type Edges = ();
+
+    #[inline]
+    unsafe fn load(ptr: *const Self) -> Self {
+        f64::from_bits((*(ptr as *const AtomicU64)).load(Ordering::Relaxed))
+    }
+
+    #[inline]
+    unsafe fn store(ptr: *mut Self, value: Self) {
+        (*(ptr as *const AtomicU64)).store(value.to_bits(), Ordering::Relaxed)
+    }
+
+    #[inline]
+    unsafe fn swap(ptr: *mut Self, value: Self) -> Self {
+        f64::from_bits((*(ptr as *const AtomicU64)).swap(value.to_bits(), Ordering::Relaxed))
+    }
+}
+
+/// A value owned by a pin that is only accessed atomically
+///
+/// This is aligned so that 64 bit values can be accessed as `AtomicU64` on 32 bit targets.
+///
+/// This is only public because it's used in [`SharedValue::Edges`], and can't be named outside the
+/// crate.
+#[doc(hidden)]
+#[repr(align(8))]
+#[derive(Debug)]
+pub struct AtomicCell<S>(UnsafeCell<S>);
+
+impl<S> AtomicCell<S>
+where
+    S: SharedValue,
+{
+    pub(crate) fn new(value: S) -> Self {
+        Self(UnsafeCell::new(value))
+    }
+
+    /// Store a new value, returning the previous one
+    #[inline]
+    pub(crate) fn swap(&self, value: S) -> S {
+        unsafe { S::swap(self.0.get(), value) }
+    }
+}
+
+/// Read the address of a pin's value from the pointer the HAL stores it in
+///
+/// The HAL changes this pointer when the pin is linked to or unlinked from a signal, which may
+/// happen on another thread.
+///
+/// # Safety
+///
+/// `cell` must be non-null, aligned and live as long as the pin.
+#[inline]
+pub(crate) unsafe fn load_ptr<T>(cell: *mut *mut T) -> *mut T {
+    (*(cell as *const AtomicPtr<T>)).load(Ordering::Relaxed)
+}
@@ -0,0 +1,360 @@
+use crate::{
+    error::ComponentInitError,
+    hal_parameter::HalParameter,
+    hal_pin::{HalPin, PinRead},
+    mock::{self, Value},
+    HalComponent, Resources,
+};
+use std::{cell::Cell, convert::TryFrom, fmt::Debug, time::Duration};
+
+/// A bench to test component logic against the mock HAL
+///
+/// Only available with the `mock` feature enabled.
+///
+/// The test bench creates a [`HalComponent`] with the given [`Resources`], then allows a test to
+/// set the values of input and bidirectional pins, run the component logic for a number of cycles
+/// and check the resulting values of output pins and parameters.
+///
+/// Values are set and read through the mock HAL in the same way LinuxCNC would, so the component
+/// logic under test uses the same pins and methods as it does in production.
+///
+/// The default simulated period is 1ms, which matches a typical servo thread. This can be changed
+/// with [`HalTestBench::with_period`].
+///
+/// # Examples
+///
+/// ```rust
+/// use linuxcnc_hal::{
+///     error::{ResourcesError, StorageError},
+///     hal_pin::{InputPin, OutputPin},
+///     prelude::*,
+///     HalTestBench, Parameter, RegisterResources, Resources,
+/// };
+/// use std::time::Duration;
+///
+/// struct Comp {
+///     input: InputPin<f64>,
+///     output: OutputPin<f64>,
+///     gain: Parameter<f64>,
+/// }
+///
+/// impl Resources for Comp {
+///     type RegisterError = ResourcesError;
+///
+///     fn register_resources(comp: &RegisterResources) -> Result<Self, Self::RegisterError> {
+///         Ok(Comp {
+///             input: comp.register_pin::<InputPin<f64>>("input")?,
+///             output: comp.register_pin::<OutputPin<f64>>("output")?,
+///             gain: comp.register_parameter::<Parameter<f64>>("gain")?,
+///         })
+///     }
+/// }
+///
+/// /// The component logic, called once per cycle
+/// fn scale(comp: &Comp, _period: Duration) -> Result<(), StorageError> {
+///     comp.output.set(comp.input.get() * comp.gain.get());
+///
+///     Ok(())
+/// }
+///
+/// let bench = HalTestBench::<Comp>::new("bench-scale").unwrap();
+/// let comp = bench.resources();
+///
+/// bench.set_parameter(&comp.gain, 2.5);
+/// bench.set_pin(&comp.input, 4.0);
+///
+/// bench.step(10, scale).unwrap();
+///
+/// bench.assert_pin(&comp.output, 10.0);
+/// assert_eq!(bench.elapsed(), Duration::from_millis(10));
+/// ```
+#[derive(Debug)]
+pub struct HalTestBench<R> {
+    /// The component under test
+    comp: HalComponent<R>,
+
+    /// Simulated period of each cycle
+    period: Duration,
+
+    /// Number of cycles run so far
+    cycles: Cell<u64>,
+}
+
+impl<R> HalTestBench<R>
+where
+    R: Resources,
+{
+    /// Create a new test bench with a component of the given name
+    ///
+    /// The component name must be unique within the test binary as tests are run in parallel.
+    pub fn new(name: impl Into<String>) -> Result<Self, ComponentInitError> {
+        let comp = HalComponent::new(name)?;
+
+        Ok(Self {
+            comp,
+            period: Duration::from_millis(1),
+            cycles: Cell::new(0),
+        })
+    }
+
+    /// Set the simulated period passed to the component logic on each cycle
+    pub fn with_period(self, period: Duration) -> Self {
+        Self { period, ..self }
+    }
+
+    /// Get a reference to the component under test
+    pub fn component(&self) -> &HalComponent<R> {
+        &self.comp
+    }
+
+    /// Get a reference to the component's resources
+    pub fn resources(&self) -> &R {
+        self.comp.resources()
+    }
+
+    /// Get the number of cycles run so far
+    pub fn cycles(&self) -> u64 {
+        self.cycles.get()
+    }
+
+    /// Get the total simulated time of all cycles run so far
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.period.as_nanos() as u64 * self.cycles())
+    }
+
+    /// Run the component logic for the given number of cycles
+    ///
+    /// The logic is called with the component's resources and the simulated period. If it returns
+    /// an error, stepping stops and the error is returned.
+    pub fn step<F, E>(&self, cycles: u64, mut logic: F) -> Result<(), E>
+    where
+        F: FnMut(&R, Duration) -> Result<(), E>,
+    {
+        for _ in 0..cycles {
+            logic(self.comp.resources(), self.period)?;
+
+            self.cycles.set(self.cycles() + 1);
+        }
+
+        Ok(())
+    }
+
+    /// Call an exported function for the given number of cycles, as if it were added to a
+    /// realtime thread with the simulated period
+    ///
+    /// The name is given without the component prefix, as passed to
+    /// [`HalFunction::new`](crate::HalFunction::new).
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the component did not export a function with the given name.
+    #[track_caller]
+    pub fn step_function(&self, name: &str, cycles: u64) {
+        let full_name = format!("{}.{}", self.comp.name(), name);
+
+        for _ in 0..cycles {
+            if let Err(e) = mock::call_funct(&full_name, self.period.as_nanos() as i64) {
+                panic!("Failed to call function {}: {}", full_name, e);
+            }
+
+            self.cycles.set(self.cycles() + 1);
+        }
+    }
+
+    /// Set the value of an input or bidirectional pin, as if it were driven by another component
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the pin is not registered with the mock HAL.
+    #[track_caller]
+    pub fn set_pin<P>(&self, pin: &P, value: P::Storage)
+    where
+        P: PinRead,
+        P::Storage: Into<Value>,
+    {
+        if let Err(e) = mock::set_pin_value(pin.name(), value) {
+            panic!("Failed to set pin {}: {}", pin.name(), e);
+        }
+    }
+
+    /// Get the current value of a pin
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the pin is not registered with the mock HAL.
+    #[track_caller]
+    pub fn pin<P>(&self, pin: &P) -> P::Storage
+    where
+        P: HalPin,
+        P::Storage: TryFrom<Value>,
+    {
+        mock::pin_value(pin.name())
+            .and_then(|value| P::Storage::try_from(value).ok())
+            .unwrap_or_else(|| panic!("Pin {} is not registered", pin.name()))
+    }
+
+    /// Set the value of a parameter, as if it were set with `setp`
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the parameter is read only or is not registered with the mock HAL.
+    #[track_caller]
+    pub fn set_parameter<P>(&self, parameter: &P, value: P::Storage)
+    where
+        P: HalParameter,
+        P::Storage: Into<Value>,
+    {
+        if let Err(e) = mock::set_param_value(parameter.name(), value) {
+            panic!("Failed to set parameter {}: {}", parameter.name(), e);
+        }
+    }
+
+    /// Get the current value of a parameter
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the parameter is not registered with the mock HAL.
+    #[track_caller]
+    pub fn parameter<P>(&self, parameter: &P) -> P::Storage
+    where
+        P: HalParameter,
+        P::Storage: TryFrom<Value>,
+    {
+        mock::param_value(parameter.name())
+            .and_then(|value| P::Storage::try_from(value).ok())
+            .unwrap_or_else(|| panic!("Parameter {} is not registered", parameter.name()))
+    }
+
+    /// Assert that a pin has the expected value
+    #[track_caller]
+    pub fn assert_pin<P>(&self, pin: &P, expected: P::Storage)
+    where
+        P: HalPin,
+        P::Storage: TryFrom<Value> + PartialEq + Debug,
+    {
+        let value = self.pin(pin);
+
+        assert_eq!(
+            value,
+            expected,
+            "Pin {} has unexpected value after {} cycles",
+            pin.name(),
+            self.cycles()
+        );
+    }
+
+    /// Assert that a parameter has the expected value
+    #[track_caller]
+    pub fn assert_parameter<P>(&self, parameter: &P, expected: P::Storage)
+    where
+        P: HalParameter,
+        P::Storage: TryFrom<Value> + PartialEq + Debug,
+    {
+        let value = self.parameter(parameter);
+
+        assert_eq!(
+            value,
+            expected,
+            "Parameter {} has unexpected value after {} cycles",
+            parameter.name(),
+            self.cycles()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::{ResourcesError, StorageError},
+        hal_pin::{BidirectionalPin, InputPin},
+        prelude::*,
+        HalFunction, Parameter, RegisterResources,
+    };
+
+    struct Counter {
+        enable: InputPin<bool>,
+        reset: BidirectionalPin<bool>,
+        count: BidirectionalPin<u32>,
+        step: Parameter<u32>,
+    }
+
+    impl Resources for Counter {
+        type RegisterError = ResourcesError;
+
+        fn register_resources(comp: &RegisterResources) -> Result<Self, Self::RegisterError> {
+            Ok(Self {
+                enable: comp.register_pin("enable")?,
+                reset: comp.register_pin("reset")?,
+                count: comp.register_pin("count")?,
+                step: comp.register_parameter("step")?,
+            })
+        }
+
+        fn functions() -> Vec<HalFunction<Self>> {
+            vec![HalFunction::new("count", |pins, period| {
+                count(pins, period).unwrap()
+            })]
+        }
+    }
+
+    fn count(pins: &Counter, _period: Duration) -> Result<(), StorageError> {
+        if pins.reset.get() {
+            pins.reset.set(false);
+            pins.count.set(0);
+        } else if pins.enable.get() {
+            let count = pins.count.get() + pins.step.get();
+
+            pins.count.set(count);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn step_counter() -> Result<(), Box<dyn std::error::Error>> {
+        let bench = HalTestBench::<Counter>::new("bench-counter")?;
+        let pins = bench.resources();
+
+        bench.set_parameter(&pins.step, 2);
+        bench.step(5, count)?;
+        bench.assert_pin(&pins.count, 0);
+
+        bench.set_pin(&pins.enable, true);
+        bench.step(5, count)?;
+        bench.assert_pin(&pins.count, 10);
+
+        bench.set_pin(&pins.reset, true);
+        bench.step(1, count)?;
+        bench.assert_pin(&pins.count, 0);
+        bench.assert_pin(&pins.reset, false);
+
+        assert_eq!(bench.cycles(), 11);
+        assert_eq!(bench.elapsed(), Duration::from_millis(11));
+
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "Pin bench-assert.count has unexpected value")]
+    fn assert_pin_fails() {
+        let bench = HalTestBench::<Counter>::new("bench-assert").unwrap();
+
+        bench.assert_pin(&bench.resources().count, 1);
+    }
+
+    #[test]
+    fn step_function() {
+        let bench = HalTestBench::<Counter>::new("bench-function").unwrap();
+        let pins = bench.resources();
+
+        assert!(mock::funct_names().contains(&"bench-function.count".to_string()));
+
+        bench.set_parameter(&pins.step, 3);
+        bench.set_pin(&pins.enable, true);
+        bench.step_function("count", 4);
+
+        bench.assert_pin(&pins.count, 12);
+        assert_eq!(bench.cycles(), 4);
+    }
+}